@@ -42,19 +42,8 @@ public class Runtime : RuntimeRefMut, IDisposable
     public Runtime(
         string path,
         SettingsMap settingsMap,
-        StateDelegate state,
-        IndexDelegate index,
-        SegmentSplittedDelegate segmentSplitted,
-        Action start,
-        Action split,
-        Action skipSplit,
-        Action undoSplit,
-        Action reset,
-        SetGameTimeDelegate setGameTime,
-        Action pauseGameTime,
-        Action resumeGameTime,
-        SetCustomVariableDelegate setCustomVariable,
-        LogDelegate log
+        TimerVTable timerVTable,
+        IntPtr timerUserData
     ) : base(IntPtr.Zero)
     {
         IntPtr settingsMapPtr = settingsMap?.ptr ?? IntPtr.Zero;
@@ -66,19 +55,8 @@ public class Runtime : RuntimeRefMut, IDisposable
         ptr = ASRNative.Runtime_new(
             path,
             settingsMapPtr,
-            state,
-            index,
-            segmentSplitted,
-            start,
-            split,
-            skipSplit,
-            undoSplit,
-            reset,
-            setGameTime,
-            pauseGameTime,
-            resumeGameTime,
-            setCustomVariable,
-            log
+            ref timerVTable,
+            timerUserData
         );
         if (ptr == IntPtr.Zero)
         {
@@ -97,6 +75,16 @@ public class Runtime : RuntimeRefMut, IDisposable
         return ASRNative.Runtime_step(ptr);
     }
 
+    public void SetLogLevel(uint level)
+    {
+        if (ptr == IntPtr.Zero)
+        {
+            return;
+        }
+
+        ASRNative.Runtime_set_log_level(ptr, level);
+    }
+
     public TimeSpan TickRate()
     {
         if (ptr == IntPtr.Zero)
@@ -208,7 +196,7 @@ public class SettingsMapRef
             return "";
         }
 
-        return ASRNative.SettingsMap_get_key(ptr, (UIntPtr)index);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.SettingsMap_get_key_utf16(ptr, (UIntPtr)index, buf, cap));
     }
     public SettingValueRef GetValue(ulong index)
     {
@@ -217,7 +205,15 @@ public class SettingsMapRef
             return null;
         }
 
-        return new SettingValueRef(ASRNative.SettingsMap_get_value(ptr, (UIntPtr)index));
+        IntPtr valuePtr = ASRNative.SettingsMap_get_value(ptr, (UIntPtr)index);
+        if (valuePtr != IntPtr.Zero)
+        {
+            return new SettingValueRef(valuePtr);
+        }
+        else
+        {
+            return null;
+        }
     }
     public SettingValueRef KeyGetValue(string key)
     {
@@ -312,7 +308,15 @@ public class SettingsListRef
             return null;
         }
 
-        return new SettingValueRef(ASRNative.SettingsList_get(ptr, (UIntPtr)index));
+        IntPtr valuePtr = ASRNative.SettingsList_get(ptr, (UIntPtr)index);
+        if (valuePtr != IntPtr.Zero)
+        {
+            return new SettingValueRef(valuePtr);
+        }
+        else
+        {
+            return null;
+        }
     }
 }
 
@@ -400,7 +404,15 @@ public class SettingValueRef
             return null;
         }
 
-        return new SettingsMapRef(ASRNative.SettingValue_get_map(ptr));
+        IntPtr mapPtr = ASRNative.SettingValue_get_map(ptr);
+        if (mapPtr != IntPtr.Zero)
+        {
+            return new SettingsMapRef(mapPtr);
+        }
+        else
+        {
+            return null;
+        }
     }
     public SettingsListRef GetList()
     {
@@ -409,7 +421,15 @@ public class SettingValueRef
             return null;
         }
 
-        return new SettingsListRef(ASRNative.SettingValue_get_list(ptr));
+        IntPtr listPtr = ASRNative.SettingValue_get_list(ptr);
+        if (listPtr != IntPtr.Zero)
+        {
+            return new SettingsListRef(listPtr);
+        }
+        else
+        {
+            return null;
+        }
     }
     public bool GetBool()
     {
@@ -445,7 +465,7 @@ public class SettingValueRef
             return "";
         }
 
-        return ASRNative.SettingValue_get_string(ptr);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.SettingValue_get_string_utf16(ptr, buf, cap));
     }
 }
 
@@ -563,7 +583,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_key(ptr, (UIntPtr)index);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_key_utf16(ptr, (UIntPtr)index, buf, cap));
     }
 
     public string GetDescription(ulong index)
@@ -573,7 +593,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_description(ptr, (UIntPtr)index);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_description_utf16(ptr, (UIntPtr)index, buf, cap));
     }
 
     public string GetTooltip(ulong index)
@@ -583,7 +603,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_tooltip(ptr, (UIntPtr)index);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_tooltip_utf16(ptr, (UIntPtr)index, buf, cap));
     }
 
     public uint GetHeadingLevel(ulong index)
@@ -661,7 +681,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_choice_option_key(ptr, (UIntPtr)index, (UIntPtr)optionIndex);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_choice_option_key_utf16(ptr, (UIntPtr)index, (UIntPtr)optionIndex, buf, cap));
     }
 
     public string GetChoiceOptionDescription(ulong index, ulong optionIndex)
@@ -671,7 +691,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_choice_option_description(ptr, (UIntPtr)index, (UIntPtr)optionIndex);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_choice_option_description_utf16(ptr, (UIntPtr)index, (UIntPtr)optionIndex, buf, cap));
     }
 
     public string GetFileSelectFilter(ulong index)
@@ -681,7 +701,7 @@ public class WidgetsRef
             return "";
         }
 
-        return ASRNative.Widgets_get_file_select_filter(ptr, (UIntPtr)index);
+        return ASRString.FromUtf16((buf, cap) => ASRNative.Widgets_get_file_select_filter_utf16(ptr, (UIntPtr)index, buf, cap));
     }
 }
 
@@ -712,12 +732,34 @@ public class Widgets : WidgetsRefMut, IDisposable
     internal Widgets(IntPtr ptr) : base(ptr) { }
 }
 
-public delegate int StateDelegate();
-public delegate int IndexDelegate();
-public delegate int SegmentSplittedDelegate(int idx);
-public delegate void SetGameTimeDelegate(long gameTime);
-public delegate void SetCustomVariableDelegate(IntPtr namePtr, UIntPtr nameLen, IntPtr valuePtr, UIntPtr valueLen);
-public delegate void LogDelegate(IntPtr messagePtr, UIntPtr messageLen);
+public delegate int StateDelegate(IntPtr userData);
+public delegate int IndexDelegate(IntPtr userData);
+public delegate int SegmentSplittedDelegate(IntPtr userData, int idx);
+public delegate void TimerActionDelegate(IntPtr userData);
+public delegate void SetGameTimeDelegate(IntPtr userData, long gameTime);
+public delegate void SetCustomVariableDelegate(IntPtr userData, IntPtr namePtr, UIntPtr nameLen, IntPtr valuePtr, UIntPtr valueLen);
+public delegate void LogDelegate(IntPtr userData, IntPtr messagePtr, UIntPtr messageLen);
+public delegate void LogLeveledDelegate(IntPtr userData, uint source, uint level, IntPtr messagePtr, UIntPtr messageLen);
+
+[StructLayout(LayoutKind.Sequential)]
+public struct TimerVTable
+{
+    public UIntPtr size;
+    public StateDelegate state;
+    public IndexDelegate index;
+    public SegmentSplittedDelegate segmentSplitted;
+    public TimerActionDelegate start;
+    public TimerActionDelegate split;
+    public TimerActionDelegate skipSplit;
+    public TimerActionDelegate undoSplit;
+    public TimerActionDelegate reset;
+    public SetGameTimeDelegate setGameTime;
+    public TimerActionDelegate pauseGameTime;
+    public TimerActionDelegate resumeGameTime;
+    public SetCustomVariableDelegate setCustomVariable;
+    public LogDelegate log;
+    public LogLeveledDelegate logLeveled;
+}
 
 public static class ASRNative
 {
@@ -725,25 +767,16 @@ public static class ASRNative
     public static extern IntPtr Runtime_new(
         ASRString path,
         IntPtr settings_map,
-        StateDelegate state,
-        IndexDelegate index,
-        SegmentSplittedDelegate segmentSplitted,
-        Action start,
-        Action split,
-        Action skipSplit,
-        Action undoSplit,
-        Action reset,
-        SetGameTimeDelegate set_game_time,
-        Action pause_game_time,
-        Action resume_game_time,
-        SetCustomVariableDelegate set_custom_variable,
-        LogDelegate log
+        ref TimerVTable timer_vtable,
+        IntPtr timer_user_data
     );
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern void Runtime_drop(IntPtr self);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern bool Runtime_step(IntPtr self);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern void Runtime_set_log_level(IntPtr self, uint level);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ulong Runtime_tick_rate(IntPtr self);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern IntPtr Runtime_get_settings_widgets(IntPtr self);
@@ -769,6 +802,8 @@ public static class ASRNative
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString SettingsMap_get_key(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr SettingsMap_get_key_utf16(IntPtr self, UIntPtr index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern IntPtr SettingsMap_get_value(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern IntPtr SettingsMap_get_value_by_key(IntPtr self, ASRString key);
@@ -812,6 +847,8 @@ public static class ASRNative
     public static extern double SettingValue_get_f64(IntPtr self);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString SettingValue_get_string(IntPtr self);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr SettingValue_get_string_utf16(IntPtr self, IntPtr buf, UIntPtr cap);
 
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern void Widgets_drop(IntPtr self);
@@ -820,10 +857,16 @@ public static class ASRNative
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_key(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_key_utf16(IntPtr self, UIntPtr index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_description(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_description_utf16(IntPtr self, UIntPtr index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_tooltip(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_tooltip_utf16(IntPtr self, UIntPtr index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern uint Widgets_get_heading_level(IntPtr self, UIntPtr index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern UIntPtr Widgets_get_type(IntPtr self, UIntPtr index);
@@ -836,9 +879,15 @@ public static class ASRNative
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_choice_option_key(IntPtr self, UIntPtr index, UIntPtr option_index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_choice_option_key_utf16(IntPtr self, UIntPtr index, UIntPtr option_index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_choice_option_description(IntPtr self, UIntPtr index, UIntPtr option_index);
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_choice_option_description_utf16(IntPtr self, UIntPtr index, UIntPtr option_index, IntPtr buf, UIntPtr cap);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern ASRString Widgets_get_file_select_filter(IntPtr self, UIntPtr index);
+    [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
+    public static extern UIntPtr Widgets_get_file_select_filter_utf16(IntPtr self, UIntPtr index, IntPtr buf, UIntPtr cap);
 
     [DllImport("asr_capi", CallingConvention = CallingConvention.Cdecl)]
     public static extern UIntPtr get_buf_len();
@@ -889,6 +938,42 @@ public class ASRString : SafeHandle
         return FromPtrLen(asrString.handle, ASRNative.get_buf_len());
     }
 
+    // Reads a string through one of the native functions with a `_utf16`
+    // suffix, which write into a buffer owned by the caller and return the
+    // length the buffer needs to have. Short strings are read into a buffer
+    // on the stack, longer ones are read again into a large enough buffer.
+    public static string FromUtf16(Func<IntPtr, UIntPtr, UIntPtr> read)
+    {
+        const int StackCapacity = 256;
+
+        unsafe
+        {
+            char* stackBuf = stackalloc char[StackCapacity];
+            ulong len = (ulong)read((IntPtr)stackBuf, (UIntPtr)StackCapacity);
+            if (len < StackCapacity)
+            {
+                return new string(stackBuf, 0, (int)len);
+            }
+
+            if (len >= int.MaxValue)
+            {
+                return null;
+            }
+
+            char[] heapBuf = new char[len + 1];
+            fixed (char* buf = heapBuf)
+            {
+                len = (ulong)read((IntPtr)buf, (UIntPtr)heapBuf.Length);
+                if (len >= (ulong)heapBuf.Length)
+                {
+                    return null;
+                }
+
+                return new string(buf, 0, (int)len);
+            }
+        }
+    }
+
     protected override bool ReleaseHandle()
     {
         if (needToFree)
@@ -2,6 +2,7 @@
 using System.Drawing;
 using System.Globalization;
 using System.IO;
+using System.Runtime.InteropServices;
 using System.Windows.Forms;
 using System.Xml;
 
@@ -33,24 +34,31 @@ public partial class ComponentSettings : UserControl
     public SettingsMap previousMap = null;
     public Widgets previousWidgets = null;
 
-    private static readonly LogDelegate log = (messagePtr, messageLen) =>
+    private static readonly LogDelegate log = (_, messagePtr, messageLen) =>
     {
         string message = ASRString.FromPtrLen(messagePtr, messageLen);
         Log.Info($"[Auto Splitting Runtime] {message}");
     };
 
-    private readonly StateDelegate getState;
-    private readonly IndexDelegate getIndex;
-    private readonly SegmentSplittedDelegate segmentSplitted;
-    private readonly Action start;
-    private readonly Action split;
-    private readonly Action skipSplit;
-    private readonly Action undoSplit;
-    private readonly Action reset;
-    private readonly SetGameTimeDelegate setGameTime;
-    private readonly Action pauseGameTime;
-    private readonly Action resumeGameTime;
-    private readonly SetCustomVariableDelegate setCustomVariable;
+    private static readonly LogLeveledDelegate logLeveled = (_, source, level, messagePtr, messageLen) =>
+    {
+        string message = ASRString.FromPtrLen(messagePtr, messageLen);
+        string prefix = source == 0 ? "[Auto Splitter]" : "[Auto Splitting Runtime]";
+        switch (level)
+        {
+            case >= 4:
+                Log.Error($"{prefix} {message}");
+                break;
+            case 3:
+                Log.Warning($"{prefix} {message}");
+                break;
+            default:
+                Log.Info($"{prefix} {message}");
+                break;
+        }
+    };
+
+    private readonly TimerVTable timerVTable;
 
     public ComponentSettings(TimerModel model)
     {
@@ -61,40 +69,46 @@ public partial class ComponentSettings : UserControl
         txtScriptPath.DataBindings.Add("Text", this, "ScriptPath", false,
             DataSourceUpdateMode.OnPropertyChanged);
 
-        getState = () =>
+        timerVTable = new TimerVTable
         {
-            return model.CurrentState.CurrentPhase switch
+            size = (UIntPtr)Marshal.SizeOf<TimerVTable>(),
+            state = (_) =>
             {
-                TimerPhase.NotRunning => 0,
-                TimerPhase.Running => 1,
-                TimerPhase.Paused => 2,
-                TimerPhase.Ended => 3,
-                _ => 0,
-            };
-        };
-        getIndex = () => model.CurrentState.CurrentSplitIndex;
-        segmentSplitted = (idx) =>
-        {
-            if (!(0 <= idx && idx < model.CurrentState.CurrentSplitIndex))
+                return model.CurrentState.CurrentPhase switch
+                {
+                    TimerPhase.NotRunning => 0,
+                    TimerPhase.Running => 1,
+                    TimerPhase.Paused => 2,
+                    TimerPhase.Ended => 3,
+                    _ => 0,
+                };
+            },
+            index = (_) => model.CurrentState.CurrentSplitIndex,
+            segmentSplitted = (_, idx) =>
             {
-                return -1;
-            }
+                if (!(0 <= idx && idx < model.CurrentState.CurrentSplitIndex))
+                {
+                    return -1;
+                }
 
-            return model.CurrentState.Run[idx].SplitTime.RealTime != null ? 1 : 0;
-        };
-        start = model.Start;
-        split = model.Split;
-        skipSplit = model.SkipSplit;
-        undoSplit = model.UndoSplit;
-        reset = model.Reset;
-        setGameTime = (ticks) => model.CurrentState.SetGameTime(new TimeSpan(ticks));
-        pauseGameTime = () => model.CurrentState.IsGameTimePaused = true;
-        resumeGameTime = () => model.CurrentState.IsGameTimePaused = false;
-        setCustomVariable = (namePtr, nameLen, valuePtr, valueLen) =>
-        {
-            string name = ASRString.FromPtrLen(namePtr, nameLen);
-            string value = ASRString.FromPtrLen(valuePtr, valueLen);
-            model.CurrentState.Run.Metadata.SetCustomVariable(name, value);
+                return model.CurrentState.Run[idx].SplitTime.RealTime != null ? 1 : 0;
+            },
+            start = (_) => model.Start(),
+            split = (_) => model.Split(),
+            skipSplit = (_) => model.SkipSplit(),
+            undoSplit = (_) => model.UndoSplit(),
+            reset = (_) => model.Reset(),
+            setGameTime = (_, ticks) => model.CurrentState.SetGameTime(new TimeSpan(ticks)),
+            pauseGameTime = (_) => model.CurrentState.IsGameTimePaused = true,
+            resumeGameTime = (_) => model.CurrentState.IsGameTimePaused = false,
+            setCustomVariable = (_, namePtr, nameLen, valuePtr, valueLen) =>
+            {
+                string name = ASRString.FromPtrLen(namePtr, nameLen);
+                string value = ASRString.FromPtrLen(valuePtr, valueLen);
+                model.CurrentState.Run.Metadata.SetCustomVariable(name, value);
+            },
+            log = log,
+            logLeveled = logLeveled,
         };
     }
 
@@ -127,19 +141,8 @@ public partial class ComponentSettings : UserControl
                 runtime = new Runtime(
                     ScriptPath,
                     settingsMap,
-                    getState,
-                    getIndex,
-                    segmentSplitted,
-                    start,
-                    split,
-                    skipSplit,
-                    undoSplit,
-                    reset,
-                    setGameTime,
-                    pauseGameTime,
-                    resumeGameTime,
-                    setCustomVariable,
-                    log
+                    timerVTable,
+                    IntPtr.Zero
                 );
             }
         }
@@ -0,0 +1,13 @@
+//! Runs auto splitters on behalf of builds of `asr-capi` for targets other than
+//! 64-bit ones. It is spawned by them and isn't meant to be started manually.
+
+#[cfg(target_pointer_width = "64")]
+fn main() {
+    asr_capi::run_helper();
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn main() {
+    eprintln!("The helper needs to be built for a 64-bit target.");
+    std::process::exit(1);
+}
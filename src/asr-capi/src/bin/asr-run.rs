@@ -0,0 +1,463 @@
+//! Runs an auto splitter without LiveSplit. The auto splitter is stepped at its
+//! own tick rate against a simulated timer, and everything it does to the
+//! timer is printed to the standard output, alongside its log messages.
+//!
+//! ```text
+//! asr-run <auto-splitter.wasm> [options]
+//! ```
+//!
+//! The runner is only built with the `testing` feature enabled, for example
+//! with `cargo run --features testing --bin asr-run`.
+//!
+//! The settings file consists of `key = value` lines. Values of `true` and
+//! `false` are stored as bools, numbers as integers or floats, and everything
+//! else as strings. Strings can be quoted to prevent them from being parsed as
+//! anything else. Empty lines and lines starting with `#` are ignored.
+//!
+//! With `--replay`, the auto splitter is instead run against a recording made
+//! with `Runtime_start_recording`. The recorded state of the timer is fed back
+//! to the auto splitter as fast as possible and every tick at which its actions
+//! differ from the recorded ones is printed. The runner then fails if there are
+//! any differences, so regressions between versions of an auto splitter can be
+//! caught automatically.
+
+#[cfg(target_pointer_width = "64")]
+fn main() {
+    if let Err(err) = run::main() {
+        eprintln!("error: {err:?}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn main() {
+    eprintln!("The runner needs to be built for a 64-bit target.");
+    std::process::exit(1);
+}
+
+#[cfg(target_pointer_width = "64")]
+mod run {
+    use anyhow::{bail, Context};
+    use asr_capi::{CTimer, MockTimer, MockTimerState, Recording, Replay, TimerEvent, TimerVTable};
+    use livesplit_auto_splitting::{settings, AutoSplitter, Config, LogLevel, Runtime, Timer};
+    use std::{
+        ffi::c_void,
+        fmt, fs,
+        path::{Path, PathBuf},
+        slice,
+        sync::OnceLock,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const USAGE: &str = "\
+Usage: asr-run <auto-splitter.wasm> [options]
+
+Options:
+  --settings <file>    Loads the settings of the auto splitter from the file.
+  --segments <count>   The amount of segments of the simulated splits. The run
+                       ends once all of them are split. Defaults to 1000.
+  --duration <secs>    Stops after the amount of seconds provided.
+  --max-steps <count>  Stops after the amount of steps provided.
+  --replay <file>      Replays the recording to the auto splitter and reports
+                       where its actions differ from the recorded ones.
+  --log-level <level>  The minimum level of the printed log messages: trace,
+                       debug, info, warning or error. Messages of the auto
+                       splitter are info messages. Defaults to info.
+  --debug-info         Compiles the auto splitter with debug information.
+  --no-optimize        Compiles the auto splitter without optimizations.
+  --fail-fast          Stops with an error once a step of the auto splitter
+                       fails.
+  -h, --help           Prints this message.";
+
+    struct Options {
+        module: PathBuf,
+        settings: Option<PathBuf>,
+        segments: usize,
+        duration: Option<Duration>,
+        max_steps: Option<u64>,
+        replay: Option<PathBuf>,
+        log_level: LogLevel,
+        debug_info: bool,
+        optimize: bool,
+        fail_fast: bool,
+    }
+
+    fn parse_args() -> anyhow::Result<Options> {
+        let mut args = std::env::args_os().skip(1);
+        let mut module = None;
+        let mut options = Options {
+            module: PathBuf::new(),
+            settings: None,
+            segments: 1000,
+            duration: None,
+            max_steps: None,
+            replay: None,
+            log_level: LogLevel::Info,
+            debug_info: false,
+            optimize: true,
+            fail_fast: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .with_context(|| format!("{name} expects a value."))
+            };
+            match arg.to_str() {
+                Some("-h" | "--help") => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                Some("--settings") => options.settings = Some(value("--settings")?.into()),
+                Some("--segments") => {
+                    options.segments = value("--segments")?
+                        .parse()
+                        .context("--segments expects a number.")?;
+                }
+                Some("--duration") => {
+                    let secs: f64 = value("--duration")?
+                        .parse()
+                        .context("--duration expects a number of seconds.")?;
+                    options.duration = Some(
+                        Duration::try_from_secs_f64(secs)
+                            .context("--duration expects a number of seconds.")?,
+                    );
+                }
+                Some("--max-steps") => {
+                    options.max_steps = Some(
+                        value("--max-steps")?
+                            .parse()
+                            .context("--max-steps expects a number.")?,
+                    );
+                }
+                Some("--replay") => options.replay = Some(value("--replay")?.into()),
+                Some("--log-level") => {
+                    options.log_level = match &*value("--log-level")? {
+                        "trace" => LogLevel::Trace,
+                        "debug" => LogLevel::Debug,
+                        "info" => LogLevel::Info,
+                        "warning" => LogLevel::Warning,
+                        "error" => LogLevel::Error,
+                        level => bail!("Unknown log level: {level}"),
+                    };
+                }
+                Some("--debug-info") => options.debug_info = true,
+                Some("--no-optimize") => options.optimize = false,
+                Some("--fail-fast") => options.fail_fast = true,
+                Some(arg) if arg.starts_with('-') => bail!("Unknown option: {arg}\n\n{USAGE}"),
+                _ if module.is_none() => module = Some(PathBuf::from(arg)),
+                _ => bail!("Only a single auto splitter can be run.\n\n{USAGE}"),
+            }
+        }
+
+        options.module =
+            module.with_context(|| format!("No auto splitter provided.\n\n{USAGE}"))?;
+        Ok(options)
+    }
+
+    /// Parses the settings file described in the documentation of the runner.
+    fn parse_settings(source: &str) -> anyhow::Result<settings::Map> {
+        let mut map = settings::Map::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .with_context(|| format!("Line {} is missing a `=`.", index + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty() {
+                bail!("Line {} is missing a key.", index + 1);
+            }
+            map.insert(key.into(), parse_value(value));
+        }
+        Ok(map)
+    }
+
+    fn parse_value(value: &str) -> settings::Value {
+        if let Some(quoted) = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+        {
+            return settings::Value::String(quoted.into());
+        }
+        match value {
+            "true" => settings::Value::Bool(true),
+            "false" => settings::Value::Bool(false),
+            _ => {
+                if let Ok(value) = value.parse() {
+                    settings::Value::I64(value)
+                } else if let Some(value) = value
+                    .parse()
+                    .ok()
+                    .filter(|_| value.bytes().any(|b| b.is_ascii_digit()))
+                {
+                    // Words like `inf` and `nan` are kept as strings.
+                    settings::Value::F64(value)
+                } else {
+                    settings::Value::String(value.into())
+                }
+            }
+        }
+    }
+
+    /// When the runner started, which the printed messages are relative to.
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+
+    fn print(message: fmt::Arguments<'_>) {
+        let elapsed = STARTED.get_or_init(Instant::now).elapsed();
+        println!("[{:>10.3}] {message}", elapsed.as_secs_f64());
+    }
+
+    fn print_event(event: &TimerEvent) {
+        const TICKS_PER_SEC: f64 = 10_000_000.0;
+
+        match event {
+            TimerEvent::Start => print(format_args!("start")),
+            TimerEvent::Split => print(format_args!("split")),
+            TimerEvent::SkipSplit => print(format_args!("skip split")),
+            TimerEvent::UndoSplit => print(format_args!("undo split")),
+            TimerEvent::Reset => print(format_args!("reset")),
+            TimerEvent::SetGameTime(ticks) => print(format_args!(
+                "game time {:.3}",
+                *ticks as f64 / TICKS_PER_SEC
+            )),
+            TimerEvent::PauseGameTime => print(format_args!("pause game time")),
+            TimerEvent::ResumeGameTime => print(format_args!("resume game time")),
+            TimerEvent::SetVariable(key, value) => print(format_args!("variable {key} = {value}")),
+            TimerEvent::Log(message) => print(format_args!("log: {message}")),
+        }
+    }
+
+    /// Prints the log messages that pass the log level of the runner.
+    unsafe extern "C" fn print_log(
+        _: *mut c_void,
+        source: u32,
+        level: u32,
+        message_ptr: *const u8,
+        message_len: usize,
+    ) {
+        // The runtime passes its messages as string slices.
+        let message = String::from_utf8_lossy(slice::from_raw_parts(message_ptr, message_len));
+        match source {
+            0 => print(format_args!("log: {message}")),
+            _ => print(format_args!("runtime {}: {message}", level_name(level))),
+        }
+    }
+
+    fn level_name(level: u32) -> &'static str {
+        match level {
+            0 => "trace",
+            1 => "debug",
+            2 => "info",
+            3 => "warning",
+            _ => "error",
+        }
+    }
+
+    /// Creates a timer that forwards to the callbacks of the timer provided,
+    /// except for the log messages, which are printed.
+    ///
+    /// # Safety
+    /// The user data needs to outlive the timer.
+    unsafe fn printing_timer(
+        vtable: &TimerVTable,
+        user_data: *mut c_void,
+        options: &Options,
+    ) -> CTimer {
+        let vtable = TimerVTable {
+            log_leveled: Some(print_log),
+            ..*vtable
+        };
+        CTimer::from_vtable(&vtable, user_data, options.log_level)
+    }
+
+    fn load<T: Timer>(
+        options: &Options,
+        module: &[u8],
+        settings_map: Option<settings::Map>,
+        timer: T,
+    ) -> anyhow::Result<AutoSplitter<T>> {
+        let mut config = Config::default();
+        config.debug_info = options.debug_info;
+        config.optimize = options.optimize;
+
+        Runtime::new(config)
+            .context("Failed creating the runtime.")?
+            .compile(module)
+            .context("Failed compiling the auto splitter.")?
+            .instantiate(timer, settings_map, Some(&options.module))
+            .context("Failed instantiating the auto splitter.")
+    }
+
+    /// Runs the auto splitter once for every tick of the recording and reports
+    /// where its actions differ from the recorded ones.
+    fn replay(
+        options: &Options,
+        module: &[u8],
+        settings_map: Option<settings::Map>,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let recording = Recording::load(path)
+            .with_context(|| format!("Failed reading the recording {}.", path.display()))?;
+        let replay = Replay::new(recording);
+
+        // SAFETY: The replay outlives the auto splitter.
+        let timer = unsafe { printing_timer(Replay::vtable(), replay.user_data(), options) };
+        let auto_splitter = load(options, module, settings_map, timer)?;
+
+        while replay.begin_step().is_some() {
+            if let Err(err) = auto_splitter.lock().update() {
+                let err = err.context("Failed executing the auto splitter.");
+                if options.fail_fast {
+                    return Err(err);
+                }
+                eprintln!("error: {err:?}");
+            }
+        }
+
+        let divergences = replay.divergences();
+        for divergence in &divergences {
+            println!(
+                "[{:>10.3}] tick {}: expected {:?}, got {:?}",
+                divergence.wall_time.as_secs_f64(),
+                divergence.tick,
+                divergence.expected,
+                divergence.actual,
+            );
+        }
+        if !divergences.is_empty() {
+            bail!(
+                "The auto splitter diverged from the recording in {} ticks.",
+                divergences.len()
+            );
+        }
+        println!("The auto splitter matches the recording.");
+        Ok(())
+    }
+
+    pub fn main() -> anyhow::Result<()> {
+        STARTED.get_or_init(Instant::now);
+        let options = parse_args()?;
+
+        let module = fs::read(&options.module)
+            .with_context(|| format!("Failed reading the file {}.", options.module.display()))?;
+
+        let settings_map = match &options.settings {
+            Some(path) => {
+                let source = fs::read_to_string(path)
+                    .with_context(|| format!("Failed reading the settings {}.", path.display()))?;
+                Some(parse_settings(&source).context("Failed parsing the settings.")?)
+            }
+            None => None,
+        };
+
+        if let Some(path) = &options.replay {
+            return replay(&options, &module, settings_map, path);
+        }
+
+        let mock_timer = MockTimer::new(options.segments);
+        // SAFETY: The mock timer outlives the auto splitter.
+        let timer =
+            unsafe { printing_timer(MockTimer::vtable(), mock_timer.user_data(), &options) };
+        let auto_splitter = load(&options, &module, settings_map, timer)?;
+
+        let started = Instant::now();
+        let mut next_tick = started;
+        let mut steps = 0;
+        loop {
+            if options
+                .max_steps
+                .is_some_and(|max_steps| steps >= max_steps)
+                || options
+                    .duration
+                    .is_some_and(|duration| started.elapsed() >= duration)
+            {
+                return Ok(());
+            }
+
+            mock_timer.begin_step();
+            let had_ended = mock_timer.state() == MockTimerState::Ended;
+            let result = auto_splitter.lock().update();
+            for (_, event) in mock_timer.take_events() {
+                print_event(&event);
+            }
+            if !had_ended && mock_timer.state() == MockTimerState::Ended {
+                print(format_args!("run ended"));
+            }
+            if let Err(err) = result {
+                let err = err.context("Failed executing the auto splitter.");
+                if options.fail_fast {
+                    return Err(err);
+                }
+                eprintln!("error: {err:?}");
+            }
+            steps += 1;
+
+            next_tick += auto_splitter.tick_rate();
+            let now = Instant::now();
+            if next_tick < now {
+                // We fell behind, so don't try to catch up on the missed ticks.
+                next_tick = now;
+            }
+            thread::sleep(next_tick - now);
+        }
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_values() {
+            assert!(matches!(parse_value("true"), settings::Value::Bool(true)));
+            assert!(matches!(parse_value("false"), settings::Value::Bool(false)));
+            assert!(matches!(parse_value("-3"), settings::Value::I64(-3)));
+            assert!(matches!(parse_value("1.5"), settings::Value::F64(v) if v == 1.5));
+            assert!(matches!(parse_value("1e3"), settings::Value::F64(v) if v == 1e3));
+            assert!(matches!(parse_value("word"), settings::Value::String(v) if &*v == "word"));
+        }
+
+        #[test]
+        fn keeps_words_parsed_as_floats_as_strings() {
+            for word in ["inf", "-inf", "infinity", "nan", "NaN"] {
+                assert!(matches!(parse_value(word), settings::Value::String(v) if &*v == word));
+            }
+        }
+
+        #[test]
+        fn keeps_quoted_values_as_strings() {
+            assert!(matches!(parse_value("\"true\""), settings::Value::String(v) if &*v == "true"));
+            assert!(matches!(parse_value("\"42\""), settings::Value::String(v) if &*v == "42"));
+            assert!(matches!(parse_value("\" a \""), settings::Value::String(v) if &*v == " a "));
+            assert!(matches!(parse_value("\"\""), settings::Value::String(v) if v.is_empty()));
+            // A lone quote isn't a quoted string.
+            assert!(matches!(parse_value("\""), settings::Value::String(v) if &*v == "\""));
+        }
+
+        #[test]
+        fn parses_settings() {
+            let map = parse_settings(
+                "# Comment\n\
+                 \n\
+                 \x20 count = 3 \n\
+                 name = \"a = b\"\n\
+                 ratio=0.5\n",
+            )
+            .unwrap();
+
+            assert_eq!(map.len(), 3);
+            assert!(matches!(map.get("count"), Some(settings::Value::I64(3))));
+            assert!(matches!(map.get("name"), Some(settings::Value::String(v)) if &**v == "a = b"));
+            assert!(matches!(map.get("ratio"), Some(&settings::Value::F64(v)) if v == 0.5));
+        }
+
+        #[test]
+        fn rejects_invalid_settings() {
+            assert!(parse_settings("missing").is_err());
+            assert!(parse_settings(" = value").is_err());
+        }
+    }
+}
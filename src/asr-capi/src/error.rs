@@ -0,0 +1,180 @@
+use crate::{output_into, output_str};
+use std::cell::RefCell;
+
+/// The category of an error reported by the runtime. The discriminants are
+/// stable and are what `Runtime_last_error_kind` returns.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorKind {
+    /// The file of the auto splitter couldn't be read.
+    FileRead = 1,
+    /// The auto splitter is not a valid WebAssembly module.
+    Compile = 2,
+    /// The auto splitter couldn't be instantiated. This for example happens
+    /// when it imports functions that this runtime doesn't provide, because it
+    /// was built for a newer runtime.
+    Instantiate = 3,
+    /// The auto splitter trapped while it was executing.
+    Trap = 4,
+    /// The auto splitter got interrupted while it was executing.
+    Interrupted = 5,
+    /// The engine for running the auto splitter couldn't be created.
+    RuntimeCreation = 6,
+    /// An argument passed by the host is invalid.
+    InvalidInput = 7,
+    /// The helper process that runs the auto splitter on behalf of 32-bit
+    /// hosts couldn't be started or stopped responding.
+    Helper = 8,
+    /// A recording of the interactions with the timer couldn't be written.
+    Recording = 9,
+    /// There is no setting at the path provided.
+    SettingNotFound = 10,
+    /// A setting path passes through a setting that is neither a map nor a
+    /// list, or it indexes a list with something other than an index. This is
+    /// also reported when a setting value or a widget is queried for something
+    /// its type doesn't have.
+    SettingTypeMismatch = 11,
+    /// A setting path indexes a list beyond its end, or an index passed to a
+    /// getter is out of bounds.
+    SettingIndexOutOfRange = 12,
+}
+
+impl ErrorKind {
+    /// Returns the error kind with the discriminant provided, like the ones
+    /// returned by `Runtime_last_error_kind`.
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        Some(match kind {
+            1 => Self::FileRead,
+            2 => Self::Compile,
+            3 => Self::Instantiate,
+            4 => Self::Trap,
+            5 => Self::Interrupted,
+            6 => Self::RuntimeCreation,
+            7 => Self::InvalidInput,
+            8 => Self::Helper,
+            9 => Self::Recording,
+            10 => Self::SettingNotFound,
+            11 => Self::SettingTypeMismatch,
+            12 => Self::SettingIndexOutOfRange,
+            _ => return None,
+        })
+    }
+}
+
+struct LastError {
+    kind: ErrorKind,
+    chain: Vec<Box<str>>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Stores the error as the last error of the current thread.
+pub fn set_last_error(kind: ErrorKind, error: &anyhow::Error) {
+    set_last_error_chain(kind, error.chain().map(|e| e.to_string().into()).collect());
+}
+
+/// Stores the chain of error messages as the last error of the current
+/// thread, with the outermost message first.
+pub fn set_last_error_chain(kind: ErrorKind, chain: Vec<Box<str>>) {
+    LAST_ERROR.with_borrow_mut(|last_error| *last_error = Some(LastError { kind, chain }));
+}
+
+/// Stores the error of a getter being called with an index that is out of
+/// bounds for the amount of elements provided.
+pub fn set_index_out_of_range(index: usize, len: usize) {
+    set_last_error(
+        ErrorKind::SettingIndexOutOfRange,
+        &anyhow::anyhow!("The index {index} is out of bounds for {len} elements."),
+    );
+}
+
+/// Stores the error of a getter being called on a setting value or a widget
+/// that is not of the type provided.
+pub fn set_type_mismatch(expected: &str) {
+    set_last_error(
+        ErrorKind::SettingTypeMismatch,
+        &anyhow::anyhow!("The setting is not {expected}."),
+    );
+}
+
+/// Returns a copy of the last error of the current thread.
+#[cfg(target_pointer_width = "64")]
+pub fn last_error() -> Option<(ErrorKind, Vec<Box<str>>)> {
+    LAST_ERROR.with_borrow(|last_error| last_error.as_ref().map(|e| (e.kind, e.chain.clone())))
+}
+
+/// Returns the kind of the last error that occurred on the current thread, or 0
+/// if no error occurred so far. Errors that occur on the step thread are only
+/// reported through the log.
+#[no_mangle]
+pub extern "C" fn Runtime_last_error_kind() -> u32 {
+    LAST_ERROR.with_borrow(|last_error| last_error.as_ref().map_or(0, |e| e.kind as u32))
+}
+
+/// Returns the message of the last error that occurred on the current thread.
+/// This is the outermost message of the error chain.
+#[no_mangle]
+pub extern "C" fn Runtime_last_error_message() -> *const u8 {
+    Runtime_last_error_chain_get(0)
+}
+
+/// Like `Runtime_last_error_message`, but writes the message into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// message doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `buf` must be valid for writes of `cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_last_error_message_into(buf: *mut u8, cap: usize) -> usize {
+    Runtime_last_error_chain_get_into(0, buf, cap)
+}
+
+/// Returns the amount of messages in the chain of causes of the last error that
+/// occurred on the current thread.
+#[no_mangle]
+pub extern "C" fn Runtime_last_error_chain_len() -> usize {
+    LAST_ERROR.with_borrow(|last_error| last_error.as_ref().map_or(0, |e| e.chain.len()))
+}
+
+/// Returns the message at the index provided in the chain of causes of the last
+/// error that occurred on the current thread. The outermost message comes
+/// first, followed by the messages of the errors that caused it. Returns an
+/// empty string if the index is out of bounds.
+#[no_mangle]
+pub extern "C" fn Runtime_last_error_chain_get(_index: usize) -> *const u8 {
+    with_chain_message(_index, output_str)
+}
+
+/// Like `Runtime_last_error_chain_get`, but writes the message into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// message doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_last_error_chain_get_into(
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    with_chain_message(_index, |m| output_into(m.as_bytes(), _buf, _cap))
+}
+
+fn with_chain_message<R>(index: usize, f: impl FnOnce(&str) -> R) -> R {
+    LAST_ERROR.with_borrow(|last_error| {
+        f(last_error
+            .as_ref()
+            .and_then(|e| e.chain.get(index))
+            .map_or("", |m| m))
+    })
+}
+
+/// Clears the last error of the current thread.
+#[no_mangle]
+pub extern "C" fn Runtime_clear_last_error() {
+    LAST_ERROR.with_borrow_mut(|last_error| *last_error = None);
+}
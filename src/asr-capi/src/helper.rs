@@ -0,0 +1,429 @@
+//! The helper process that runs auto splitters on behalf of builds of this
+//! library for targets other than 64-bit ones, see `remote.rs` for the other
+//! side. Requests are read from the standard input and the responses, timer
+//! callbacks and log messages are written to the standard output. Every helper
+//! hosts at most a single auto splitter. Until the client handled a callback,
+//! the requests it sends in the meantime are handled right away.
+
+use crate::{
+    error::{last_error, ErrorKind},
+    ipc::{self, Callback, Decoder, Encoder, Message, RemoteError, Request, Response},
+    log,
+    runtime::{
+        Runtime, Runtime_get_settings_map, Runtime_get_settings_widgets, Runtime_interrupt,
+        Runtime_new_from_bytes_with_config, Runtime_set_settings_map, Runtime_set_step_budget,
+        Runtime_step, Runtime_tick_rate, Runtime_was_interrupted,
+    },
+    runtime_config::{
+        RuntimeConfig, RuntimeConfig_set_backtrace_details, RuntimeConfig_set_debug_info,
+        RuntimeConfig_set_optimize,
+    },
+    settings::Widget,
+    settings_map::SettingsMap,
+    timer::{lossy_str, TimerVTable},
+};
+use livesplit_auto_splitting::wasi_path;
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    io,
+    path::Path,
+    sync::{mpsc, Arc, OnceLock},
+    thread,
+};
+
+/// Runs the helper until its standard input gets closed.
+pub fn run() {
+    let runtime = Arc::new(OnceLock::new());
+    let (requests, received_requests) = mpsc::channel();
+
+    // Interrupts need to be handled while the auto splitter is running, so the
+    // requests are read on a separate thread.
+    {
+        let runtime = runtime.clone();
+        thread::spawn(move || read_requests(&runtime, requests));
+    }
+
+    // The timer callbacks may be called for as long as the process exists.
+    let host: &'static Host = Box::leak(Box::new(Host {
+        requests: received_requests,
+        runtime,
+        widgets: RefCell::new(None),
+        settings_map: RefCell::new(None),
+        generation: Cell::new(0),
+        busy: Cell::new(false),
+    }));
+
+    while let Ok(request) = host.requests.recv() {
+        if let Request::TimerReply(_) = request {
+            continue;
+        }
+        if host.respond(request).is_err() {
+            break;
+        }
+    }
+}
+
+fn read_requests(runtime: &OnceLock<Box<Runtime>>, requests: mpsc::Sender<Request>) {
+    let mut stdin = io::stdin().lock();
+    let mut buf = Vec::new();
+    while ipc::read_frame(&mut stdin, &mut buf, ipc::MAX_REQUEST_LEN).is_ok() {
+        let Ok(request) = Decoder::new(&buf).request() else {
+            break;
+        };
+        match request {
+            Request::Interrupt => {
+                if let Some(runtime) = runtime.get() {
+                    Runtime_interrupt(runtime);
+                }
+            }
+            request => {
+                if requests.send(request).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn send(message: &Message) -> io::Result<()> {
+    let mut encoder = Encoder::new();
+    encoder.message(message);
+    ipc::write_frame(io::stdout().lock(), &encoder.finish())
+}
+
+/// The user data of the timer callbacks. It's only used by the thread that
+/// handles the requests.
+struct Host {
+    requests: mpsc::Receiver<Request>,
+    runtime: Arc<OnceLock<Box<Runtime>>>,
+    /// The widgets most recently sent to the client and their generation.
+    widgets: RefCell<Option<(u64, Arc<Vec<Widget>>)>>,
+    /// The settings map most recently sent to the client and its generation.
+    settings_map: RefCell<Option<(u64, SettingsMap)>>,
+    generation: Cell<u64>,
+    /// Whether the auto splitter is being loaded or stepped, so the client
+    /// can't do either from within a callback.
+    busy: Cell<bool>,
+}
+
+impl Host {
+    fn respond(&self, request: Request) -> io::Result<()> {
+        let response = match request {
+            Request::Create { .. } | Request::Step if self.busy.get() => {
+                Response::Err(RemoteError {
+                    kind: ErrorKind::InvalidInput,
+                    chain: vec![
+                        "The auto splitter can't be loaded or stepped from within a callback."
+                            .into(),
+                    ],
+                })
+            }
+            request => self.handle(request),
+        };
+        send(&Message::Response(response))
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::Create {
+                debug_info,
+                optimize,
+                backtrace_details,
+                module,
+                settings_map,
+            } => self.create(
+                debug_info,
+                optimize,
+                backtrace_details,
+                &module,
+                settings_map,
+            ),
+            Request::PathToWasi(path) => {
+                Response::String(wasi_path::from_native(Path::new(&*path)).unwrap_or_default())
+            }
+            Request::WasiToPath(path) => Response::String(
+                wasi_path::to_native(&path, true)
+                    .and_then(|path| path.into_os_string().into_string().ok())
+                    .unwrap_or_default()
+                    .into(),
+            ),
+            request => {
+                let Some(runtime) = self.runtime.get() else {
+                    return Response::Err(RemoteError {
+                        kind: ErrorKind::InvalidInput,
+                        chain: vec!["The auto splitter is not loaded.".into()],
+                    });
+                };
+                match request {
+                    Request::Step => {
+                        self.busy.set(true);
+                        let stepped = Runtime_step(runtime);
+                        self.busy.set(false);
+                        let error = if stepped { None } else { take_error() };
+                        Response::Stepped {
+                            error,
+                            interrupted: Runtime_was_interrupted(runtime),
+                        }
+                    }
+                    Request::SetStepBudget(ticks) => {
+                        Runtime_set_step_budget(runtime, ticks);
+                        Response::Ok
+                    }
+                    Request::TickRate => Response::TickRate(Runtime_tick_rate(runtime)),
+                    Request::GetSettingsWidgets { known_generation } => {
+                        let widgets = Runtime_get_settings_widgets(runtime).inner;
+                        let mut cached = self.widgets.borrow_mut();
+                        let generation = match &*cached {
+                            Some((generation, old)) if Arc::ptr_eq(old, &widgets) => *generation,
+                            _ => {
+                                let generation = self.next_generation();
+                                *cached = Some((generation, widgets.clone()));
+                                generation
+                            }
+                        };
+                        Response::SettingsWidgets {
+                            generation,
+                            widgets: (generation != known_generation).then(|| (*widgets).clone()),
+                        }
+                    }
+                    Request::GetSettingsMap { known_generation } => {
+                        let map = *Runtime_get_settings_map(runtime);
+                        let generation = self.remember_settings_map(map.clone());
+                        Response::SettingsMap {
+                            generation,
+                            map: (generation != known_generation).then_some(map),
+                        }
+                    }
+                    Request::SetSettingsMap(map) => {
+                        Runtime_set_settings_map(runtime, Box::new(map));
+                        let map = *Runtime_get_settings_map(runtime);
+                        Response::Generation(self.remember_settings_map(map))
+                    }
+                    Request::SetSetting { key, value } => {
+                        runtime.update_setting(key, Some(value));
+                        Response::Ok
+                    }
+                    Request::RemoveSetting(key) => {
+                        runtime.update_setting(key, None);
+                        Response::Ok
+                    }
+                    Request::SetSettingAtPath { path, value } => {
+                        if runtime.set_setting_at_path(&path, value) {
+                            Response::Ok
+                        } else {
+                            Response::Err(take_error().unwrap_or(RemoteError {
+                                kind: ErrorKind::InvalidInput,
+                                chain: vec!["Failed setting the setting.".into()],
+                            }))
+                        }
+                    }
+                    _ => Response::Err(RemoteError {
+                        kind: ErrorKind::InvalidInput,
+                        chain: vec!["The request is not supported.".into()],
+                    }),
+                }
+            }
+        }
+    }
+
+    fn create(
+        &self,
+        debug_info: Option<bool>,
+        optimize: Option<bool>,
+        backtrace_details: Option<bool>,
+        module: &[u8],
+        settings_map: Option<SettingsMap>,
+    ) -> Response {
+        if self.runtime.get().is_some() {
+            return Response::Err(RemoteError {
+                kind: ErrorKind::InvalidInput,
+                chain: vec!["The helper already hosts an auto splitter.".into()],
+            });
+        }
+
+        let mut config = RuntimeConfig::default();
+        if let Some(value) = debug_info {
+            RuntimeConfig_set_debug_info(&mut config, value);
+        }
+        if let Some(value) = optimize {
+            RuntimeConfig_set_optimize(&mut config, value);
+        }
+        if let Some(value) = backtrace_details {
+            RuntimeConfig_set_backtrace_details(&mut config, value);
+        }
+
+        let vtable = TimerVTable {
+            size: std::mem::size_of::<TimerVTable>(),
+            state,
+            index,
+            segment_splitted,
+            start,
+            split,
+            skip_split,
+            undo_split,
+            reset,
+            set_game_time,
+            pause_game_time,
+            resume_game_time,
+            set_custom_variable,
+            log,
+            log_leveled: Some(log_leveled),
+        };
+
+        // SAFETY: The vtable's callbacks and the host live for as long as the
+        // process does.
+        self.busy.set(true);
+        let runtime = unsafe {
+            Runtime_new_from_bytes_with_config(
+                module.as_ptr(),
+                module.len(),
+                settings_map.map(Box::new),
+                &config,
+                &vtable,
+                self as *const Host as *mut c_void,
+            )
+        };
+        self.busy.set(false);
+
+        match runtime {
+            Some(runtime) => {
+                let _ = self.runtime.set(runtime);
+                Response::Ok
+            }
+            None => Response::Err(take_error().unwrap_or(RemoteError {
+                kind: ErrorKind::Helper,
+                chain: vec!["Failed loading the auto splitter.".into()],
+            })),
+        }
+    }
+
+    /// Returns the generation of the settings map, which changes whenever the
+    /// map is not the one that was seen last.
+    fn remember_settings_map(&self, map: SettingsMap) -> u64 {
+        let mut cached = self.settings_map.borrow_mut();
+        match &*cached {
+            Some((generation, old)) if map.is_unchanged(old) => *generation,
+            _ => {
+                let generation = self.next_generation();
+                *cached = Some((generation, map));
+                generation
+            }
+        }
+    }
+
+    fn next_generation(&self) -> u64 {
+        self.generation.set(self.generation.get() + 1);
+        self.generation.get()
+    }
+}
+
+fn take_error() -> Option<RemoteError> {
+    last_error().map(|(kind, chain)| RemoteError { kind, chain })
+}
+
+/// Sends the callback to the client and waits for its reply, handling the
+/// requests the client sends while handling the callback. If the client is
+/// gone, 0 is returned.
+unsafe fn query(user_data: *mut c_void, callback: Callback) -> i32 {
+    let host = &*(user_data as *const Host);
+    if send(&Message::Callback(callback)).is_err() {
+        return 0;
+    }
+    while let Ok(request) = host.requests.recv() {
+        match request {
+            Request::TimerReply(reply) => return reply,
+            request => {
+                if host.respond(request).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    0
+}
+
+unsafe fn notify(user_data: *mut c_void, callback: Callback) {
+    query(user_data, callback);
+}
+
+unsafe extern "C" fn state(user_data: *mut c_void) -> i32 {
+    query(user_data, Callback::State)
+}
+
+unsafe extern "C" fn index(user_data: *mut c_void) -> i32 {
+    query(user_data, Callback::Index)
+}
+
+unsafe extern "C" fn segment_splitted(user_data: *mut c_void, index: i32) -> i32 {
+    query(user_data, Callback::SegmentSplitted(index))
+}
+
+unsafe extern "C" fn start(user_data: *mut c_void) {
+    notify(user_data, Callback::Start);
+}
+
+unsafe extern "C" fn split(user_data: *mut c_void) {
+    notify(user_data, Callback::Split);
+}
+
+unsafe extern "C" fn skip_split(user_data: *mut c_void) {
+    notify(user_data, Callback::SkipSplit);
+}
+
+unsafe extern "C" fn undo_split(user_data: *mut c_void) {
+    notify(user_data, Callback::UndoSplit);
+}
+
+unsafe extern "C" fn reset(user_data: *mut c_void) {
+    notify(user_data, Callback::Reset);
+}
+
+unsafe extern "C" fn set_game_time(user_data: *mut c_void, ticks: i64) {
+    notify(user_data, Callback::SetGameTime(ticks));
+}
+
+unsafe extern "C" fn pause_game_time(user_data: *mut c_void) {
+    notify(user_data, Callback::PauseGameTime);
+}
+
+unsafe extern "C" fn resume_game_time(user_data: *mut c_void) {
+    notify(user_data, Callback::ResumeGameTime);
+}
+
+unsafe extern "C" fn set_custom_variable(
+    user_data: *mut c_void,
+    name_ptr: *const u8,
+    name_len: usize,
+    value_ptr: *const u8,
+    value_len: usize,
+) {
+    notify(
+        user_data,
+        Callback::SetCustomVariable(
+            lossy_str(name_ptr, name_len),
+            lossy_str(value_ptr, value_len),
+        ),
+    );
+}
+
+unsafe extern "C" fn log(user_data: *mut c_void, message_ptr: *const u8, message_len: usize) {
+    log::log_unleveled(log_leveled, user_data, message_ptr, message_len);
+}
+
+unsafe extern "C" fn log_leveled(
+    user_data: *mut c_void,
+    source: u32,
+    level: u32,
+    message_ptr: *const u8,
+    message_len: usize,
+) {
+    notify(
+        user_data,
+        Callback::Log {
+            source,
+            level,
+            message: lossy_str(message_ptr, message_len),
+        },
+    );
+}
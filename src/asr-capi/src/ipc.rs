@@ -0,0 +1,1072 @@
+//! The protocol spoken between the C API and the helper process that runs the
+//! auto splitter out of process. Every message is framed by its length as a
+//! little endian `u32`, followed by the encoded message. The helper serves a
+//! single client and handles one request at a time. While handling a request
+//! it may send any number of callbacks, before finishing with a response.
+//! Every callback expects a `TimerReply` once the client handled it. Until then
+//! the helper handles the requests that the client sends while handling the
+//! callback, so the client may call back into the runtime. Interrupts are
+//! accepted at any time.
+
+use crate::{
+    error::ErrorKind,
+    setting_value::SettingValue,
+    settings::{ChoiceOption, FileFilter, Widget, WidgetKind},
+    settings_list::SettingsList,
+    settings_map::SettingsMap,
+};
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+/// Messages of the helper larger than this are considered a protocol
+/// violation, so a corrupt or hostile length doesn't make the host attempt a
+/// huge allocation. They only carry settings, paths and log messages.
+pub const MAX_MESSAGE_LEN: usize = 16 << 20;
+
+/// Requests larger than this are considered a protocol violation. They are
+/// allowed to be larger than messages, as they carry the module of the auto
+/// splitter, and only the 64-bit helper reads them.
+pub const MAX_REQUEST_LEN: usize = 1 << 30;
+
+pub enum Request {
+    Create {
+        debug_info: Option<bool>,
+        optimize: Option<bool>,
+        backtrace_details: Option<bool>,
+        module: Vec<u8>,
+        settings_map: Option<SettingsMap>,
+    },
+    Step,
+    Interrupt,
+    SetStepBudget(u64),
+    TickRate,
+    /// Requests the settings widgets, unless they are still the ones with the
+    /// generation provided.
+    GetSettingsWidgets {
+        known_generation: u64,
+    },
+    /// Requests the settings map, unless it is still the one with the
+    /// generation provided.
+    GetSettingsMap {
+        known_generation: u64,
+    },
+    SetSettingsMap(SettingsMap),
+    SetSetting {
+        key: Arc<str>,
+        value: SettingValue,
+    },
+    RemoveSetting(Arc<str>),
+    SetSettingAtPath {
+        path: Box<str>,
+        value: SettingValue,
+    },
+    PathToWasi(Box<str>),
+    WasiToPath(Box<str>),
+    /// Tells the helper that the client handled the callback, along with the
+    /// result of a timer query.
+    TimerReply(i32),
+}
+
+pub enum Callback {
+    State,
+    Index,
+    SegmentSplitted(i32),
+    Start,
+    Split,
+    SkipSplit,
+    UndoSplit,
+    Reset,
+    SetGameTime(i64),
+    PauseGameTime,
+    ResumeGameTime,
+    SetCustomVariable(Box<str>, Box<str>),
+    Log {
+        source: u32,
+        level: u32,
+        message: Box<str>,
+    },
+}
+
+pub enum Response {
+    Ok,
+    Err(RemoteError),
+    Stepped {
+        error: Option<RemoteError>,
+        interrupted: bool,
+    },
+    TickRate(u64),
+    SettingsWidgets {
+        generation: u64,
+        widgets: Option<Vec<Widget>>,
+    },
+    SettingsMap {
+        generation: u64,
+        map: Option<SettingsMap>,
+    },
+    Generation(u64),
+    String(Box<str>),
+}
+
+/// A message sent by the helper.
+pub enum Message {
+    Callback(Callback),
+    Response(Response),
+}
+
+/// An error that occurred in the helper, as stored as its last error.
+pub struct RemoteError {
+    pub kind: ErrorKind,
+    pub chain: Vec<Box<str>>,
+}
+
+pub fn write_frame(mut writer: impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len()).map_err(|_| invalid_data())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Reads the next frame into the buffer. Frames longer than the maximum length
+/// provided and frames that there's not enough memory for are treated as
+/// malformed.
+pub fn read_frame(mut reader: impl Read, buf: &mut Vec<u8>, max_len: usize) -> io::Result<()> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > max_len {
+        return Err(invalid_data());
+    }
+    buf.clear();
+    buf.try_reserve_exact(len).map_err(|_| invalid_data())?;
+    buf.resize(len, 0);
+    reader.read_exact(buf)
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed message.")
+}
+
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i64(&mut self, v: i64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn usize(&mut self, v: usize) {
+        self.u64(v as u64);
+    }
+
+    fn bytes(&mut self, v: &[u8]) {
+        self.usize(v.len());
+        self.buf.extend_from_slice(v);
+    }
+
+    fn str(&mut self, v: &str) {
+        self.bytes(v.as_bytes());
+    }
+
+    fn opt_bool(&mut self, v: Option<bool>) {
+        self.u8(match v {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        });
+    }
+
+    fn opt_str(&mut self, v: Option<&str>) {
+        self.bool(v.is_some());
+        if let Some(v) = v {
+            self.str(v);
+        }
+    }
+
+    fn error(&mut self, error: &RemoteError) {
+        self.u32(error.kind as u32);
+        self.usize(error.chain.len());
+        for message in &error.chain {
+            self.str(message);
+        }
+    }
+
+    fn map(&mut self, map: &SettingsMap) {
+        self.usize(map.len());
+        let mut index = 0;
+        while let Some((key, value)) = map.get_by_index(index) {
+            self.str(key);
+            self.value(value);
+            index += 1;
+        }
+    }
+
+    fn list(&mut self, list: &SettingsList) {
+        self.usize(list.len());
+        let mut index = 0;
+        while let Some(value) = list.get(index) {
+            self.value(value);
+            index += 1;
+        }
+    }
+
+    fn value(&mut self, value: &SettingValue) {
+        #[allow(unreachable_patterns)]
+        match value {
+            SettingValue::Map(v) => {
+                self.u8(1);
+                self.map(v);
+            }
+            SettingValue::List(v) => {
+                self.u8(2);
+                self.list(v);
+            }
+            SettingValue::Bool(v) => {
+                self.u8(3);
+                self.bool(*v);
+            }
+            SettingValue::I64(v) => {
+                self.u8(4);
+                self.i64(*v);
+            }
+            SettingValue::F64(v) => {
+                self.u8(5);
+                self.f64(*v);
+            }
+            SettingValue::String(v) => {
+                self.u8(6);
+                self.str(v);
+            }
+            // Values that the protocol doesn't know about can't be
+            // represented on the other side, so they are dropped.
+            _ => self.u8(0),
+        }
+    }
+
+    fn widget(&mut self, widget: &Widget) {
+        self.str(&widget.key);
+        self.str(&widget.description);
+        self.opt_str(widget.tooltip.as_deref());
+        match &widget.kind {
+            WidgetKind::Title { heading_level } => {
+                self.u8(0);
+                self.u32(*heading_level);
+            }
+            WidgetKind::Bool { default_value } => {
+                self.u8(1);
+                self.bool(*default_value);
+            }
+            WidgetKind::Choice {
+                default_option_key,
+                options,
+            } => {
+                self.u8(2);
+                self.str(default_option_key);
+                self.usize(options.len());
+                for option in options.iter() {
+                    self.str(&option.key);
+                    self.str(&option.description);
+                }
+            }
+            WidgetKind::FileSelect { filters } => {
+                self.u8(3);
+                self.usize(filters.len());
+                for filter in filters.iter() {
+                    match filter {
+                        FileFilter::Name {
+                            description,
+                            pattern,
+                        } => {
+                            self.u8(0);
+                            self.opt_str(description.as_deref());
+                            self.str(pattern);
+                        }
+                        FileFilter::MimeType(mime_type) => {
+                            self.u8(1);
+                            self.str(mime_type);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn request(&mut self, request: &Request) {
+        match request {
+            Request::Create {
+                debug_info,
+                optimize,
+                backtrace_details,
+                module,
+                settings_map,
+            } => {
+                self.u8(0);
+                self.opt_bool(*debug_info);
+                self.opt_bool(*optimize);
+                self.opt_bool(*backtrace_details);
+                self.bytes(module);
+                self.bool(settings_map.is_some());
+                if let Some(map) = settings_map {
+                    self.map(map);
+                }
+            }
+            Request::Step => self.u8(1),
+            Request::Interrupt => self.u8(2),
+            Request::SetStepBudget(ticks) => {
+                self.u8(3);
+                self.u64(*ticks);
+            }
+            Request::TickRate => self.u8(4),
+            Request::GetSettingsWidgets { known_generation } => {
+                self.u8(5);
+                self.u64(*known_generation);
+            }
+            Request::GetSettingsMap { known_generation } => {
+                self.u8(6);
+                self.u64(*known_generation);
+            }
+            Request::SetSettingsMap(map) => {
+                self.u8(7);
+                self.map(map);
+            }
+            Request::SetSetting { key, value } => {
+                self.u8(8);
+                self.str(key);
+                self.value(value);
+            }
+            Request::PathToWasi(path) => {
+                self.u8(9);
+                self.str(path);
+            }
+            Request::WasiToPath(path) => {
+                self.u8(10);
+                self.str(path);
+            }
+            Request::TimerReply(value) => {
+                self.u8(11);
+                self.u32(*value as u32);
+            }
+            Request::RemoveSetting(key) => {
+                self.u8(12);
+                self.str(key);
+            }
+            Request::SetSettingAtPath { path, value } => {
+                self.u8(13);
+                self.str(path);
+                self.value(value);
+            }
+        }
+    }
+
+    pub fn message(&mut self, message: &Message) {
+        match message {
+            Message::Callback(callback) => {
+                self.u8(0);
+                self.callback(callback);
+            }
+            Message::Response(response) => {
+                self.u8(1);
+                self.response(response);
+            }
+        }
+    }
+
+    fn callback(&mut self, callback: &Callback) {
+        match callback {
+            Callback::State => self.u8(0),
+            Callback::Index => self.u8(1),
+            Callback::SegmentSplitted(index) => {
+                self.u8(2);
+                self.u32(*index as u32);
+            }
+            Callback::Start => self.u8(3),
+            Callback::Split => self.u8(4),
+            Callback::SkipSplit => self.u8(5),
+            Callback::UndoSplit => self.u8(6),
+            Callback::Reset => self.u8(7),
+            Callback::SetGameTime(ticks) => {
+                self.u8(8);
+                self.i64(*ticks);
+            }
+            Callback::PauseGameTime => self.u8(9),
+            Callback::ResumeGameTime => self.u8(10),
+            Callback::SetCustomVariable(name, value) => {
+                self.u8(11);
+                self.str(name);
+                self.str(value);
+            }
+            Callback::Log {
+                source,
+                level,
+                message,
+            } => {
+                self.u8(12);
+                self.u32(*source);
+                self.u32(*level);
+                self.str(message);
+            }
+        }
+    }
+
+    fn response(&mut self, response: &Response) {
+        match response {
+            Response::Ok => self.u8(0),
+            Response::Err(error) => {
+                self.u8(1);
+                self.error(error);
+            }
+            Response::Stepped { error, interrupted } => {
+                self.u8(2);
+                self.bool(error.is_some());
+                if let Some(error) = error {
+                    self.error(error);
+                }
+                self.bool(*interrupted);
+            }
+            Response::TickRate(ticks) => {
+                self.u8(3);
+                self.u64(*ticks);
+            }
+            Response::SettingsWidgets {
+                generation,
+                widgets,
+            } => {
+                self.u8(4);
+                self.u64(*generation);
+                self.bool(widgets.is_some());
+                if let Some(widgets) = widgets {
+                    self.usize(widgets.len());
+                    for widget in widgets {
+                        self.widget(widget);
+                    }
+                }
+            }
+            Response::SettingsMap { generation, map } => {
+                self.u8(5);
+                self.u64(*generation);
+                self.bool(map.is_some());
+                if let Some(map) = map {
+                    self.map(map);
+                }
+            }
+            Response::Generation(generation) => {
+                self.u8(6);
+                self.u64(*generation);
+            }
+            Response::String(v) => {
+                self.u8(7);
+                self.str(v);
+            }
+        }
+    }
+}
+
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(invalid_data());
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn usize(&mut self) -> io::Result<usize> {
+        usize::try_from(self.u64()?).map_err(|_| invalid_data())
+    }
+
+    /// Reads the length of a sequence. Every element takes up at least one
+    /// byte, which bounds the length by the remaining bytes.
+    fn len(&mut self) -> io::Result<usize> {
+        let len = self.usize()?;
+        if len > self.buf.len() {
+            return Err(invalid_data());
+        }
+        Ok(len)
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.usize()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> io::Result<&'a str> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| invalid_data())
+    }
+
+    fn opt_bool(&mut self) -> io::Result<Option<bool>> {
+        Ok(match self.u8()? {
+            0 => None,
+            1 => Some(false),
+            2 => Some(true),
+            _ => return Err(invalid_data()),
+        })
+    }
+
+    fn opt_str(&mut self) -> io::Result<Option<&'a str>> {
+        Ok(if self.bool()? {
+            Some(self.str()?)
+        } else {
+            None
+        })
+    }
+
+    fn error(&mut self) -> io::Result<RemoteError> {
+        let kind = ErrorKind::from_u32(self.u32()?).ok_or_else(invalid_data)?;
+        let len = self.len()?;
+        let chain = (0..len)
+            .map(|_| Ok(self.str()?.into()))
+            .collect::<io::Result<_>>()?;
+        Ok(RemoteError { kind, chain })
+    }
+
+    fn map(&mut self) -> io::Result<SettingsMap> {
+        let mut map = SettingsMap::new();
+        for _ in 0..self.len()? {
+            let key = self.str()?;
+            if let Some(value) = self.value()? {
+                map.insert(key.into(), value);
+            }
+        }
+        Ok(map)
+    }
+
+    fn list(&mut self) -> io::Result<SettingsList> {
+        let mut list = SettingsList::new();
+        for _ in 0..self.len()? {
+            if let Some(value) = self.value()? {
+                list.push(value);
+            }
+        }
+        Ok(list)
+    }
+
+    fn value(&mut self) -> io::Result<Option<SettingValue>> {
+        Ok(Some(match self.u8()? {
+            0 => return Ok(None),
+            1 => SettingValue::Map(self.map()?),
+            2 => SettingValue::List(self.list()?),
+            3 => SettingValue::Bool(self.bool()?),
+            4 => SettingValue::I64(self.i64()?),
+            5 => SettingValue::F64(self.f64()?),
+            6 => SettingValue::String(self.str()?.into()),
+            _ => return Err(invalid_data()),
+        }))
+    }
+
+    fn widget(&mut self) -> io::Result<Widget> {
+        let key = self.str()?.into();
+        let description = self.str()?.into();
+        let tooltip = self.opt_str()?.map(Into::into);
+        let kind = match self.u8()? {
+            0 => WidgetKind::Title {
+                heading_level: self.u32()?,
+            },
+            1 => WidgetKind::Bool {
+                default_value: self.bool()?,
+            },
+            2 => {
+                let default_option_key = self.str()?.into();
+                let len = self.len()?;
+                let options = (0..len)
+                    .map(|_| {
+                        Ok(ChoiceOption {
+                            key: self.str()?.into(),
+                            description: self.str()?.into(),
+                        })
+                    })
+                    .collect::<io::Result<_>>()?;
+                WidgetKind::Choice {
+                    default_option_key,
+                    options,
+                }
+            }
+            3 => {
+                let len = self.len()?;
+                let filters = (0..len)
+                    .map(|_| {
+                        Ok(match self.u8()? {
+                            0 => FileFilter::Name {
+                                description: self.opt_str()?.map(Into::into),
+                                pattern: self.str()?.into(),
+                            },
+                            1 => FileFilter::MimeType(self.str()?.into()),
+                            _ => return Err(invalid_data()),
+                        })
+                    })
+                    .collect::<io::Result<_>>()?;
+                WidgetKind::FileSelect { filters }
+            }
+            _ => return Err(invalid_data()),
+        };
+        Ok(Widget {
+            key,
+            description,
+            tooltip,
+            kind,
+        })
+    }
+
+    pub fn request(&mut self) -> io::Result<Request> {
+        Ok(match self.u8()? {
+            0 => Request::Create {
+                debug_info: self.opt_bool()?,
+                optimize: self.opt_bool()?,
+                backtrace_details: self.opt_bool()?,
+                module: self.bytes()?.to_vec(),
+                settings_map: if self.bool()? {
+                    Some(self.map()?)
+                } else {
+                    None
+                },
+            },
+            1 => Request::Step,
+            2 => Request::Interrupt,
+            3 => Request::SetStepBudget(self.u64()?),
+            4 => Request::TickRate,
+            5 => Request::GetSettingsWidgets {
+                known_generation: self.u64()?,
+            },
+            6 => Request::GetSettingsMap {
+                known_generation: self.u64()?,
+            },
+            7 => Request::SetSettingsMap(self.map()?),
+            8 => {
+                let key = self.str()?.into();
+                let value = self.value()?.ok_or_else(invalid_data)?;
+                Request::SetSetting { key, value }
+            }
+            9 => Request::PathToWasi(self.str()?.into()),
+            10 => Request::WasiToPath(self.str()?.into()),
+            11 => Request::TimerReply(self.u32()? as i32),
+            12 => Request::RemoveSetting(self.str()?.into()),
+            13 => {
+                let path = self.str()?.into();
+                let value = self.value()?.ok_or_else(invalid_data)?;
+                Request::SetSettingAtPath { path, value }
+            }
+            _ => return Err(invalid_data()),
+        })
+    }
+
+    pub fn message(&mut self) -> io::Result<Message> {
+        Ok(match self.u8()? {
+            0 => Message::Callback(self.callback()?),
+            1 => Message::Response(self.response()?),
+            _ => return Err(invalid_data()),
+        })
+    }
+
+    fn callback(&mut self) -> io::Result<Callback> {
+        Ok(match self.u8()? {
+            0 => Callback::State,
+            1 => Callback::Index,
+            2 => Callback::SegmentSplitted(self.u32()? as i32),
+            3 => Callback::Start,
+            4 => Callback::Split,
+            5 => Callback::SkipSplit,
+            6 => Callback::UndoSplit,
+            7 => Callback::Reset,
+            8 => Callback::SetGameTime(self.i64()?),
+            9 => Callback::PauseGameTime,
+            10 => Callback::ResumeGameTime,
+            11 => Callback::SetCustomVariable(self.str()?.into(), self.str()?.into()),
+            12 => Callback::Log {
+                source: self.u32()?,
+                level: self.u32()?,
+                message: self.str()?.into(),
+            },
+            _ => return Err(invalid_data()),
+        })
+    }
+
+    fn response(&mut self) -> io::Result<Response> {
+        Ok(match self.u8()? {
+            0 => Response::Ok,
+            1 => Response::Err(self.error()?),
+            2 => Response::Stepped {
+                error: if self.bool()? {
+                    Some(self.error()?)
+                } else {
+                    None
+                },
+                interrupted: self.bool()?,
+            },
+            3 => Response::TickRate(self.u64()?),
+            4 => Response::SettingsWidgets {
+                generation: self.u64()?,
+                widgets: if self.bool()? {
+                    let len = self.len()?;
+                    Some((0..len).map(|_| self.widget()).collect::<io::Result<_>>()?)
+                } else {
+                    None
+                },
+            },
+            5 => Response::SettingsMap {
+                generation: self.u64()?,
+                map: if self.bool()? {
+                    Some(self.map()?)
+                } else {
+                    None
+                },
+            },
+            6 => Response::Generation(self.u64()?),
+            7 => Response::String(self.str()?.into()),
+            _ => return Err(invalid_data()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_map() -> SettingsMap {
+        let mut list = SettingsList::new();
+        list.push(SettingValue::I64(-3));
+        list.push(SettingValue::String("ä".into()));
+
+        let mut nested = SettingsMap::new();
+        nested.insert("list".into(), SettingValue::List(list));
+
+        let mut map = SettingsMap::new();
+        map.insert("bool".into(), SettingValue::Bool(true));
+        map.insert("f64".into(), SettingValue::F64(0.1 + 0.2));
+        map.insert("map".into(), SettingValue::Map(nested));
+        map
+    }
+
+    fn widgets() -> Vec<Widget> {
+        let widget = |key: &str, tooltip: Option<&str>, kind| Widget {
+            key: key.into(),
+            description: "Description".into(),
+            tooltip: tooltip.map(Into::into),
+            kind,
+        };
+        vec![
+            widget("title", None, WidgetKind::Title { heading_level: 2 }),
+            widget(
+                "bool",
+                Some("Tooltip"),
+                WidgetKind::Bool {
+                    default_value: true,
+                },
+            ),
+            widget(
+                "choice",
+                None,
+                WidgetKind::Choice {
+                    default_option_key: "b".into(),
+                    options: vec![
+                        ChoiceOption {
+                            key: "a".into(),
+                            description: "A".into(),
+                        },
+                        ChoiceOption {
+                            key: "b".into(),
+                            description: "B".into(),
+                        },
+                    ]
+                    .into(),
+                },
+            ),
+            widget(
+                "file",
+                None,
+                WidgetKind::FileSelect {
+                    filters: vec![
+                        FileFilter::Name {
+                            description: Some("Saves".into()),
+                            pattern: "*.sav".into(),
+                        },
+                        FileFilter::Name {
+                            description: None,
+                            pattern: "*".into(),
+                        },
+                        FileFilter::MimeType("text/plain".into()),
+                    ]
+                    .into(),
+                },
+            ),
+        ]
+    }
+
+    fn error() -> RemoteError {
+        RemoteError {
+            kind: ErrorKind::Trap,
+            chain: vec!["Outer.".into(), "Inner.".into()],
+        }
+    }
+
+    fn requests() -> Vec<Request> {
+        vec![
+            Request::Create {
+                debug_info: None,
+                optimize: Some(false),
+                backtrace_details: Some(true),
+                module: b"\0asm".to_vec(),
+                settings_map: Some(settings_map()),
+            },
+            Request::Create {
+                debug_info: Some(true),
+                optimize: None,
+                backtrace_details: None,
+                module: Vec::new(),
+                settings_map: None,
+            },
+            Request::Step,
+            Request::Interrupt,
+            Request::SetStepBudget(u64::MAX),
+            Request::TickRate,
+            Request::GetSettingsWidgets {
+                known_generation: 3,
+            },
+            Request::GetSettingsMap {
+                known_generation: 4,
+            },
+            Request::SetSettingsMap(settings_map()),
+            Request::SetSetting {
+                key: "key".into(),
+                value: SettingValue::Map(settings_map()),
+            },
+            Request::RemoveSetting("key".into()),
+            Request::SetSettingAtPath {
+                path: "map/list/1".into(),
+                value: SettingValue::F64(f64::NEG_INFINITY),
+            },
+            Request::PathToWasi("C:\\splits".into()),
+            Request::WasiToPath("/mnt/c/splits".into()),
+            Request::TimerReply(-1),
+        ]
+    }
+
+    fn messages() -> Vec<Message> {
+        let callbacks = [
+            Callback::State,
+            Callback::Index,
+            Callback::SegmentSplitted(-1),
+            Callback::Start,
+            Callback::Split,
+            Callback::SkipSplit,
+            Callback::UndoSplit,
+            Callback::Reset,
+            Callback::SetGameTime(i64::MIN),
+            Callback::PauseGameTime,
+            Callback::ResumeGameTime,
+            Callback::SetCustomVariable("name".into(), "value".into()),
+            Callback::Log {
+                source: 1,
+                level: 4,
+                message: "Message.".into(),
+            },
+        ];
+        let responses = [
+            Response::Ok,
+            Response::Err(error()),
+            Response::Stepped {
+                error: Some(error()),
+                interrupted: true,
+            },
+            Response::Stepped {
+                error: None,
+                interrupted: false,
+            },
+            Response::TickRate(166_667),
+            Response::SettingsWidgets {
+                generation: 1,
+                widgets: Some(widgets()),
+            },
+            Response::SettingsWidgets {
+                generation: 2,
+                widgets: None,
+            },
+            Response::SettingsMap {
+                generation: 3,
+                map: Some(settings_map()),
+            },
+            Response::SettingsMap {
+                generation: 4,
+                map: None,
+            },
+            Response::Generation(5),
+            Response::String("path".into()),
+        ];
+        callbacks
+            .into_iter()
+            .map(Message::Callback)
+            .chain(responses.into_iter().map(Message::Response))
+            .collect()
+    }
+
+    fn encode_request(request: &Request) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.request(request);
+        encoder.finish()
+    }
+
+    fn encode_message(message: &Message) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.message(message);
+        encoder.finish()
+    }
+
+    // The messages can't be compared directly, so a round trip is considered
+    // lossless if the decoded message encodes to the same bytes.
+
+    #[test]
+    fn round_trips_requests() {
+        for request in requests() {
+            let encoded = encode_request(&request);
+            let decoded = Decoder::new(&encoded).request().unwrap();
+            assert_eq!(encode_request(&decoded), encoded);
+        }
+    }
+
+    #[test]
+    fn round_trips_messages() {
+        for message in messages() {
+            let encoded = encode_message(&message);
+            let decoded = Decoder::new(&encoded).message().unwrap();
+            assert_eq!(encode_message(&decoded), encoded);
+        }
+    }
+
+    #[test]
+    fn keeps_the_values_of_settings() {
+        let encoded = encode_request(&Request::SetSettingsMap(settings_map()));
+        let Request::SetSettingsMap(map) = Decoder::new(&encoded).request().unwrap() else {
+            panic!("Decoded the wrong request.");
+        };
+
+        assert!(matches!(map.get("bool"), Some(SettingValue::Bool(true))));
+        assert!(matches!(map.get("f64"), Some(&SettingValue::F64(v)) if v == 0.1 + 0.2));
+        let Some(SettingValue::Map(nested)) = map.get("map") else {
+            panic!("The nested map is missing.");
+        };
+        let Some(SettingValue::List(list)) = nested.get("list") else {
+            panic!("The list is missing.");
+        };
+        assert!(matches!(list.get(0), Some(SettingValue::I64(-3))));
+        assert!(matches!(list.get(1), Some(SettingValue::String(v)) if &**v == "ä"));
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        for message in messages() {
+            let encoded = encode_message(&message);
+            for len in 0..encoded.len() {
+                assert!(Decoder::new(&encoded[..len]).message().is_err());
+            }
+        }
+        for request in requests() {
+            let encoded = encode_request(&request);
+            for len in 0..encoded.len() {
+                assert!(Decoder::new(&encoded[..len]).request().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_tags() {
+        assert!(Decoder::new(&[14]).request().is_err());
+        assert!(Decoder::new(&[2]).message().is_err());
+        assert!(Decoder::new(&[0, 13]).message().is_err());
+        assert!(Decoder::new(&[1, 8]).message().is_err());
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, b"first").unwrap();
+        write_frame(&mut stream, b"").unwrap();
+
+        let mut reader = &stream[..];
+        let mut buf = Vec::new();
+        read_frame(&mut reader, &mut buf, MAX_MESSAGE_LEN).unwrap();
+        assert_eq!(buf, b"first");
+        read_frame(&mut reader, &mut buf, MAX_MESSAGE_LEN).unwrap();
+        assert_eq!(buf, b"");
+        assert!(read_frame(&mut reader, &mut buf, MAX_MESSAGE_LEN).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let len = (MAX_MESSAGE_LEN as u32 + 1).to_le_bytes();
+        let err = read_frame(&len[..], &mut Vec::new(), MAX_MESSAGE_LEN).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Requests may be larger, as they carry the module.
+        let mut frame = len.to_vec();
+        frame.resize(4 + MAX_MESSAGE_LEN + 1, 0);
+        let mut buf = Vec::new();
+        read_frame(&frame[..], &mut buf, MAX_REQUEST_LEN).unwrap();
+        assert_eq!(buf.len(), MAX_MESSAGE_LEN + 1);
+    }
+}
@@ -1,21 +1,132 @@
+use error::set_last_error;
+use std::{cell::RefCell, ffi::CStr};
+
 #[cfg(target_pointer_width = "64")]
 use {
-    livesplit_auto_splitting::{time, wasi_path, LogLevel, Timer, TimerState},
-    std::{cell::RefCell, ffi::CStr, fmt, path::Path},
+    livesplit_auto_splitting::{settings, wasi_path},
+    std::path::Path,
 };
 
+mod error;
+#[cfg(target_pointer_width = "64")]
+mod helper;
+// Other targets only act as the client of the helper, so they don't use the
+// helper's side of the protocol.
+#[cfg_attr(not(target_pointer_width = "64"), allow(dead_code))]
+mod ipc;
+mod log;
+// The mock timer and the replay of recordings are only exposed with the
+// `testing` feature, which leaves the parts that recording doesn't use unused.
+#[cfg_attr(not(feature = "testing"), allow(dead_code))]
+mod mock_timer;
+#[cfg_attr(not(feature = "testing"), allow(dead_code))]
+mod recording;
+mod remote;
 mod runtime;
+mod runtime_config;
+mod setting_path;
 mod setting_value;
+#[cfg(not(target_pointer_width = "64"))]
+mod settings;
+mod settings_diff;
+mod settings_json;
 mod settings_list;
 mod settings_map;
+mod settings_xml;
+mod stats;
+mod timer;
+mod watchdog;
 mod widgets;
 
+/// Runs the helper process that hosts auto splitters on behalf of 32-bit
+/// builds of this library, which communicate with it through its standard
+/// input and output.
+#[cfg(target_pointer_width = "64")]
+#[doc(hidden)]
+pub use helper::run as run_helper;
+
 #[cfg(target_pointer_width = "64")]
+pub use timer::CTimer;
+pub use timer::TimerVTable;
+#[cfg(feature = "testing")]
+pub use {
+    mock_timer::{MockTimer, MockTimerState, TimerAction, TimerEvent},
+    recording::{Divergence, Interaction, Recording, RecordingEntry, Replay},
+};
+
+// The C API, so it can be called from Rust as well.
+pub use {
+    error::{
+        ErrorKind, Runtime_clear_last_error, Runtime_last_error_chain_get,
+        Runtime_last_error_chain_get_into, Runtime_last_error_chain_len, Runtime_last_error_kind,
+        Runtime_last_error_message, Runtime_last_error_message_into,
+    },
+    log::{
+        LogRecord, LogRecord_drop, LogRecord_get_level, LogRecord_get_message,
+        LogRecord_get_message_into, LogRecord_get_source, LogRecord_get_timestamp,
+    },
+    runtime::{
+        Runtime, Runtime_are_settings_changed, Runtime_diff_settings, Runtime_drop,
+        Runtime_get_settings_map, Runtime_get_settings_widgets, Runtime_interrupt,
+        Runtime_logs_clear, Runtime_logs_get, Runtime_logs_len, Runtime_new,
+        Runtime_new_from_bytes, Runtime_new_from_bytes_with_config, Runtime_new_with_config,
+        Runtime_reload, Runtime_reload_from_bytes, Runtime_set_helper_path, Runtime_set_log_level,
+        Runtime_set_settings_map, Runtime_set_step_budget, Runtime_settings_map_get_value_by_path,
+        Runtime_settings_map_remove, Runtime_settings_map_set_bool, Runtime_settings_map_set_f64,
+        Runtime_settings_map_set_i64, Runtime_settings_map_set_list, Runtime_settings_map_set_map,
+        Runtime_settings_map_set_string, Runtime_settings_map_set_value_by_path,
+        Runtime_start_recording, Runtime_start_thread, Runtime_start_watching,
+        Runtime_stats_failures, Runtime_stats_last, Runtime_stats_max, Runtime_stats_mean,
+        Runtime_stats_overruns, Runtime_stats_p99, Runtime_stats_reset, Runtime_stats_steps,
+        Runtime_step, Runtime_stop_recording, Runtime_stop_thread, Runtime_stop_watching,
+        Runtime_tick_rate, Runtime_was_interrupted,
+    },
+    runtime_config::{
+        RuntimeConfig, RuntimeConfig_drop, RuntimeConfig_new, RuntimeConfig_set_backtrace_details,
+        RuntimeConfig_set_debug_info, RuntimeConfig_set_optimize, RuntimeConfig_set_sandboxed,
+    },
+    setting_value::{
+        SettingValue, SettingValue_drop, SettingValue_get_bool, SettingValue_get_f64,
+        SettingValue_get_i64, SettingValue_get_list, SettingValue_get_map, SettingValue_get_string,
+        SettingValue_get_string_into, SettingValue_get_string_utf16, SettingValue_get_type,
+        SettingValue_new_bool, SettingValue_new_f64, SettingValue_new_i64, SettingValue_new_list,
+        SettingValue_new_map, SettingValue_new_string,
+    },
+    settings_diff::{
+        SettingsDiff, SettingsDiff_drop, SettingsDiff_get_current_value, SettingsDiff_get_kind,
+        SettingsDiff_get_path, SettingsDiff_get_path_into, SettingsDiff_get_path_utf16,
+        SettingsDiff_get_previous_value, SettingsDiff_len,
+    },
+    settings_list::{
+        SettingsList, SettingsList_drop, SettingsList_get, SettingsList_len, SettingsList_new,
+        SettingsList_push,
+    },
+    settings_map::{
+        SettingsMap, SettingsMap_diff, SettingsMap_drop, SettingsMap_from_json,
+        SettingsMap_from_xml, SettingsMap_get_key, SettingsMap_get_key_into,
+        SettingsMap_get_key_utf16, SettingsMap_get_value, SettingsMap_get_value_by_key,
+        SettingsMap_get_value_by_path, SettingsMap_insert, SettingsMap_len, SettingsMap_new,
+        SettingsMap_remove, SettingsMap_set_value_by_path, SettingsMap_to_json,
+        SettingsMap_to_json_into, SettingsMap_to_xml, SettingsMap_to_xml_into,
+    },
+    widgets::{
+        Widgets, Widgets_drop, Widgets_get_bool, Widgets_get_choice_current_index,
+        Widgets_get_choice_option_description, Widgets_get_choice_option_description_into,
+        Widgets_get_choice_option_description_utf16, Widgets_get_choice_option_key,
+        Widgets_get_choice_option_key_into, Widgets_get_choice_option_key_utf16,
+        Widgets_get_choice_options_len, Widgets_get_description, Widgets_get_description_into,
+        Widgets_get_description_utf16, Widgets_get_file_select_filter,
+        Widgets_get_file_select_filter_into, Widgets_get_file_select_filter_utf16,
+        Widgets_get_heading_level, Widgets_get_key, Widgets_get_key_into, Widgets_get_key_utf16,
+        Widgets_get_tooltip, Widgets_get_tooltip_into, Widgets_get_tooltip_utf16, Widgets_get_type,
+        Widgets_len,
+    },
+};
+
 thread_local! {
     static OUTPUT_VEC: RefCell<Vec<u8>>  = RefCell::new(Vec::new());
 }
 
-#[cfg(target_pointer_width = "64")]
 fn output_vec<F>(f: F) -> *const u8
 where
     F: FnOnce(&mut Vec<u8>),
@@ -28,178 +139,246 @@ where
     })
 }
 
-#[cfg(target_pointer_width = "64")]
 fn output_str(s: &str) -> *const u8 {
     output_vec(|o| {
         o.extend_from_slice(s.as_bytes());
     })
 }
 
-#[cfg(target_pointer_width = "64")]
-unsafe fn str(s: *const u8) -> &'static str {
+/// Reads a nul-terminated string passed in by the host. A null pointer is
+/// treated as an empty string. If the string is not valid UTF-8, an
+/// `InvalidInput` error is stored as the last error and `None` is returned.
+unsafe fn str(s: *const u8) -> Option<&'static str> {
     if s.is_null() {
-        ""
-    } else {
-        let bytes = CStr::from_ptr(s.cast()).to_bytes();
-        std::str::from_utf8_unchecked(bytes)
-    }
-}
-
-#[cfg(target_pointer_width = "64")]
-pub struct CTimer {
-    state: unsafe extern "C" fn() -> i32,
-    index: unsafe extern "C" fn() -> i32,
-    segment_splitted: unsafe extern "C" fn(i32) -> i32,
-    start: unsafe extern "C" fn(),
-    split: unsafe extern "C" fn(),
-    skip_split: unsafe extern "C" fn(),
-    undo_split: unsafe extern "C" fn(),
-    reset: unsafe extern "C" fn(),
-    set_game_time: unsafe extern "C" fn(i64),
-    pause_game_time: unsafe extern "C" fn(),
-    resume_game_time: unsafe extern "C" fn(),
-    set_custom_variable: unsafe extern "C" fn(*const u8, usize, *const u8, usize),
-    log: unsafe extern "C" fn(*const u8, usize),
-}
-
-#[cfg(target_pointer_width = "64")]
-impl Timer for CTimer {
-    fn state(&self) -> TimerState {
-        match unsafe { (self.state)() } {
-            1 => TimerState::Running,
-            2 => TimerState::Paused,
-            3 => TimerState::Ended,
-            _ => TimerState::NotRunning,
-        }
+        return Some("");
     }
-
-    fn current_split_index(&self) -> Option<usize> {
-        let i = unsafe { (self.index)() };
-        if i >= 0 {
-            Some(i as usize)
-        } else {
+    let bytes = CStr::from_ptr(s.cast()).to_bytes();
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Some(s),
+        Err(err) => {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::Error::from(err).context("The string passed is not valid UTF-8."),
+            );
             None
         }
     }
+}
 
-    fn segment_splitted(&self, idx: usize) -> Option<bool> {
-        match unsafe { (self.segment_splitted)(i32::try_from(idx).ok()?) } {
-            1 => Some(true),
-            0 => Some(false),
-            _ => None,
-        }
-    }
-
-    fn start(&mut self) {
-        unsafe { (self.start)() }
-    }
-
-    fn split(&mut self) {
-        unsafe { (self.split)() }
-    }
-
-    fn skip_split(&mut self) {
-        unsafe { (self.skip_split)() }
-    }
-
-    fn undo_split(&mut self) {
-        unsafe { (self.undo_split)() }
-    }
-
-    fn reset(&mut self) {
-        unsafe { (self.reset)() }
-    }
-
-    fn set_game_time(&mut self, time: time::Duration) {
-        const TICKS_PER_SEC: i64 = 10_000_000;
-        const NANOS_PER_SEC: i64 = 1_000_000_000;
-        const NANOS_PER_TICK: i64 = NANOS_PER_SEC / TICKS_PER_SEC;
-
-        let (secs, nanos) = (time.whole_seconds(), time.subsec_nanoseconds());
-        let ticks = secs * TICKS_PER_SEC + nanos as i64 / NANOS_PER_TICK;
-        unsafe { (self.set_game_time)(ticks) }
-    }
-
-    fn pause_game_time(&mut self) {
-        unsafe { (self.pause_game_time)() }
-    }
-
-    fn resume_game_time(&mut self) {
-        unsafe { (self.resume_game_time)() }
-    }
-
-    fn set_variable(&mut self, name: &str, value: &str) {
-        unsafe {
-            (self.set_custom_variable)(name.as_ptr(), name.len(), value.as_ptr(), value.len())
-        }
-    }
-
-    fn log_auto_splitter(&mut self, message: fmt::Arguments<'_>) {
-        log(self.log, message);
-    }
-
-    fn log_runtime(&mut self, message: fmt::Arguments<'_>, _: LogLevel) {
-        log(self.log, message);
+/// Copies the bytes into the buffer owned by the caller, following the
+/// convention described at `get_buf_len`.
+unsafe fn output_into(bytes: &[u8], buf: *mut u8, cap: usize) -> usize {
+    if !buf.is_null() && cap != 0 {
+        let len = if bytes.len() < cap { bytes.len() } else { 0 };
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, len);
+        *buf.add(len) = 0;
     }
+    bytes.len()
 }
 
-#[cfg(target_pointer_width = "64")]
-fn log(log_fn: unsafe extern "C" fn(*const u8, usize), message: fmt::Arguments<'_>) {
-    let mut owned;
-    let message = match message.as_str() {
-        Some(m) => m,
-        None => {
-            owned = smallstr::SmallString::<[u8; 4 << 10]>::new();
-            use std::fmt::Write;
-            let _ = write!(owned, "{message}");
-            &owned
+/// Encodes the string as UTF-16 into the buffer owned by the caller, following
+/// the convention described at `get_buf_len`.
+unsafe fn output_utf16_into(s: &str, buf: *mut u16, cap: usize) -> usize {
+    let len = s.encode_utf16().count();
+    if !buf.is_null() && cap != 0 {
+        if len < cap {
+            for (index, unit) in s.encode_utf16().enumerate() {
+                *buf.add(index) = unit;
+            }
         }
-    };
-    unsafe { log_fn(message.as_ptr(), message.len()) }
+        *buf.add(if len < cap { len } else { 0 }) = 0;
+    }
+    len
 }
 
 /// Returns the byte length of the last nul-terminated string returned on the
 /// current thread. The length excludes the nul-terminator.
+///
+/// Strings returned this way are overwritten by the next call that returns a
+/// string on the same thread. The functions with an `_into` suffix instead
+/// write the string into a buffer owned by the caller. The string and its
+/// nul-terminator are only written if they fit into the buffer's capacity.
+/// Otherwise just a nul-terminator is written at the start of the buffer, if
+/// there is room for it, so the buffer holds an empty string. Either
+/// way the byte length of the string, excluding the nul-terminator, is
+/// returned, so the call can be repeated with a large enough buffer. The
+/// functions with a `_utf16` suffix work the same way, but write UTF-16 code
+/// units into a `u16` buffer, so both the capacity and the returned length are
+/// counted in code units.
 #[no_mangle]
 pub extern "C" fn get_buf_len() -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        OUTPUT_VEC.with(|v| v.borrow().len() - 1)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    0
+    OUTPUT_VEC.with(|v| v.borrow().len().saturating_sub(1))
 }
 
 /// Translates `original_path` into a path that is accessible through the WASI
 /// file system, so a Windows path of `C:\foo\bar.exe` would be returned as
-/// `/mnt/c/foo/bar.exe`.
+/// `/mnt/c/foo/bar.exe`. Returns an empty string if the path is not valid UTF-8
+/// or can't be translated.
 ///
 /// # Safety
-/// `original_path` must be a valid nul-terminated UTF-8 string.
+/// `original_path` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn path_to_wasi(_original_path: *const u8) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let wasi = wasi_path::from_native(Path::new(str(_original_path))).unwrap_or_default();
-        output_str(&wasi)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    "\0".as_ptr()
+    output_str(&to_wasi(_original_path))
+}
+
+/// Like `path_to_wasi`, but writes the path into a buffer owned by the caller.
+/// See `get_buf_len` for how the buffer is filled. If the path doesn't fit,
+/// only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `original_path` must be a valid nul-terminated string. `buf` must be valid
+/// for writes of `cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn path_to_wasi_into(
+    _original_path: *const u8,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(to_wasi(_original_path).as_bytes(), _buf, _cap)
+}
+
+/// Like `path_to_wasi`, but writes the path as UTF-16 into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the path
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `original_path` must be a valid nul-terminated string. `buf` must be valid
+/// for writes of `cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn path_to_wasi_utf16(
+    _original_path: *const u8,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(&to_wasi(_original_path), _buf, _cap)
+}
+
+#[cfg(target_pointer_width = "64")]
+unsafe fn to_wasi(original_path: *const u8) -> Box<str> {
+    str(original_path)
+        .and_then(|path| wasi_path::from_native(Path::new(path)))
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+unsafe fn to_wasi(original_path: *const u8) -> Box<str> {
+    str(original_path)
+        .and_then(remote::path_to_wasi)
+        .unwrap_or_default()
 }
 
 /// Translates from a path accessible through the WASI file system to a path
 /// accessible outside that, so a WASI path of `/mnt/c/foo/bar.exe` would be
-/// translated on Windows to `C:\foo\bar.exe`.
+/// translated on Windows to `C:\foo\bar.exe`. Returns an empty string if the
+/// path is not valid UTF-8 or can't be translated.
 ///
 /// # Safety
-/// `wasi_path` must be a valid nul-terminated UTF-8 string.
+/// `wasi_path` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn wasi_to_path(_wasi_path: *const u8) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let path = wasi_path::to_native(str(_wasi_path), true).unwrap_or_default();
-        output_str(path.to_str().unwrap_or_default())
+    output_str(&to_native(_wasi_path))
+}
+
+/// Like `wasi_to_path`, but writes the path into a buffer owned by the caller.
+/// See `get_buf_len` for how the buffer is filled. If the path doesn't fit,
+/// only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `wasi_path` must be a valid nul-terminated string. `buf` must be valid for
+/// writes of `cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_to_path_into(
+    _wasi_path: *const u8,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(to_native(_wasi_path).as_bytes(), _buf, _cap)
+}
+
+/// Like `wasi_to_path`, but writes the path as UTF-16 into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the path
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `wasi_path` must be a valid nul-terminated string. `buf` must be valid for
+/// writes of `cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_to_path_utf16(
+    _wasi_path: *const u8,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(&to_native(_wasi_path), _buf, _cap)
+}
+
+#[cfg(target_pointer_width = "64")]
+unsafe fn to_native(wasi_path: *const u8) -> Box<str> {
+    str(wasi_path)
+        .and_then(|path| wasi_path::to_native(path, true))
+        .and_then(|path| path.into_os_string().into_string().ok())
+        .unwrap_or_default()
+        .into()
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+unsafe fn to_native(wasi_path: *const u8) -> Box<str> {
+    str(wasi_path)
+        .and_then(remote::wasi_to_path)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn writes_strings_that_fit_into_the_buffer() {
+        let mut buf = [0xff; 5];
+        assert_eq!(unsafe { output_into(b"abc", buf.as_mut_ptr(), 5) }, 3);
+        assert_eq!(buf, *b"abc\0\xff");
+
+        let mut buf = [0xff; 4];
+        assert_eq!(unsafe { output_into(b"abc", buf.as_mut_ptr(), 4) }, 3);
+        assert_eq!(buf, *b"abc\0");
+    }
+
+    #[test]
+    fn only_writes_a_nul_terminator_if_the_string_doesnt_fit() {
+        let mut buf = [0xff; 3];
+        assert_eq!(unsafe { output_into(b"abc", buf.as_mut_ptr(), 3) }, 3);
+        assert_eq!(buf, [0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn doesnt_write_into_empty_buffers() {
+        let mut buf = [0xff];
+        assert_eq!(unsafe { output_into(b"abc", buf.as_mut_ptr(), 0) }, 3);
+        assert_eq!(buf, [0xff]);
+        assert_eq!(unsafe { output_into(b"abc", ptr::null_mut(), 0) }, 3);
+    }
+
+    #[test]
+    fn writes_utf16_strings_that_fit_into_the_buffer() {
+        // The emoji is encoded as a surrogate pair.
+        let mut buf = [0xffff; 4];
+        assert_eq!(unsafe { output_utf16_into("a😀", buf.as_mut_ptr(), 4) }, 3);
+        assert_eq!(buf, [0x61, 0xd83d, 0xde00, 0]);
+    }
+
+    #[test]
+    fn only_writes_a_nul_terminator_if_the_utf16_string_doesnt_fit() {
+        let mut buf = [0xffff; 3];
+        assert_eq!(unsafe { output_utf16_into("a😀", buf.as_mut_ptr(), 3) }, 3);
+        assert_eq!(buf, [0, 0xffff, 0xffff]);
+    }
+
+    #[test]
+    fn doesnt_write_utf16_into_empty_buffers() {
+        let mut buf = [0xffff];
+        assert_eq!(unsafe { output_utf16_into("a😀", buf.as_mut_ptr(), 0) }, 3);
+        assert_eq!(buf, [0xffff]);
+        assert_eq!(unsafe { output_utf16_into("a😀", ptr::null_mut(), 0) }, 3);
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    "\0".as_ptr()
 }
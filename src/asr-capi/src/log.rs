@@ -0,0 +1,285 @@
+use crate::{output_into, output_str, timer::TimerVTable};
+use std::{
+    collections::VecDeque,
+    ffi::c_void,
+    fmt,
+    sync::{
+        atomic::{self, AtomicU32},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(target_pointer_width = "64")]
+use livesplit_auto_splitting::LogLevel;
+
+/// The maximum amount of log records that are kept around. Once exceeded, the
+/// oldest records are dropped.
+const LOG_CAPACITY: usize = 1024;
+
+/// Where a log message originates from. The discriminants are the values passed
+/// to the host's leveled log callback.
+#[derive(Copy, Clone)]
+#[repr(u32)]
+pub enum LogSource {
+    /// The auto splitter printed the message.
+    AutoSplitter = 0,
+    /// The runtime itself logged the message.
+    Runtime = 1,
+}
+
+/// The level of informational messages, as passed to the host.
+pub const INFO_LEVEL: u32 = 2;
+
+/// The level of error messages, as passed to the host.
+pub const ERROR_LEVEL: u32 = 4;
+
+/// Converts the log level into the value passed to the host. The levels are
+/// ordered by their severity, starting with 0 for trace messages up to 4 for
+/// errors.
+#[cfg(target_pointer_width = "64")]
+pub fn level_to_u32(level: LogLevel) -> u32 {
+    match level {
+        LogLevel::Trace => 0,
+        LogLevel::Debug => 1,
+        LogLevel::Info => INFO_LEVEL,
+        LogLevel::Warning => 3,
+        LogLevel::Error => ERROR_LEVEL,
+    }
+}
+
+/// Implements the `log` callback of a timer in terms of its `log_leveled`
+/// callback, for timers that provide both. The messages are passed on as info
+/// messages of the auto splitter.
+///
+/// # Safety
+/// The arguments need to be valid for the `log_leveled` callback.
+pub unsafe fn log_unleveled(
+    log_leveled: unsafe extern "C" fn(*mut c_void, u32, u32, *const u8, usize),
+    user_data: *mut c_void,
+    message_ptr: *const u8,
+    message_len: usize,
+) {
+    log_leveled(
+        user_data,
+        LogSource::AutoSplitter as u32,
+        INFO_LEVEL,
+        message_ptr,
+        message_len,
+    );
+}
+
+/// A log message that was kept around by the runtime, so it can be queried
+/// after the fact.
+#[derive(Clone)]
+pub struct LogRecord {
+    timestamp: i64,
+    level: u32,
+    source: LogSource,
+    message: Box<str>,
+}
+
+/// Forwards log messages to the host, dropping all messages below the minimum
+/// level that the host configured. The most recent messages are additionally
+/// kept in a bounded buffer, regardless of their level, so they can still be
+/// inspected after the fact.
+pub struct Logger {
+    log: unsafe extern "C" fn(*mut c_void, *const u8, usize),
+    log_leveled: Option<unsafe extern "C" fn(*mut c_void, u32, u32, *const u8, usize)>,
+    user_data: *mut c_void,
+    min_level: AtomicU32,
+    records: Mutex<VecDeque<LogRecord>>,
+}
+
+// SAFETY: The host is responsible for making its callbacks and the user data
+// usable from the threads that it drives the runtime from.
+unsafe impl Send for Logger {}
+unsafe impl Sync for Logger {}
+
+impl Logger {
+    pub fn new(vtable: &TimerVTable, user_data: *mut c_void) -> Self {
+        Self {
+            log: vtable.log,
+            log_leveled: vtable.log_leveled,
+            user_data,
+            min_level: AtomicU32::new(0),
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn records_len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    pub fn record(&self, index: usize) -> Option<LogRecord> {
+        self.records.lock().unwrap().get(index).cloned()
+    }
+
+    pub fn clear_records(&self) {
+        self.records.lock().unwrap().clear();
+    }
+
+    pub fn set_min_level(&self, level: u32) {
+        self.min_level.store(level, atomic::Ordering::Relaxed);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    pub fn log(&self, source: LogSource, level: LogLevel, message: fmt::Arguments<'_>) {
+        self.log_with_level(source, level_to_u32(level), message);
+    }
+
+    /// Logs the message with the level already converted into the value
+    /// passed to the host.
+    pub fn log_with_level(&self, source: LogSource, level: u32, message: fmt::Arguments<'_>) {
+        let mut owned;
+        let message = match message.as_str() {
+            Some(m) => m,
+            None => {
+                owned = smallstr::SmallString::<[u8; 4 << 10]>::new();
+                use std::fmt::Write;
+                let _ = write!(owned, "{message}");
+                &owned
+            }
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+
+        {
+            let mut records = self.records.lock().unwrap();
+            if records.len() >= LOG_CAPACITY {
+                records.pop_front();
+            }
+            records.push_back(LogRecord {
+                timestamp,
+                level,
+                source,
+                message: message.into(),
+            });
+        }
+
+        if level < self.min_level.load(atomic::Ordering::Relaxed) {
+            return;
+        }
+
+        unsafe {
+            match self.log_leveled {
+                Some(log_leveled) => log_leveled(
+                    self.user_data,
+                    source as u32,
+                    level,
+                    message.as_ptr(),
+                    message.len(),
+                ),
+                None => (self.log)(self.user_data, message.as_ptr(), message.len()),
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn LogRecord_drop(_: Box<LogRecord>) {}
+
+/// Returns the time the message was logged at, in milliseconds since the Unix
+/// epoch.
+#[no_mangle]
+pub extern "C" fn LogRecord_get_timestamp(_this: &LogRecord) -> i64 {
+    _this.timestamp
+}
+
+/// Returns the level of the message, ranging from 0 for trace messages up to 4
+/// for errors.
+#[no_mangle]
+pub extern "C" fn LogRecord_get_level(_this: &LogRecord) -> u32 {
+    _this.level
+}
+
+/// Returns 0 if the auto splitter printed the message and 1 if the runtime
+/// logged it.
+#[no_mangle]
+pub extern "C" fn LogRecord_get_source(_this: &LogRecord) -> u32 {
+    _this.source as u32
+}
+
+#[no_mangle]
+pub extern "C" fn LogRecord_get_message(_this: &LogRecord) -> *const u8 {
+    output_str(&_this.message)
+}
+
+/// Like `LogRecord_get_message`, but writes the message into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the message
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn LogRecord_get_message_into(
+    _this: &LogRecord,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(_this.message.as_bytes(), _buf, _cap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_timer::{MockTimer, TimerEvent};
+
+    fn messages(logger: &Logger) -> Vec<Box<str>> {
+        (0..logger.records_len())
+            .map(|index| logger.record(index).unwrap().message)
+            .collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_records() {
+        let timer = MockTimer::new(1);
+        let logger = Logger::new(MockTimer::vtable(), timer.user_data());
+
+        for index in 0..LOG_CAPACITY + 2 {
+            logger.log_with_level(LogSource::Runtime, INFO_LEVEL, format_args!("{index}"));
+        }
+
+        let messages = messages(&logger);
+        assert_eq!(messages.len(), LOG_CAPACITY);
+        assert_eq!(&*messages[0], "2");
+        assert_eq!(*messages[LOG_CAPACITY - 1], *(LOG_CAPACITY + 1).to_string());
+        assert!(logger.record(LOG_CAPACITY).is_none());
+    }
+
+    #[test]
+    fn keeps_records_below_the_min_level() {
+        let timer = MockTimer::new(1);
+        let logger = Logger::new(MockTimer::vtable(), timer.user_data());
+        logger.set_min_level(ERROR_LEVEL);
+
+        logger.log_with_level(LogSource::Runtime, INFO_LEVEL, format_args!("info"));
+        logger.log_with_level(LogSource::Runtime, ERROR_LEVEL, format_args!("error"));
+
+        assert_eq!(messages(&logger), [Box::from("info"), Box::from("error")]);
+        assert_eq!(timer.runtime_logs(), [(ERROR_LEVEL, Box::from("error"))]);
+
+        logger.clear_records();
+        assert_eq!(logger.records_len(), 0);
+    }
+
+    #[test]
+    fn passes_unleveled_messages_on_as_info_messages_of_the_auto_splitter() {
+        let timer = MockTimer::new(1);
+        let message = "message";
+
+        unsafe {
+            log_unleveled(
+                MockTimer::vtable().log_leveled.unwrap(),
+                timer.user_data(),
+                message.as_ptr(),
+                message.len(),
+            );
+        }
+
+        assert_eq!(timer.event_list(), [TimerEvent::Log("message".into())]);
+        assert!(timer.runtime_logs().is_empty());
+    }
+}
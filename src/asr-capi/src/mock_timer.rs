@@ -0,0 +1,400 @@
+//! A timer implemented purely in Rust, so the runtime can be driven without a
+//! host. It provides the callbacks of a [`TimerVTable`] and behaves like the
+//! timer in LiveSplit, while recording everything the auto splitter does to it.
+
+use crate::{
+    log,
+    timer::{lossy_str, TimerVTable},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::c_void,
+    sync::Mutex,
+};
+
+/// The state of a [`MockTimer`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MockTimerState {
+    NotRunning,
+    Running,
+    Paused,
+    Ended,
+}
+
+/// Something the auto splitter did to the timer. Actions that the timer
+/// ignored, like splitting while it isn't running, are not recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimerEvent {
+    Start,
+    Split,
+    SkipSplit,
+    UndoSplit,
+    Reset,
+    /// The game time in ticks of 100 nanoseconds.
+    SetGameTime(i64),
+    PauseGameTime,
+    ResumeGameTime,
+    SetVariable(Box<str>, Box<str>),
+    /// A message that the auto splitter printed.
+    Log(Box<str>),
+}
+
+/// Something the runner does to the timer in between the steps of the auto
+/// splitter. These can be scheduled with [`MockTimer::schedule`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimerAction {
+    Start,
+    Split,
+    SkipSplit,
+    UndoSplit,
+    Reset,
+    Pause,
+    Resume,
+}
+
+/// A timer for running auto splitters without a host. Pass
+/// [`MockTimer::vtable`] and [`MockTimer::user_data`] to `Runtime_new`. The
+/// timer needs to outlive the runtime.
+///
+/// Call [`MockTimer::begin_step`] before every step of the auto splitter, so
+/// the scheduled actions are applied and the events are attributed to the
+/// right step.
+pub struct MockTimer {
+    state: Mutex<State>,
+}
+
+struct State {
+    timer_state: MockTimerState,
+    segments: usize,
+    index: usize,
+    splitted: Vec<bool>,
+    game_time: Option<i64>,
+    game_time_paused: bool,
+    variables: HashMap<Box<str>, Box<str>>,
+    step: u64,
+    timeline: BTreeMap<u64, Vec<TimerAction>>,
+    events: Vec<(u64, TimerEvent)>,
+    runtime_logs: Vec<(u32, Box<str>)>,
+}
+
+impl State {
+    fn apply(&mut self, action: TimerAction) -> bool {
+        match action {
+            TimerAction::Start => {
+                if self.timer_state != MockTimerState::NotRunning {
+                    return false;
+                }
+                self.timer_state = MockTimerState::Running;
+                self.index = 0;
+                self.splitted.clear();
+                self.game_time = None;
+                self.game_time_paused = false;
+            }
+            TimerAction::Split => {
+                if self.timer_state != MockTimerState::Running {
+                    return false;
+                }
+                self.splitted.push(true);
+                self.index += 1;
+                if self.index >= self.segments {
+                    self.timer_state = MockTimerState::Ended;
+                }
+            }
+            TimerAction::SkipSplit => {
+                if !matches!(
+                    self.timer_state,
+                    MockTimerState::Running | MockTimerState::Paused
+                ) || self.index + 1 >= self.segments
+                {
+                    return false;
+                }
+                self.splitted.push(false);
+                self.index += 1;
+            }
+            TimerAction::UndoSplit => {
+                if self.timer_state == MockTimerState::NotRunning || self.index == 0 {
+                    return false;
+                }
+                if self.timer_state == MockTimerState::Ended {
+                    self.timer_state = MockTimerState::Running;
+                }
+                self.splitted.pop();
+                self.index -= 1;
+            }
+            TimerAction::Reset => {
+                if self.timer_state == MockTimerState::NotRunning {
+                    return false;
+                }
+                self.timer_state = MockTimerState::NotRunning;
+                self.index = 0;
+                self.splitted.clear();
+            }
+            TimerAction::Pause => {
+                if self.timer_state != MockTimerState::Running {
+                    return false;
+                }
+                self.timer_state = MockTimerState::Paused;
+            }
+            TimerAction::Resume => {
+                if self.timer_state != MockTimerState::Paused {
+                    return false;
+                }
+                self.timer_state = MockTimerState::Running;
+            }
+        }
+        true
+    }
+
+    fn record(&mut self, event: TimerEvent) {
+        self.events.push((self.step, event));
+    }
+}
+
+impl MockTimer {
+    /// Creates a timer that isn't running, with splits consisting of the
+    /// amount of segments provided. The run ends once all of them are split.
+    pub fn new(segments: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                timer_state: MockTimerState::NotRunning,
+                segments: segments.max(1),
+                index: 0,
+                splitted: Vec::new(),
+                game_time: None,
+                game_time_paused: false,
+                variables: HashMap::new(),
+                step: 0,
+                timeline: BTreeMap::new(),
+                events: Vec::new(),
+                runtime_logs: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns the vtable with the callbacks of the mock timer.
+    pub fn vtable() -> &'static TimerVTable {
+        static VTABLE: TimerVTable = TimerVTable {
+            size: std::mem::size_of::<TimerVTable>(),
+            state,
+            index,
+            segment_splitted,
+            start,
+            split,
+            skip_split,
+            undo_split,
+            reset,
+            set_game_time,
+            pause_game_time,
+            resume_game_time,
+            set_custom_variable,
+            log,
+            log_leveled: Some(log_leveled),
+        };
+        &VTABLE
+    }
+
+    /// Returns the user data to pass alongside the vtable.
+    pub fn user_data(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    /// Schedules the action to be applied right before the step provided. The
+    /// first step is step 1.
+    pub fn schedule(&self, step: u64, action: TimerAction) {
+        let mut state = self.state.lock().unwrap();
+        state.timeline.entry(step).or_default().push(action);
+    }
+
+    /// Advances to the next step and applies the actions scheduled for it.
+    /// Returns the number of the step.
+    pub fn begin_step(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.step += 1;
+        let step = state.step;
+        for action in state.timeline.remove(&step).unwrap_or_default() {
+            state.apply(action);
+        }
+        step
+    }
+
+    /// Returns the events recorded so far, each with the step it happened in.
+    /// Events that happen before the first step are attributed to step 0.
+    pub fn events(&self) -> Vec<(u64, TimerEvent)> {
+        self.state.lock().unwrap().events.clone()
+    }
+
+    /// Removes the events recorded so far and returns them, each with the step
+    /// it happened in.
+    pub fn take_events(&self) -> Vec<(u64, TimerEvent)> {
+        std::mem::take(&mut self.state.lock().unwrap().events)
+    }
+
+    /// Returns the events recorded so far, without the steps they happened in.
+    pub fn event_list(&self) -> Vec<TimerEvent> {
+        let state = self.state.lock().unwrap();
+        state
+            .events
+            .iter()
+            .map(|(_, event)| event.clone())
+            .collect()
+    }
+
+    /// Returns the messages logged by the runtime itself with their levels.
+    pub fn runtime_logs(&self) -> Vec<(u32, Box<str>)> {
+        self.state.lock().unwrap().runtime_logs.clone()
+    }
+
+    pub fn state(&self) -> MockTimerState {
+        self.state.lock().unwrap().timer_state
+    }
+
+    /// Returns the index of the current segment, or [`None`] if the timer
+    /// isn't running.
+    pub fn split_index(&self) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        match state.timer_state {
+            MockTimerState::NotRunning => None,
+            _ => Some(state.index),
+        }
+    }
+
+    /// Returns whether the segment provided got split, or [`None`] if it
+    /// wasn't reached yet. Skipped segments are not split.
+    pub fn segment_splitted(&self, index: usize) -> Option<bool> {
+        self.state.lock().unwrap().splitted.get(index).copied()
+    }
+
+    /// Returns the game time in ticks of 100 nanoseconds, if the auto splitter
+    /// set it during the current attempt.
+    pub fn game_time(&self) -> Option<i64> {
+        self.state.lock().unwrap().game_time
+    }
+
+    pub fn is_game_time_paused(&self) -> bool {
+        self.state.lock().unwrap().game_time_paused
+    }
+
+    pub fn variable(&self, name: &str) -> Option<Box<str>> {
+        self.state.lock().unwrap().variables.get(name).cloned()
+    }
+
+    fn with_state<R>(user_data: *mut c_void, f: impl FnOnce(&mut State) -> R) -> R {
+        // SAFETY: The user data is the pointer returned by `user_data`, and
+        // the timer outlives the runtime.
+        let this = unsafe { &*(user_data as *const Self) };
+        f(&mut this.state.lock().unwrap())
+    }
+
+    fn action(user_data: *mut c_void, action: TimerAction, event: TimerEvent) {
+        Self::with_state(user_data, |state| {
+            if state.apply(action) {
+                state.record(event);
+            }
+        });
+    }
+}
+
+unsafe extern "C" fn state(user_data: *mut c_void) -> i32 {
+    MockTimer::with_state(user_data, |state| match state.timer_state {
+        MockTimerState::NotRunning => 0,
+        MockTimerState::Running => 1,
+        MockTimerState::Paused => 2,
+        MockTimerState::Ended => 3,
+    })
+}
+
+unsafe extern "C" fn index(user_data: *mut c_void) -> i32 {
+    MockTimer::with_state(user_data, |state| match state.timer_state {
+        MockTimerState::NotRunning => -1,
+        _ => state.index as i32,
+    })
+}
+
+unsafe extern "C" fn segment_splitted(user_data: *mut c_void, index: i32) -> i32 {
+    MockTimer::with_state(user_data, |state| {
+        match usize::try_from(index)
+            .ok()
+            .and_then(|index| state.splitted.get(index))
+        {
+            Some(true) => 1,
+            Some(false) => 0,
+            None => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn start(user_data: *mut c_void) {
+    MockTimer::action(user_data, TimerAction::Start, TimerEvent::Start);
+}
+
+unsafe extern "C" fn split(user_data: *mut c_void) {
+    MockTimer::action(user_data, TimerAction::Split, TimerEvent::Split);
+}
+
+unsafe extern "C" fn skip_split(user_data: *mut c_void) {
+    MockTimer::action(user_data, TimerAction::SkipSplit, TimerEvent::SkipSplit);
+}
+
+unsafe extern "C" fn undo_split(user_data: *mut c_void) {
+    MockTimer::action(user_data, TimerAction::UndoSplit, TimerEvent::UndoSplit);
+}
+
+unsafe extern "C" fn reset(user_data: *mut c_void) {
+    MockTimer::action(user_data, TimerAction::Reset, TimerEvent::Reset);
+}
+
+unsafe extern "C" fn set_game_time(user_data: *mut c_void, ticks: i64) {
+    MockTimer::with_state(user_data, |state| {
+        state.game_time = Some(ticks);
+        state.record(TimerEvent::SetGameTime(ticks));
+    });
+}
+
+unsafe extern "C" fn pause_game_time(user_data: *mut c_void) {
+    MockTimer::with_state(user_data, |state| {
+        state.game_time_paused = true;
+        state.record(TimerEvent::PauseGameTime);
+    });
+}
+
+unsafe extern "C" fn resume_game_time(user_data: *mut c_void) {
+    MockTimer::with_state(user_data, |state| {
+        state.game_time_paused = false;
+        state.record(TimerEvent::ResumeGameTime);
+    });
+}
+
+unsafe extern "C" fn set_custom_variable(
+    user_data: *mut c_void,
+    name_ptr: *const u8,
+    name_len: usize,
+    value_ptr: *const u8,
+    value_len: usize,
+) {
+    let (name, value) = (
+        lossy_str(name_ptr, name_len),
+        lossy_str(value_ptr, value_len),
+    );
+    MockTimer::with_state(user_data, |state| {
+        state.variables.insert(name.clone(), value.clone());
+        state.record(TimerEvent::SetVariable(name, value));
+    });
+}
+
+unsafe extern "C" fn log(user_data: *mut c_void, message_ptr: *const u8, message_len: usize) {
+    log::log_unleveled(log_leveled, user_data, message_ptr, message_len);
+}
+
+unsafe extern "C" fn log_leveled(
+    user_data: *mut c_void,
+    source: u32,
+    level: u32,
+    message_ptr: *const u8,
+    message_len: usize,
+) {
+    let message = lossy_str(message_ptr, message_len);
+    MockTimer::with_state(user_data, |state| match source {
+        0 => state.record(TimerEvent::Log(message)),
+        _ => state.runtime_logs.push((level, message)),
+    });
+}
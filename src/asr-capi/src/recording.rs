@@ -0,0 +1,839 @@
+//! Records how an auto splitter interacts with the timer, so its behavior can
+//! be reproduced later on. Every query is stored together with the answer of
+//! the host and every action is stored as is, both alongside the tick and the
+//! wall-clock time they happened at. A [`Replay`] then answers the queries of
+//! an auto splitter with the recorded state of the timer and reports the ticks
+//! at which its actions differ from the recorded ones.
+//!
+//! A recording starts with the magic bytes `ASRREC`, a version byte and the
+//! tick the recording starts at. Every entry consists of the difference of its
+//! tick and its wall-clock time in microseconds to the previous entry, followed
+//! by a byte for the kind of interaction and its arguments. Integers are stored
+//! as LEB128 varints, with signed ones being zigzag encoded, and strings are
+//! prefixed by their length. A recording that was stopped properly ends with an
+//! entry of kind 255, which marks the last tick of the recording.
+
+use crate::{
+    log::{LogSource, Logger, ERROR_LEVEL},
+    mock_timer::TimerEvent,
+    timer::{lossy_str, TimerVTable},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::c_void,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{self, AtomicBool, AtomicU64},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const MAGIC: &[u8; 6] = b"ASRREC";
+const VERSION: u8 = 1;
+const END: u8 = 255;
+
+/// A query of the auto splitter along with the answer of the host, or an
+/// action of the auto splitter.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Interaction {
+    State(i32),
+    Index(i32),
+    SegmentSplitted {
+        index: i32,
+        splitted: i32,
+    },
+    /// An action, which is never a [`TimerEvent::Log`].
+    Action(TimerEvent),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingEntry {
+    /// The step of the auto splitter the interaction happened in. Steps are
+    /// counted from the creation of the runtime, starting at 1.
+    pub tick: u64,
+    /// The wall-clock time since the recording started.
+    pub wall_time: Duration,
+    pub interaction: Interaction,
+}
+
+/// A recording as written by `Runtime_start_recording`.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub first_tick: u64,
+    /// The last tick that got recorded. If the recording wasn't stopped
+    /// properly, this is the tick of the last entry.
+    pub last_tick: u64,
+    pub entries: Vec<RecordingEntry>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    /// Decodes the recording. A truncated entry at the end, as left behind by
+    /// a host that crashed while recording, is ignored.
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { buf: bytes };
+        if reader.take(MAGIC.len())? != MAGIC || reader.u8()? != VERSION {
+            return Err(invalid_data());
+        }
+
+        let first_tick = reader.varint()?;
+        let mut recording = Self {
+            first_tick,
+            last_tick: first_tick,
+            entries: Vec::new(),
+        };
+        let (mut tick, mut micros) = (first_tick, 0u64);
+
+        while !reader.buf.is_empty() {
+            match reader.entry(&mut tick, &mut micros) {
+                Ok(Some(entry)) => {
+                    recording.last_tick = entry.tick;
+                    recording.entries.push(entry);
+                }
+                Ok(None) => {
+                    recording.last_tick = tick;
+                    break;
+                }
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(recording)
+    }
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed recording.")
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn write_signed(buf: &mut Vec<u8>, v: i64) {
+    write_varint(buf, ((v << 1) ^ (v >> 63)) as u64);
+}
+
+fn write_str(buf: &mut Vec<u8>, v: &str) {
+    write_varint(buf, v.len() as u64);
+    buf.extend_from_slice(v.as_bytes());
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            v |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(invalid_data())
+    }
+
+    fn signed(&mut self) -> io::Result<i64> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        i32::try_from(self.signed()?).map_err(|_| invalid_data())
+    }
+
+    fn str(&mut self) -> io::Result<Box<str>> {
+        let len = usize::try_from(self.varint()?).map_err(|_| invalid_data())?;
+        let bytes = self.take(len)?;
+        Ok(String::from_utf8_lossy(bytes).into())
+    }
+
+    /// Reads the next entry, or returns [`None`] if the end marker is reached,
+    /// in which case `tick` is the last tick of the recording.
+    fn entry(&mut self, tick: &mut u64, micros: &mut u64) -> io::Result<Option<RecordingEntry>> {
+        *tick = tick.saturating_add(self.varint()?);
+        *micros = micros.saturating_add(self.varint()?);
+        let interaction = match self.u8()? {
+            0 => Interaction::State(self.i32()?),
+            1 => Interaction::Index(self.i32()?),
+            2 => Interaction::SegmentSplitted {
+                index: self.i32()?,
+                splitted: self.i32()?,
+            },
+            3 => Interaction::Action(TimerEvent::Start),
+            4 => Interaction::Action(TimerEvent::Split),
+            5 => Interaction::Action(TimerEvent::SkipSplit),
+            6 => Interaction::Action(TimerEvent::UndoSplit),
+            7 => Interaction::Action(TimerEvent::Reset),
+            8 => Interaction::Action(TimerEvent::SetGameTime(self.signed()?)),
+            9 => Interaction::Action(TimerEvent::PauseGameTime),
+            10 => Interaction::Action(TimerEvent::ResumeGameTime),
+            11 => Interaction::Action(TimerEvent::SetVariable(self.str()?, self.str()?)),
+            END => return Ok(None),
+            _ => return Err(invalid_data()),
+        };
+        Ok(Some(RecordingEntry {
+            tick: *tick,
+            wall_time: Duration::from_micros(*micros),
+            interaction,
+        }))
+    }
+}
+
+/// Writes the entries of a recording to its file.
+struct Recorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    tick: u64,
+    micros: u64,
+    buf: Vec<u8>,
+}
+
+impl Recorder {
+    fn create(path: &Path, first_tick: u64) -> io::Result<Self> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.push(VERSION);
+        write_varint(&mut buf, first_tick);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&buf)?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            tick: first_tick,
+            micros: 0,
+            buf,
+        })
+    }
+
+    fn begin_entry(&mut self, tick: u64, kind: u8) {
+        let micros = u64::try_from(self.started.elapsed().as_micros()).unwrap_or(u64::MAX);
+        self.buf.clear();
+        write_varint(&mut self.buf, tick.saturating_sub(self.tick));
+        write_varint(&mut self.buf, micros.saturating_sub(self.micros));
+        self.buf.push(kind);
+        self.tick = tick;
+        self.micros = micros;
+    }
+
+    fn record(&mut self, tick: u64, interaction: &Interaction) -> io::Result<()> {
+        match interaction {
+            Interaction::State(state) => {
+                self.begin_entry(tick, 0);
+                write_signed(&mut self.buf, (*state).into());
+            }
+            Interaction::Index(index) => {
+                self.begin_entry(tick, 1);
+                write_signed(&mut self.buf, (*index).into());
+            }
+            Interaction::SegmentSplitted { index, splitted } => {
+                self.begin_entry(tick, 2);
+                write_signed(&mut self.buf, (*index).into());
+                write_signed(&mut self.buf, (*splitted).into());
+            }
+            Interaction::Action(event) => match event {
+                TimerEvent::Start => self.begin_entry(tick, 3),
+                TimerEvent::Split => self.begin_entry(tick, 4),
+                TimerEvent::SkipSplit => self.begin_entry(tick, 5),
+                TimerEvent::UndoSplit => self.begin_entry(tick, 6),
+                TimerEvent::Reset => self.begin_entry(tick, 7),
+                TimerEvent::SetGameTime(ticks) => {
+                    self.begin_entry(tick, 8);
+                    write_signed(&mut self.buf, *ticks);
+                }
+                TimerEvent::PauseGameTime => self.begin_entry(tick, 9),
+                TimerEvent::ResumeGameTime => self.begin_entry(tick, 10),
+                TimerEvent::SetVariable(name, value) => {
+                    self.begin_entry(tick, 11);
+                    write_str(&mut self.buf, name);
+                    write_str(&mut self.buf, value);
+                }
+                TimerEvent::Log(_) => return Ok(()),
+            },
+        }
+        self.writer.write_all(&self.buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn finish(mut self, last_tick: u64) -> io::Result<()> {
+        self.begin_entry(last_tick, END);
+        self.writer.write_all(&self.buf)?;
+        self.writer.flush()
+    }
+}
+
+/// Sits in between the runtime and the timer of the host. It forwards all the
+/// callbacks to the host and records them while a recording is active.
+pub struct TimerTap {
+    vtable: TimerVTable,
+    user_data: *mut c_void,
+    logger: Arc<Logger>,
+    tick: AtomicU64,
+    recording: AtomicBool,
+    recorder: Mutex<Option<Recorder>>,
+}
+
+// SAFETY: The host is responsible for making its callbacks and the user data
+// usable from the threads that it drives the runtime from.
+unsafe impl Send for TimerTap {}
+unsafe impl Sync for TimerTap {}
+
+impl TimerTap {
+    pub fn new(vtable: TimerVTable, user_data: *mut c_void, logger: Arc<Logger>) -> Self {
+        Self {
+            vtable,
+            user_data,
+            logger,
+            tick: AtomicU64::new(0),
+            recording: AtomicBool::new(false),
+            recorder: Mutex::new(None),
+        }
+    }
+
+    /// Returns the vtable that forwards to the timer of the host. It needs to
+    /// be used with [`TimerTap::user_data`].
+    pub fn vtable() -> &'static TimerVTable {
+        static VTABLE: TimerVTable = TimerVTable {
+            size: std::mem::size_of::<TimerVTable>(),
+            state,
+            index,
+            segment_splitted,
+            start,
+            split,
+            skip_split,
+            undo_split,
+            reset,
+            set_game_time,
+            pause_game_time,
+            resume_game_time,
+            set_custom_variable,
+            log,
+            log_leveled: Some(log_leveled),
+        };
+        &VTABLE
+    }
+
+    pub fn user_data(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    /// Needs to be called before every step of the auto splitter, so the
+    /// interactions are attributed to the right tick.
+    pub fn begin_step(&self) {
+        self.tick.fetch_add(1, atomic::Ordering::Relaxed);
+        if self.recording.load(atomic::Ordering::Relaxed) {
+            // Flush what the previous step recorded, so the recording survives
+            // a crash of the host.
+            self.with_recorder(Recorder::flush);
+        }
+    }
+
+    /// Starts recording into the file at the path provided, beginning with the
+    /// next step. A recording that is already active gets stopped first.
+    pub fn start_recording(&self, path: &Path) -> io::Result<()> {
+        let mut recorder = self.recorder.lock().unwrap();
+        let tick = self.tick.load(atomic::Ordering::Relaxed);
+        if let Some(previous) = recorder.take() {
+            self.recording.store(false, atomic::Ordering::Relaxed);
+            previous.finish(tick)?;
+        }
+        *recorder = Some(Recorder::create(path, tick + 1)?);
+        self.recording.store(true, atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Stops the recording and writes the end marker. Does nothing if there
+    /// is no active recording.
+    pub fn stop_recording(&self) -> io::Result<()> {
+        let recorder = self.recorder.lock().unwrap().take();
+        self.recording.store(false, atomic::Ordering::Relaxed);
+        match recorder {
+            Some(recorder) => recorder.finish(self.tick.load(atomic::Ordering::Relaxed)),
+            None => Ok(()),
+        }
+    }
+
+    fn record(&self, interaction: impl FnOnce() -> Interaction) {
+        if self.recording.load(atomic::Ordering::Relaxed) {
+            let tick = self.tick.load(atomic::Ordering::Relaxed);
+            self.with_recorder(|recorder| recorder.record(tick, &interaction()));
+        }
+    }
+
+    /// Runs the function on the active recorder. If writing fails, the
+    /// recording is stopped and the error is logged.
+    fn with_recorder(&self, f: impl FnOnce(&mut Recorder) -> io::Result<()>) {
+        let mut recorder = self.recorder.lock().unwrap();
+        let Some(active) = &mut *recorder else {
+            return;
+        };
+        if let Err(err) = f(active) {
+            *recorder = None;
+            self.recording.store(false, atomic::Ordering::Relaxed);
+            self.logger.log_with_level(
+                LogSource::Runtime,
+                ERROR_LEVEL,
+                format_args!(
+                    "{:?}",
+                    anyhow::Error::from(err).context("Failed writing the recording."),
+                ),
+            );
+        }
+    }
+}
+
+impl Drop for TimerTap {
+    fn drop(&mut self) {
+        let _ = self.stop_recording();
+    }
+}
+
+unsafe fn tap<'a>(user_data: *mut c_void) -> &'a TimerTap {
+    &*(user_data as *const TimerTap)
+}
+
+unsafe extern "C" fn state(user_data: *mut c_void) -> i32 {
+    let tap = tap(user_data);
+    let state = (tap.vtable.state)(tap.user_data);
+    tap.record(|| Interaction::State(state));
+    state
+}
+
+unsafe extern "C" fn index(user_data: *mut c_void) -> i32 {
+    let tap = tap(user_data);
+    let index = (tap.vtable.index)(tap.user_data);
+    tap.record(|| Interaction::Index(index));
+    index
+}
+
+unsafe extern "C" fn segment_splitted(user_data: *mut c_void, index: i32) -> i32 {
+    let tap = tap(user_data);
+    let splitted = (tap.vtable.segment_splitted)(tap.user_data, index);
+    tap.record(|| Interaction::SegmentSplitted { index, splitted });
+    splitted
+}
+
+unsafe extern "C" fn start(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.start)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::Start));
+}
+
+unsafe extern "C" fn split(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.split)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::Split));
+}
+
+unsafe extern "C" fn skip_split(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.skip_split)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::SkipSplit));
+}
+
+unsafe extern "C" fn undo_split(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.undo_split)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::UndoSplit));
+}
+
+unsafe extern "C" fn reset(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.reset)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::Reset));
+}
+
+unsafe extern "C" fn set_game_time(user_data: *mut c_void, ticks: i64) {
+    let tap = tap(user_data);
+    (tap.vtable.set_game_time)(tap.user_data, ticks);
+    tap.record(|| Interaction::Action(TimerEvent::SetGameTime(ticks)));
+}
+
+unsafe extern "C" fn pause_game_time(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.pause_game_time)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::PauseGameTime));
+}
+
+unsafe extern "C" fn resume_game_time(user_data: *mut c_void) {
+    let tap = tap(user_data);
+    (tap.vtable.resume_game_time)(tap.user_data);
+    tap.record(|| Interaction::Action(TimerEvent::ResumeGameTime));
+}
+
+unsafe extern "C" fn set_custom_variable(
+    user_data: *mut c_void,
+    name_ptr: *const u8,
+    name_len: usize,
+    value_ptr: *const u8,
+    value_len: usize,
+) {
+    let tap = tap(user_data);
+    (tap.vtable.set_custom_variable)(tap.user_data, name_ptr, name_len, value_ptr, value_len);
+    tap.record(|| {
+        Interaction::Action(TimerEvent::SetVariable(
+            lossy_str(name_ptr, name_len),
+            lossy_str(value_ptr, value_len),
+        ))
+    });
+}
+
+unsafe extern "C" fn log(user_data: *mut c_void, message_ptr: *const u8, message_len: usize) {
+    let tap = tap(user_data);
+    (tap.vtable.log)(tap.user_data, message_ptr, message_len);
+}
+
+unsafe extern "C" fn log_leveled(
+    user_data: *mut c_void,
+    source: u32,
+    level: u32,
+    message_ptr: *const u8,
+    message_len: usize,
+) {
+    let tap = tap(user_data);
+    match tap.vtable.log_leveled {
+        Some(log_leveled) => log_leveled(tap.user_data, source, level, message_ptr, message_len),
+        None => (tap.vtable.log)(tap.user_data, message_ptr, message_len),
+    }
+}
+
+/// A tick at which the actions of the auto splitter differ from the recorded
+/// ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    pub tick: u64,
+    /// The wall-clock time of the tick within the recording.
+    pub wall_time: Duration,
+    pub expected: Vec<TimerEvent>,
+    pub actual: Vec<TimerEvent>,
+}
+
+/// A timer that replays a [`Recording`] to an auto splitter. Pass
+/// [`Replay::vtable`] and [`Replay::user_data`] to `Runtime_new` and call
+/// [`Replay::begin_step`] before every step of the auto splitter, until it
+/// returns [`None`]. The replay needs to outlive the runtime.
+///
+/// Only the state of the timer is replayed. The n-th query of a kind within a
+/// tick is answered with the n-th recorded answer in that tick, or the most
+/// recent answer if there are fewer of them. The actions of the auto splitter
+/// don't affect the answers, so once it diverges from the recording, the
+/// following ticks may diverge as well.
+pub struct Replay {
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Query {
+    State,
+    Index,
+    SegmentSplitted(i32),
+}
+
+impl Query {
+    /// The answer if the recording doesn't know about the query yet.
+    fn default_answer(self) -> i32 {
+        match self {
+            Query::State => 0,
+            Query::Index | Query::SegmentSplitted(_) => -1,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RecordedTick {
+    wall_time: Duration,
+    answers: HashMap<Query, Vec<i32>>,
+    actions: Vec<TimerEvent>,
+}
+
+struct ReplayState {
+    first_tick: u64,
+    last_tick: u64,
+    ticks: BTreeMap<u64, RecordedTick>,
+    tick: Option<u64>,
+    /// The most recent answers of the ticks before the current one.
+    answers: HashMap<Query, i32>,
+    /// How many queries of each kind were answered in the current tick.
+    answered: HashMap<Query, usize>,
+    actual: BTreeMap<u64, Vec<TimerEvent>>,
+}
+
+impl ReplayState {
+    fn answer(&mut self, query: Query) -> i32 {
+        let recorded = self
+            .tick
+            .and_then(|tick| self.ticks.get(&tick))
+            .and_then(|tick| tick.answers.get(&query));
+        match recorded {
+            Some(answers) => {
+                let answered = self.answered.entry(query).or_default();
+                let answer = answers[(*answered).min(answers.len() - 1)];
+                *answered += 1;
+                answer
+            }
+            None => self
+                .answers
+                .get(&query)
+                .copied()
+                .unwrap_or(query.default_answer()),
+        }
+    }
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        let mut ticks = BTreeMap::<u64, RecordedTick>::new();
+        for entry in recording.entries {
+            let tick = ticks.entry(entry.tick).or_insert_with(|| RecordedTick {
+                wall_time: entry.wall_time,
+                ..Default::default()
+            });
+            let (query, answer) = match entry.interaction {
+                Interaction::State(state) => (Query::State, state),
+                Interaction::Index(index) => (Query::Index, index),
+                Interaction::SegmentSplitted { index, splitted } => {
+                    (Query::SegmentSplitted(index), splitted)
+                }
+                Interaction::Action(event) => {
+                    tick.actions.push(event);
+                    continue;
+                }
+            };
+            tick.answers.entry(query).or_default().push(answer);
+        }
+
+        Self {
+            state: Mutex::new(ReplayState {
+                first_tick: recording.first_tick,
+                last_tick: recording.last_tick,
+                ticks,
+                tick: None,
+                answers: HashMap::new(),
+                answered: HashMap::new(),
+                actual: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Returns the vtable with the callbacks of the replay.
+    pub fn vtable() -> &'static TimerVTable {
+        static VTABLE: TimerVTable = TimerVTable {
+            size: std::mem::size_of::<TimerVTable>(),
+            state: replay_state,
+            index: replay_index,
+            segment_splitted: replay_segment_splitted,
+            start: replay_start,
+            split: replay_split,
+            skip_split: replay_skip_split,
+            undo_split: replay_undo_split,
+            reset: replay_reset,
+            set_game_time: replay_set_game_time,
+            pause_game_time: replay_pause_game_time,
+            resume_game_time: replay_resume_game_time,
+            set_custom_variable: replay_set_custom_variable,
+            log: replay_log,
+            log_leveled: None,
+        };
+        &VTABLE
+    }
+
+    /// Returns the user data to pass alongside the vtable.
+    pub fn user_data(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    /// Advances to the next tick of the recording and returns it, or returns
+    /// [`None`] if all the ticks were replayed.
+    pub fn begin_step(&self) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        let next = state.tick.map_or(state.first_tick, |tick| tick + 1);
+        if next > state.last_tick {
+            return None;
+        }
+
+        let state = &mut *state;
+        if let Some(previous) = state.tick.and_then(|tick| state.ticks.get(&tick)) {
+            for (query, answers) in &previous.answers {
+                if let Some(&answer) = answers.last() {
+                    state.answers.insert(*query, answer);
+                }
+            }
+        }
+        state.answered.clear();
+        state.tick = Some(next);
+        Some(next)
+    }
+
+    /// Answers a query for the state of the timer, using the same values as
+    /// the `state` callback of the [`TimerVTable`].
+    pub fn state(&self) -> i32 {
+        self.state.lock().unwrap().answer(Query::State)
+    }
+
+    /// Answers a query for the index of the current segment, using the same
+    /// values as the `index` callback of the [`TimerVTable`].
+    pub fn index(&self) -> i32 {
+        self.state.lock().unwrap().answer(Query::Index)
+    }
+
+    /// Answers a query for whether a segment got split, using the same values
+    /// as the `segment_splitted` callback of the [`TimerVTable`].
+    pub fn segment_splitted(&self, index: i32) -> i32 {
+        self.state
+            .lock()
+            .unwrap()
+            .answer(Query::SegmentSplitted(index))
+    }
+
+    /// Stores an action of the auto splitter for the current tick. Log
+    /// messages are ignored.
+    pub fn act(&self, event: TimerEvent) {
+        if matches!(event, TimerEvent::Log(_)) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let tick = state.tick.unwrap_or(state.first_tick);
+        state.actual.entry(tick).or_default().push(event);
+    }
+
+    /// Compares the actions of the auto splitter to the recorded ones. Only
+    /// the ticks that were replayed so far are compared.
+    pub fn divergences(&self) -> Vec<Divergence> {
+        let state = self.state.lock().unwrap();
+        let Some(current) = state.tick else {
+            return Vec::new();
+        };
+
+        let mut ticks = state
+            .ticks
+            .iter()
+            .filter(|(_, tick)| !tick.actions.is_empty())
+            .map(|(&tick, _)| tick)
+            .chain(state.actual.keys().copied())
+            .filter(|&tick| tick <= current)
+            .collect::<Vec<_>>();
+        ticks.sort_unstable();
+        ticks.dedup();
+
+        ticks
+            .into_iter()
+            .filter_map(|tick| {
+                let expected = state
+                    .ticks
+                    .get(&tick)
+                    .map_or(&[][..], |recorded| &recorded.actions);
+                let actual = state.actual.get(&tick).map_or(&[][..], |actual| actual);
+                if expected == actual {
+                    return None;
+                }
+                let wall_time = state
+                    .ticks
+                    .range(..=tick)
+                    .next_back()
+                    .map_or(Duration::ZERO, |(_, recorded)| recorded.wall_time);
+                Some(Divergence {
+                    tick,
+                    wall_time,
+                    expected: expected.to_vec(),
+                    actual: actual.to_vec(),
+                })
+            })
+            .collect()
+    }
+}
+
+unsafe fn replay<'a>(user_data: *mut c_void) -> &'a Replay {
+    &*(user_data as *const Replay)
+}
+
+unsafe extern "C" fn replay_state(user_data: *mut c_void) -> i32 {
+    replay(user_data).state()
+}
+
+unsafe extern "C" fn replay_index(user_data: *mut c_void) -> i32 {
+    replay(user_data).index()
+}
+
+unsafe extern "C" fn replay_segment_splitted(user_data: *mut c_void, index: i32) -> i32 {
+    replay(user_data).segment_splitted(index)
+}
+
+unsafe extern "C" fn replay_start(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::Start);
+}
+
+unsafe extern "C" fn replay_split(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::Split);
+}
+
+unsafe extern "C" fn replay_skip_split(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::SkipSplit);
+}
+
+unsafe extern "C" fn replay_undo_split(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::UndoSplit);
+}
+
+unsafe extern "C" fn replay_reset(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::Reset);
+}
+
+unsafe extern "C" fn replay_set_game_time(user_data: *mut c_void, ticks: i64) {
+    replay(user_data).act(TimerEvent::SetGameTime(ticks));
+}
+
+unsafe extern "C" fn replay_pause_game_time(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::PauseGameTime);
+}
+
+unsafe extern "C" fn replay_resume_game_time(user_data: *mut c_void) {
+    replay(user_data).act(TimerEvent::ResumeGameTime);
+}
+
+unsafe extern "C" fn replay_set_custom_variable(
+    user_data: *mut c_void,
+    name_ptr: *const u8,
+    name_len: usize,
+    value_ptr: *const u8,
+    value_len: usize,
+) {
+    replay(user_data).act(TimerEvent::SetVariable(
+        lossy_str(name_ptr, name_len),
+        lossy_str(value_ptr, value_len),
+    ));
+}
+
+unsafe extern "C" fn replay_log(_: *mut c_void, _: *const u8, _: usize) {}
@@ -0,0 +1,725 @@
+//! The runtime can only be compiled for 64-bit targets. On all other targets
+//! the auto splitter instead runs in a 64-bit helper process that is spawned
+//! for every runtime, which the C API forwards its calls to over the helper's
+//! standard input and output. The timer callbacks and log messages of the
+//! auto splitter are sent back while the helper is handling a call. On 64-bit
+//! targets the same is done for runtimes that are configured to be sandboxed.
+
+use crate::{
+    error::{set_last_error, set_last_error_chain, ErrorKind},
+    ipc::{self, Callback, Decoder, Encoder, Message, RemoteError, Request, Response},
+    log::{LogSource, Logger},
+    runtime::report_error,
+    runtime_config::HelperConfig,
+    setting_value::SettingValue,
+    settings::Widget,
+    settings_map::SettingsMap,
+    timer::TimerVTable,
+    watchdog::Watchdog,
+};
+use std::{
+    ffi::c_void,
+    io::{self, BufReader},
+    path::PathBuf,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{self, AtomicBool, AtomicU64},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+/// The path of the helper executable, if the host overrode it.
+static HELPER_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The helper that is used for translating paths, as that doesn't need a
+/// runtime.
+#[cfg(not(target_pointer_width = "64"))]
+static PATH_HELPER: Mutex<Option<Helper>> = Mutex::new(None);
+
+pub fn set_helper_path(path: PathBuf) {
+    *HELPER_PATH.lock().unwrap() = Some(path);
+}
+
+/// Returns the path of the helper executable. Unless the host overrode it,
+/// the helper is expected to be next to this library.
+fn helper_path() -> PathBuf {
+    if let Some(path) = &*HELPER_PATH.lock().unwrap() {
+        return path.clone();
+    }
+    let mut path = module_dir()
+        .or_else(|| Some(std::env::current_exe().ok()?.parent()?.to_path_buf()))
+        .unwrap_or_default();
+    path.push("asr-helper");
+    path.set_extension(std::env::consts::EXE_EXTENSION);
+    path
+}
+
+/// Returns the directory that this library got loaded from.
+#[cfg(windows)]
+fn module_dir() -> Option<PathBuf> {
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt, ptr};
+
+    const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+    const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: u32 = 0x4;
+    const MAX_PATH_LEN: usize = 32768;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: *mut *mut c_void)
+            -> i32;
+        fn GetModuleFileNameW(module: *mut c_void, file_name: *mut u16, size: u32) -> u32;
+    }
+
+    unsafe {
+        let mut module = ptr::null_mut();
+        if GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            module_dir as fn() -> Option<PathBuf> as *const u16,
+            &mut module,
+        ) == 0
+        {
+            return None;
+        }
+
+        let mut buf = vec![0u16; MAX_PATH_LEN];
+        let len = GetModuleFileNameW(module, buf.as_mut_ptr(), MAX_PATH_LEN as u32) as usize;
+        if len == 0 || len >= MAX_PATH_LEN {
+            return None;
+        }
+
+        let mut path = PathBuf::from(OsString::from_wide(&buf[..len]));
+        path.pop();
+        Some(path)
+    }
+}
+
+#[cfg(not(windows))]
+fn module_dir() -> Option<PathBuf> {
+    None
+}
+
+/// A connection to a helper process. The process gets killed when the
+/// connection is dropped.
+struct Helper {
+    child: Arc<Mutex<Child>>,
+    stdin: Mutex<ChildStdin>,
+    stdout: Mutex<(BufReader<ChildStdout>, Vec<u8>)>,
+    /// The thread whose call is in progress and how many of its calls are
+    /// nested, as the host may call back into the runtime from a callback.
+    /// The calls of other threads wait until it's done, so the responses of
+    /// concurrent calls don't get mixed up.
+    caller: Mutex<Option<(ThreadId, usize)>>,
+    caller_done: Condvar,
+    /// Kills the process if it doesn't respond to a call within its time
+    /// limit, as reading the response would block forever otherwise. It's
+    /// paused while the host handles a callback.
+    watchdog: Arc<Watchdog>,
+    watchdog_thread: Mutex<Option<thread::JoinHandle<()>>>,
+    /// Whether the watchdog killed the process.
+    timed_out: Arc<AtomicBool>,
+}
+
+impl Helper {
+    fn spawn() -> io::Result<Self> {
+        let mut command = Command::new(helper_path());
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let mut child = command.spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::ErrorKind::BrokenPipe.into());
+        };
+
+        Ok(Self {
+            child: Arc::new(Mutex::new(child)),
+            stdin: Mutex::new(stdin),
+            stdout: Mutex::new((BufReader::new(stdout), Vec::new())),
+            caller: Mutex::new(None),
+            caller_done: Condvar::new(),
+            watchdog: Arc::new(Watchdog::new()),
+            watchdog_thread: Mutex::new(None),
+            timed_out: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    fn send(&self, request: &Request) -> io::Result<()> {
+        let mut encoder = Encoder::new();
+        encoder.request(request);
+        ipc::write_frame(&mut *self.stdin.lock().unwrap(), &encoder.finish())
+    }
+
+    /// Sends the request and waits for its response. The callbacks sent by the
+    /// helper in the meantime are passed to the function provided, which
+    /// returns the reply to them. If a time limit is provided and the helper
+    /// doesn't respond within it, the helper gets killed. The time the host
+    /// spends handling callbacks doesn't count towards the limit. Calls made
+    /// from within the callbacks are nested in the call and aren't watched.
+    fn call(
+        &self,
+        request: &Request,
+        time_limit: Option<Duration>,
+        mut on_callback: impl FnMut(Callback) -> i32,
+    ) -> io::Result<Response> {
+        let call = self.enter();
+
+        let Some(time_limit) = time_limit.filter(|_| !call.nested) else {
+            return self.exchange(request, on_callback);
+        };
+        self.watch(time_limit);
+        let response = self.exchange(request, |callback| {
+            self.watchdog.pause();
+            let reply = on_callback(callback);
+            self.watchdog.resume();
+            reply
+        });
+        self.watchdog.end_step();
+
+        if self.timed_out.load(atomic::Ordering::Relaxed) {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "The helper process exceeded the step budget.",
+            ));
+        }
+        response
+    }
+
+    /// Starts watching a call, spawning the watchdog thread if necessary.
+    fn watch(&self, time_limit: Duration) {
+        let mut watchdog_thread = self.watchdog_thread.lock().unwrap();
+        if watchdog_thread.is_none() {
+            let (watchdog, child, timed_out) = (
+                self.watchdog.clone(),
+                self.child.clone(),
+                self.timed_out.clone(),
+            );
+            // If the thread can't be spawned, the helper still enforces the
+            // step budget on its own, as long as it isn't hung.
+            *watchdog_thread = thread::Builder::new()
+                .name("Auto Splitter Helper Watchdog".into())
+                .spawn(move || {
+                    watchdog.run(|| {
+                        timed_out.store(true, atomic::Ordering::Relaxed);
+                        let _ = child.lock().unwrap().kill();
+                    })
+                })
+                .ok();
+        }
+        drop(watchdog_thread);
+
+        self.watchdog.set_budget(Some(time_limit));
+        self.watchdog.begin_step();
+    }
+
+    /// Waits until no other thread's call is in progress.
+    fn enter(&self) -> Call<'_> {
+        let current = thread::current().id();
+        let mut caller = self.caller.lock().unwrap();
+        loop {
+            match &mut *caller {
+                None => {
+                    *caller = Some((current, 0));
+                    return Call {
+                        helper: self,
+                        nested: false,
+                    };
+                }
+                Some((thread, nested)) if *thread == current => {
+                    *nested += 1;
+                    return Call {
+                        helper: self,
+                        nested: true,
+                    };
+                }
+                Some(_) => caller = self.caller_done.wait(caller).unwrap(),
+            }
+        }
+    }
+
+    fn exchange(
+        &self,
+        request: &Request,
+        mut on_callback: impl FnMut(Callback) -> i32,
+    ) -> io::Result<Response> {
+        self.send(request)?;
+        loop {
+            // The output isn't locked while the host handles the callback, so
+            // nested calls can read their responses.
+            let message = {
+                let mut stdout = self.stdout.lock().unwrap();
+                let (reader, buf) = &mut *stdout;
+                ipc::read_frame(reader, buf, ipc::MAX_MESSAGE_LEN)?;
+                Decoder::new(buf).message()?
+            };
+            match message {
+                Message::Callback(callback) => {
+                    let reply = on_callback(callback);
+                    self.send(&Request::TimerReply(reply))?;
+                }
+                Message::Response(response) => return Ok(response),
+            }
+        }
+    }
+}
+
+/// A call that is in progress, possibly nested in another one of the same
+/// thread.
+struct Call<'a> {
+    helper: &'a Helper,
+    nested: bool,
+}
+
+impl Drop for Call<'_> {
+    fn drop(&mut self) {
+        let mut caller = self.helper.caller.lock().unwrap();
+        match &mut *caller {
+            Some((_, nested)) if *nested != 0 => *nested -= 1,
+            _ => {
+                *caller = None;
+                drop(caller);
+                self.helper.caller_done.notify_one();
+            }
+        }
+    }
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        if let Some(watchdog_thread) = self.watchdog_thread.get_mut().unwrap().take() {
+            self.watchdog.shutdown();
+            let _ = watchdog_thread.join();
+        }
+        let mut child = self.child.lock().unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn unexpected_response() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Unexpected response.")
+}
+
+/// Spawns a helper, reporting the error if that fails.
+fn spawn_helper(logger: &Logger) -> Option<Arc<Helper>> {
+    match Helper::spawn() {
+        Ok(helper) => Some(Arc::new(helper)),
+        Err(err) => {
+            report_error(
+                logger,
+                ErrorKind::Helper,
+                anyhow::Error::from(err).context(format!(
+                    "Failed starting the helper process at {}.",
+                    helper_path().display(),
+                )),
+            );
+            None
+        }
+    }
+}
+
+fn set_remote_error(error: RemoteError) {
+    set_last_error_chain(error.kind, error.chain);
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+pub fn path_to_wasi(path: &str) -> Option<Box<str>> {
+    translate_path(Request::PathToWasi(path.into()))
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+pub fn wasi_to_path(path: &str) -> Option<Box<str>> {
+    translate_path(Request::WasiToPath(path.into()))
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn translate_path(request: Request) -> Option<Box<str>> {
+    let mut helper = PATH_HELPER.lock().unwrap();
+    let result = match &mut *helper {
+        Some(helper) => Ok(helper),
+        None => Helper::spawn().map(|spawned| helper.insert(spawned)),
+    }
+    .and_then(|helper| helper.call(&request, None, |_| 0));
+
+    match result {
+        Ok(Response::String(path)) => Some(path).filter(|path| !path.is_empty()),
+        Ok(_) => None,
+        Err(err) => {
+            // Spawn a new helper next time.
+            *helper = None;
+            set_last_error(
+                ErrorKind::Helper,
+                &anyhow::Error::from(err).context("Failed translating the path."),
+            );
+            None
+        }
+    }
+}
+
+/// An auto splitter that runs in a helper process. If the helper crashes, a
+/// new one gets started that loads the auto splitter again.
+pub struct RemoteRuntime {
+    helper: Mutex<Arc<Helper>>,
+    /// The module and the configuration of the auto splitter, so it can be
+    /// loaded again when the helper gets restarted.
+    module: Box<[u8]>,
+    config: HelperConfig,
+    vtable: TimerVTable,
+    user_data: *mut c_void,
+    pub logger: Arc<Logger>,
+    was_interrupted: AtomicBool,
+    /// The step budget, so it can be restored when the helper gets restarted.
+    step_budget: AtomicU64,
+    /// When the helper got restarted the last time, so an auto splitter that
+    /// keeps crashing right away doesn't keep spawning new processes.
+    last_restart: Mutex<Option<Instant>>,
+    /// The most recently received settings map and its generation, so it
+    /// only needs to be transferred again once it changed.
+    settings_map: Mutex<(u64, SettingsMap)>,
+    /// The most recently received settings widgets and their generation.
+    settings_widgets: Mutex<(u64, Arc<Vec<Widget>>)>,
+}
+
+// SAFETY: The host is responsible for making its callbacks and the user data
+// usable from the threads that it drives the runtime from.
+unsafe impl Send for RemoteRuntime {}
+unsafe impl Sync for RemoteRuntime {}
+
+/// The minimum amount of time between two restarts of the helper.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// How much longer than the step budget the helper may take to respond to a
+/// step before it is considered hung. The helper interrupts steps that exceed
+/// the budget on its own, so this only needs to cover the round trip.
+const HUNG_HELPER_GRACE: Duration = Duration::from_secs(1);
+
+const NANOS_PER_TICK: u64 = 100;
+
+impl RemoteRuntime {
+    /// Spawns a helper process and loads the auto splitter in it. Errors are
+    /// logged and stored as the last error.
+    pub fn new(
+        module: &[u8],
+        settings_map: Option<SettingsMap>,
+        config: HelperConfig,
+        vtable: TimerVTable,
+        user_data: *mut c_void,
+        logger: Arc<Logger>,
+    ) -> Option<Self> {
+        let helper = spawn_helper(&logger)?;
+
+        let this = Self {
+            helper: Mutex::new(helper.clone()),
+            module: module.into(),
+            config,
+            vtable,
+            user_data,
+            logger,
+            was_interrupted: AtomicBool::new(false),
+            step_budget: AtomicU64::new(0),
+            last_restart: Mutex::new(None),
+            settings_map: Mutex::new((0, SettingsMap::new())),
+            settings_widgets: Mutex::new((0, Arc::new(Vec::new()))),
+        };
+
+        this.create(&helper, settings_map).then_some(this)
+    }
+
+    /// Loads the auto splitter in the helper provided.
+    fn create(&self, helper: &Helper, settings_map: Option<SettingsMap>) -> bool {
+        let response = helper.call(
+            &Request::Create {
+                debug_info: self.config.debug_info,
+                optimize: self.config.optimize,
+                backtrace_details: self.config.backtrace_details,
+                module: self.module.to_vec(),
+                settings_map,
+            },
+            None,
+            |callback| self.handle_callback(callback),
+        );
+
+        match response {
+            Ok(Response::Ok) => true,
+            Ok(Response::Err(error)) => {
+                set_remote_error(error);
+                false
+            }
+            Ok(_) => {
+                self.report_unexpected_response();
+                false
+            }
+            Err(err) => {
+                report_error(
+                    &self.logger,
+                    ErrorKind::Helper,
+                    anyhow::Error::from(err).context("The helper process stopped responding."),
+                );
+                false
+            }
+        }
+    }
+
+    /// Calls into the helper, dispatching the callbacks to the host. If the
+    /// helper can't be reached or a step doesn't finish within the step
+    /// budget, the error gets reported, the helper gets restarted and
+    /// [`None`] is returned.
+    fn call(&self, request: &Request) -> Option<Response> {
+        let time_limit = match request {
+            Request::Step => self.step_time_limit(),
+            _ => None,
+        };
+        let helper = self.helper.lock().unwrap().clone();
+        match helper.call(request, time_limit, |callback| {
+            self.handle_callback(callback)
+        }) {
+            Ok(response) => Some(response),
+            Err(err) => {
+                self.restart(&helper, err);
+                None
+            }
+        }
+    }
+
+    fn step_time_limit(&self) -> Option<Duration> {
+        let ticks = self.step_budget.load(atomic::Ordering::Relaxed);
+        (ticks != 0)
+            .then(|| Duration::from_nanos(ticks.saturating_mul(NANOS_PER_TICK)) + HUNG_HELPER_GRACE)
+    }
+
+    /// Replaces the helper that failed with a new one, unless that already
+    /// happened or the last restart was too recent. The helper isn't locked
+    /// while the new one loads the auto splitter, as the host may call back
+    /// into the runtime from the timer callbacks.
+    fn restart(&self, failed: &Arc<Helper>, err: io::Error) {
+        let err = anyhow::Error::from(err).context("The helper process stopped responding.");
+
+        {
+            let helper = self.helper.lock().unwrap();
+            let mut last_restart = self.last_restart.lock().unwrap();
+            if !Arc::ptr_eq(&helper, failed)
+                || last_restart.is_some_and(|last_restart| last_restart.elapsed() < RESTART_DELAY)
+            {
+                set_last_error(ErrorKind::Helper, &err);
+                return;
+            }
+            *last_restart = Some(Instant::now());
+        }
+
+        report_error(
+            &self.logger,
+            ErrorKind::Helper,
+            err.context("Restarting the auto splitter."),
+        );
+
+        let Some(restarted) = spawn_helper(&self.logger) else {
+            return;
+        };
+
+        // The generations of the new helper start over, so the caches need to
+        // be refreshed. The most recent settings map gets passed on to the
+        // auto splitter.
+        let settings_map = {
+            let mut cached = self.settings_map.lock().unwrap();
+            cached.0 = 0;
+            cached.1.clone()
+        };
+        self.settings_widgets.lock().unwrap().0 = 0;
+
+        if !self.create(&restarted, Some(settings_map)) {
+            return;
+        }
+
+        let step_budget = self.step_budget.load(atomic::Ordering::Relaxed);
+        if step_budget != 0 {
+            let _ = restarted.call(&Request::SetStepBudget(step_budget), None, |callback| {
+                self.handle_callback(callback)
+            });
+        }
+
+        let mut helper = self.helper.lock().unwrap();
+        if Arc::ptr_eq(&helper, failed) {
+            *helper = restarted;
+        }
+    }
+
+    fn report_unexpected_response(&self) {
+        report_error(
+            &self.logger,
+            ErrorKind::Helper,
+            anyhow::Error::from(unexpected_response())
+                .context("The helper process sent an invalid response."),
+        );
+    }
+
+    fn handle_callback(&self, callback: Callback) -> i32 {
+        let (vtable, user_data) = (&self.vtable, self.user_data);
+        unsafe {
+            match callback {
+                Callback::State => return (vtable.state)(user_data),
+                Callback::Index => return (vtable.index)(user_data),
+                Callback::SegmentSplitted(index) => {
+                    return (vtable.segment_splitted)(user_data, index)
+                }
+                Callback::Start => (vtable.start)(user_data),
+                Callback::Split => (vtable.split)(user_data),
+                Callback::SkipSplit => (vtable.skip_split)(user_data),
+                Callback::UndoSplit => (vtable.undo_split)(user_data),
+                Callback::Reset => (vtable.reset)(user_data),
+                Callback::SetGameTime(ticks) => (vtable.set_game_time)(user_data, ticks),
+                Callback::PauseGameTime => (vtable.pause_game_time)(user_data),
+                Callback::ResumeGameTime => (vtable.resume_game_time)(user_data),
+                Callback::SetCustomVariable(name, value) => (vtable.set_custom_variable)(
+                    user_data,
+                    name.as_ptr(),
+                    name.len(),
+                    value.as_ptr(),
+                    value.len(),
+                ),
+                Callback::Log {
+                    source,
+                    level,
+                    message,
+                } => {
+                    let source = match source {
+                        0 => LogSource::AutoSplitter,
+                        _ => LogSource::Runtime,
+                    };
+                    self.logger
+                        .log_with_level(source, level, format_args!("{message}"));
+                }
+            }
+        }
+        0
+    }
+
+    pub fn step(&self) -> bool {
+        match self.call(&Request::Step) {
+            Some(Response::Stepped { error, interrupted }) => {
+                self.was_interrupted
+                    .store(interrupted, atomic::Ordering::Relaxed);
+                match error {
+                    Some(error) => {
+                        set_remote_error(error);
+                        false
+                    }
+                    None => true,
+                }
+            }
+            Some(Response::Err(error)) => {
+                set_remote_error(error);
+                false
+            }
+            Some(_) => {
+                self.report_unexpected_response();
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn interrupt(&self) {
+        // The helper handles interrupts while it is busy with another request.
+        let helper = self.helper.lock().unwrap().clone();
+        let _ = helper.send(&Request::Interrupt);
+    }
+
+    pub fn was_interrupted(&self) -> bool {
+        self.was_interrupted.load(atomic::Ordering::Relaxed)
+    }
+
+    pub fn set_step_budget(&self, ticks: u64) {
+        self.step_budget.store(ticks, atomic::Ordering::Relaxed);
+        self.call(&Request::SetStepBudget(ticks));
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        match self.call(&Request::TickRate) {
+            Some(Response::TickRate(ticks)) => {
+                Duration::from_nanos(ticks.saturating_mul(NANOS_PER_TICK))
+            }
+            // Keep stepping at a sane rate if the helper isn't responding.
+            _ => Duration::from_secs(1) / 120,
+        }
+    }
+
+    // The caches aren't locked during the calls, as restarting the helper
+    // needs to reset them.
+
+    pub fn settings_widgets(&self) -> Arc<Vec<Widget>> {
+        let known_generation = self.settings_widgets.lock().unwrap().0;
+        let response = self.call(&Request::GetSettingsWidgets { known_generation });
+        let mut cached = self.settings_widgets.lock().unwrap();
+        if let Some(Response::SettingsWidgets {
+            generation,
+            widgets: Some(widgets),
+        }) = response
+        {
+            *cached = (generation, Arc::new(widgets));
+        }
+        cached.1.clone()
+    }
+
+    pub fn settings_map(&self) -> SettingsMap {
+        let known_generation = self.settings_map.lock().unwrap().0;
+        let response = self.call(&Request::GetSettingsMap { known_generation });
+        let mut cached = self.settings_map.lock().unwrap();
+        if let Some(Response::SettingsMap {
+            generation,
+            map: Some(map),
+        }) = response
+        {
+            *cached = (generation, map);
+        }
+        cached.1.clone()
+    }
+
+    pub fn set_settings_map(&self, map: SettingsMap) {
+        if let Some(Response::Generation(generation)) =
+            self.call(&Request::SetSettingsMap(map.clone()))
+        {
+            // The helper now has the same map, so keep ours, in order for the
+            // host to be able to detect that the map is unchanged.
+            *self.settings_map.lock().unwrap() = (generation, map);
+        }
+    }
+
+    pub fn set_setting(&self, key: Arc<str>, value: SettingValue) {
+        self.call(&Request::SetSetting { key, value });
+    }
+
+    pub fn remove_setting(&self, key: Arc<str>) {
+        self.call(&Request::RemoveSetting(key));
+    }
+
+    /// Replaces the setting at the path provided. Returns false if the path
+    /// doesn't lead to a setting.
+    pub fn set_setting_at_path(&self, path: &str, value: SettingValue) -> bool {
+        match self.call(&Request::SetSettingAtPath {
+            path: path.into(),
+            value,
+        }) {
+            Some(Response::Ok) => true,
+            Some(Response::Err(error)) => {
+                set_remote_error(error);
+                false
+            }
+            Some(_) => {
+                self.report_unexpected_response();
+                false
+            }
+            None => false,
+        }
+    }
+}
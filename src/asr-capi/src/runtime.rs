@@ -1,214 +1,1342 @@
-use crate::{settings_map::SettingsMap, widgets::Widgets};
+use crate::{
+    error::{set_last_error, ErrorKind},
+    log::{LogRecord, LogSource, Logger, ERROR_LEVEL, INFO_LEVEL},
+    recording::TimerTap,
+    remote::{self, RemoteRuntime},
+    runtime_config::RuntimeConfig,
+    setting_path::{self, set_path_error},
+    setting_value::SettingValue,
+    settings::Widget,
+    settings_diff::{self, SettingsDiff},
+    settings_list::SettingsList,
+    settings_map::SettingsMap,
+    stats::StepStats,
+    str,
+    timer::TimerVTable,
+    widgets::Widgets,
+};
+use std::{
+    ffi::c_void,
+    fs, io, mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{self, AtomicU64},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
 
 #[cfg(target_pointer_width = "64")]
-use crate::{log, setting_value::SettingValue, str, CTimer};
+use crate::{settings_map, timer::CTimer, watchdog::Watchdog};
 #[cfg(target_pointer_width = "64")]
-use anyhow::Context;
+use std::{convert::Infallible, sync::atomic::AtomicBool};
+
+pub struct Runtime {
+    shared: Arc<Shared>,
+    step_thread: Mutex<Option<StepThread>>,
+    watcher: Mutex<Option<Watcher>>,
+}
+
+/// The state of the runtime that is shared with the step and watcher threads.
+struct Shared {
+    /// The auto splitter that is currently loaded. It gets replaced when the
+    /// auto splitter is reloaded.
+    backend: Mutex<Arc<Backend>>,
+    /// The file the auto splitter was loaded from, if any.
+    path: Option<PathBuf>,
+    config: RuntimeConfig,
+    logger: Arc<Logger>,
+    /// The step budget, so it can be applied again after reloading.
+    step_budget: AtomicU64,
+    stats: StepStats,
+    /// All the timer callbacks pass through here, so they can be recorded.
+    /// This is dropped after the backend, which calls into it.
+    timer: Arc<TimerTap>,
+}
+
+/// Where the auto splitter runs.
+enum Backend {
+    /// The auto splitter runs in this process.
+    #[cfg(target_pointer_width = "64")]
+    InProcess {
+        inner: Arc<Inner>,
+        watchdog_thread: Mutex<Option<thread::JoinHandle<()>>>,
+    },
+    /// The auto splitter runs in a helper process. On targets other than
+    /// 64-bit ones, this is always the case.
+    Sandboxed(Box<RemoteRuntime>),
+}
+
+/// The state of an auto splitter running in this process that is shared with
+/// the watchdog thread.
 #[cfg(target_pointer_width = "64")]
-use livesplit_auto_splitting::Config;
+struct Inner {
+    runtime: livesplit_auto_splitting::AutoSplitter<CTimer>,
+    logger: Arc<Logger>,
+    watchdog: Watchdog,
+    interrupt_requested: AtomicBool,
+    was_interrupted: AtomicBool,
+}
+
 #[cfg(target_pointer_width = "64")]
-use std::{fs, sync::Arc};
+impl Inner {
+    fn new(runtime: livesplit_auto_splitting::AutoSplitter<CTimer>, logger: Arc<Logger>) -> Self {
+        Self {
+            runtime,
+            logger,
+            watchdog: Watchdog::new(),
+            interrupt_requested: AtomicBool::new(false),
+            was_interrupted: AtomicBool::new(false),
+        }
+    }
+
+    fn step(&self) -> bool {
+        let mut runtime = self.runtime.lock();
+        // Interrupts requested in between steps don't affect this step.
+        self.interrupt_requested
+            .store(false, atomic::Ordering::Relaxed);
+        self.watchdog.begin_step();
+        let result = runtime.update();
+        self.watchdog.end_step();
+        drop(runtime);
+
+        let interrupted = self
+            .interrupt_requested
+            .swap(false, atomic::Ordering::Relaxed);
+
+        match result {
+            Ok(_) => {
+                self.was_interrupted.store(false, atomic::Ordering::Relaxed);
+                true
+            }
+            Err(err) => {
+                self.was_interrupted
+                    .store(interrupted, atomic::Ordering::Relaxed);
+                if interrupted {
+                    report_error(
+                        &self.logger,
+                        ErrorKind::Interrupted,
+                        err.context("The auto splitter was interrupted."),
+                    );
+                } else {
+                    report_error(
+                        &self.logger,
+                        ErrorKind::Trap,
+                        err.context("Failed executing the auto splitter."),
+                    );
+                }
+                false
+            }
+        }
+    }
+
+    fn interrupt(&self) {
+        self.interrupt_requested
+            .store(true, atomic::Ordering::Relaxed);
+        self.runtime.interrupt_handle().interrupt();
+    }
+
+    /// Replaces the settings map with the one returned by the function
+    /// provided, unless it returns [`None`]. The map is modified through a
+    /// compare-and-swap, so concurrent modifications by the auto splitter
+    /// don't get lost. The function is called again whenever that happens.
+    fn modify_settings_map<E>(
+        &self,
+        modify: impl Fn(&SettingsMap) -> Result<Option<SettingsMap>, E>,
+    ) -> Result<(), E> {
+        loop {
+            let old = self.runtime.settings_map();
+            let Some(new) = modify(&old)? else {
+                return Ok(());
+            };
+            if self.runtime.set_settings_map_if_unchanged(&old, new) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A thread that steps the auto splitter at its own tick rate. Dropping the
+/// sender wakes up the thread and makes it stop.
+struct StepThread {
+    stop: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl StepThread {
+    /// Spawns the thread. The function provided steps the auto splitter once
+    /// and returns its current tick rate.
+    fn spawn(mut step: impl FnMut() -> Duration + Send + 'static) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::Builder::new()
+            .name("Auto Splitter".into())
+            .spawn(move || {
+                let mut next_tick = Instant::now();
+                loop {
+                    next_tick += step();
+                    let now = Instant::now();
+                    if next_tick < now {
+                        // We fell behind, so don't try to catch up on the
+                        // missed ticks.
+                        next_tick = now;
+                    }
+
+                    match stopped.recv_timeout(next_tick - now) {
+                        Err(mpsc::RecvTimeoutError::Timeout) => {}
+                        _ => break,
+                    }
+                }
+            })?;
+        Ok(Self { stop, handle })
+    }
+
+    fn join(self) {
+        drop(self.stop);
+        let _ = self.handle.join();
+    }
+}
+
+/// Logs the error and stores it as the last error of the current thread.
+pub fn report_error(logger: &Logger, kind: ErrorKind, err: anyhow::Error) {
+    logger.log_with_level(LogSource::Runtime, ERROR_LEVEL, format_args!("{err:?}"));
+    set_last_error(kind, &err);
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.get_mut().unwrap().take() {
+            watcher.join();
+        }
+        if let Some(step_thread) = self.step_thread.get_mut().unwrap().take() {
+            step_thread.join();
+        }
+    }
+}
 
 #[cfg(target_pointer_width = "64")]
-pub struct Runtime {
-    runtime: livesplit_auto_splitting::AutoSplitter<CTimer>,
-    log: unsafe extern "C" fn(*const u8, usize),
+impl Drop for Backend {
+    fn drop(&mut self) {
+        if let Backend::InProcess {
+            inner,
+            watchdog_thread,
+        } = self
+        {
+            if let Some(watchdog_thread) = watchdog_thread.get_mut().unwrap().take() {
+                inner.watchdog.shutdown();
+                let _ = watchdog_thread.join();
+            }
+        }
+    }
+}
+
+impl Backend {
+    fn step(&self) -> bool {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.step(),
+            Backend::Sandboxed(remote) => remote.step(),
+        }
+    }
+
+    fn interrupt(&self) {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.interrupt(),
+            Backend::Sandboxed(remote) => remote.interrupt(),
+        }
+    }
+
+    fn was_interrupted(&self) -> bool {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => {
+                inner.was_interrupted.load(atomic::Ordering::Relaxed)
+            }
+            Backend::Sandboxed(remote) => remote.was_interrupted(),
+        }
+    }
+
+    fn set_step_budget(&self, ticks: u64) {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess {
+                inner,
+                watchdog_thread,
+            } => {
+                const NANOS_PER_TICK: u64 = 100;
+
+                if ticks == 0 {
+                    inner.watchdog.set_budget(None);
+                    return;
+                }
+
+                let mut watchdog_thread = watchdog_thread.lock().unwrap();
+                if watchdog_thread.is_none() {
+                    let watched = inner.clone();
+                    match thread::Builder::new()
+                        .name("Auto Splitter Watchdog".into())
+                        .spawn(move || watched.watchdog.run(|| watched.interrupt()))
+                    {
+                        Ok(thread) => *watchdog_thread = Some(thread),
+                        Err(err) => {
+                            inner.logger.log_with_level(
+                                LogSource::Runtime,
+                                ERROR_LEVEL,
+                                format_args!(
+                                    "{:?}",
+                                    anyhow::Error::from(err)
+                                        .context("Failed spawning the watchdog thread."),
+                                ),
+                            );
+                            return;
+                        }
+                    }
+                }
+
+                inner.watchdog.set_budget(Some(Duration::from_nanos(
+                    ticks.saturating_mul(NANOS_PER_TICK),
+                )));
+            }
+            Backend::Sandboxed(remote) => remote.set_step_budget(ticks),
+        }
+    }
+
+    fn tick_rate(&self) -> Duration {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.runtime.tick_rate(),
+            Backend::Sandboxed(remote) => remote.tick_rate(),
+        }
+    }
+
+    fn settings_widgets(&self) -> Arc<Vec<Widget>> {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.runtime.settings_widgets(),
+            Backend::Sandboxed(remote) => remote.settings_widgets(),
+        }
+    }
+
+    fn settings_map(&self) -> SettingsMap {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.runtime.settings_map(),
+            Backend::Sandboxed(remote) => remote.settings_map(),
+        }
+    }
+
+    fn set_settings_map(&self, settings_map: SettingsMap) {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => inner.runtime.set_settings_map(settings_map),
+            Backend::Sandboxed(remote) => remote.set_settings_map(settings_map),
+        }
+    }
+
+    /// Stores the value under the key provided in the settings map, or
+    /// removes the key if there is no value. The helper process modifies the
+    /// map of sandboxed auto splitters the same way.
+    fn update_setting(&self, key: Arc<str>, value: Option<SettingValue>) {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => {
+                let Ok(()) = inner.modify_settings_map(|map| {
+                    let mut map = map.clone();
+                    match &value {
+                        Some(value) => map.insert(key.clone(), value.clone()),
+                        None => {
+                            if !settings_map::remove(&mut map, &key) {
+                                return Ok::<_, Infallible>(None);
+                            }
+                        }
+                    }
+                    Ok(Some(map))
+                });
+            }
+            Backend::Sandboxed(remote) => match value {
+                Some(value) => remote.set_setting(key, value),
+                None => remote.remove_setting(key),
+            },
+        }
+    }
+
+    /// Replaces the setting at the path provided in the settings map. Returns
+    /// false and stores the last error if the path doesn't lead to a setting.
+    fn set_setting_at_path(&self, path: &str, value: SettingValue) -> bool {
+        match self {
+            #[cfg(target_pointer_width = "64")]
+            Backend::InProcess { inner, .. } => {
+                match inner.modify_settings_map(|map| {
+                    setting_path::set(map, path, value.clone()).map(Some)
+                }) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        set_path_error(err);
+                        false
+                    }
+                }
+            }
+            Backend::Sandboxed(remote) => remote.set_setting_at_path(path, value),
+        }
+    }
 }
 
-#[cfg(not(target_pointer_width = "64"))]
-pub type Runtime = ();
+impl Shared {
+    fn backend(&self) -> Arc<Backend> {
+        self.backend.lock().unwrap().clone()
+    }
+
+    /// Steps the auto splitter once and returns whether the step succeeded,
+    /// along with the tick rate of the auto splitter. Querying the tick rate
+    /// of a sandboxed auto splitter takes a round trip to the helper, so
+    /// unless `query_tick_rate` is set, the one queried last is used instead.
+    fn step(&self, query_tick_rate: bool) -> (bool, Duration) {
+        self.timer.begin_step();
+        let backend = self.backend();
+        let started = Instant::now();
+        let succeeded = backend.step();
+        let duration = started.elapsed();
+
+        let tick_rate = match (&*backend, self.stats.tick_rate()) {
+            (Backend::Sandboxed(_), Some(tick_rate)) if !query_tick_rate => tick_rate,
+            _ => self.tick_rate(),
+        };
+        self.stats.record(duration, succeeded, tick_rate);
+        (succeeded, tick_rate)
+    }
+
+    fn tick_rate(&self) -> Duration {
+        let tick_rate = self.backend().tick_rate();
+        self.stats.set_tick_rate(tick_rate);
+        tick_rate
+    }
 
+    fn set_step_budget(&self, ticks: u64) {
+        self.step_budget.store(ticks, atomic::Ordering::Relaxed);
+        self.backend().set_step_budget(ticks);
+    }
+
+    fn reload_from_path(&self) -> bool {
+        let Some(path) = &self.path else {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::anyhow!("The auto splitter wasn't loaded from a file."),
+            );
+            return false;
+        };
+        match fs::read(path) {
+            Ok(module) => self.reload(&module),
+            Err(err) => {
+                report_error(
+                    &self.logger,
+                    ErrorKind::FileRead,
+                    anyhow::Error::from(err)
+                        .context("Failed reading the file for the auto splitter."),
+                );
+                false
+            }
+        }
+    }
+
+    /// Replaces the auto splitter with the module provided, carrying over the
+    /// settings map. The current auto splitter keeps running if the module
+    /// can't be loaded.
+    fn reload(&self, module: &[u8]) -> bool {
+        let settings_map = self.backend().settings_map();
+        let Some(backend) = new_backend(
+            module,
+            Some(settings_map.clone()),
+            &self.config,
+            &self.timer,
+            self.logger.clone(),
+        ) else {
+            return false;
+        };
+        let ticks = self.step_budget.load(atomic::Ordering::Relaxed);
+        if ticks != 0 {
+            backend.set_step_budget(ticks);
+        }
+
+        let previous = {
+            let mut current = self.backend.lock().unwrap();
+            // The settings may have changed while the module was loading.
+            let latest = current.settings_map();
+            if !latest.is_unchanged(&settings_map) {
+                backend.set_settings_map(latest);
+            }
+            mem::replace(&mut *current, Arc::new(backend))
+        };
+        // A step that is still running keeps the previous auto splitter alive
+        // until it's done.
+        drop(previous);
+
+        self.stats.reset();
+        self.logger.log_with_level(
+            LogSource::Runtime,
+            INFO_LEVEL,
+            format_args!("Reloaded the auto splitter."),
+        );
+        true
+    }
+}
+
+/// A thread that polls the file of the auto splitter and reloads the auto
+/// splitter whenever the file changes. Dropping the sender wakes up the thread
+/// and makes it stop.
+struct Watcher {
+    stop: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl Watcher {
+    fn spawn(shared: Arc<Shared>, path: PathBuf) -> io::Result<Self> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::Builder::new()
+            .name("Auto Splitter Watcher".into())
+            .spawn(move || {
+                let mut loaded = file_version(&path);
+                let mut pending = None;
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(POLL_INTERVAL)
+                {
+                    let Some(version) = file_version(&path) else {
+                        continue;
+                    };
+                    if Some(version) == loaded {
+                        pending = None;
+                        continue;
+                    }
+                    // Only reload once the file stopped changing, so it isn't
+                    // read while it's still being written.
+                    if pending != Some(version) {
+                        pending = Some(version);
+                        continue;
+                    }
+                    loaded = pending.take();
+                    shared.reload_from_path();
+                }
+            })?;
+        Ok(Self { stop, handle })
+    }
+
+    fn join(self) {
+        drop(self.stop);
+        let _ = self.handle.join();
+    }
+}
+
+/// Returns the modification time and size of the file, which change whenever
+/// the file gets written to.
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl Runtime {
+    fn new(shared: Shared) -> Self {
+        Self {
+            shared: Arc::new(shared),
+            step_thread: Mutex::new(None),
+            watcher: Mutex::new(None),
+        }
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.shared.logger
+    }
+
+    fn backend(&self) -> Arc<Backend> {
+        self.shared.backend()
+    }
+
+    fn step(&self) -> bool {
+        self.shared.step(false).0
+    }
+
+    fn interrupt(&self) {
+        self.backend().interrupt();
+    }
+
+    fn was_interrupted(&self) -> bool {
+        self.backend().was_interrupted()
+    }
+
+    fn set_step_budget(&self, ticks: u64) {
+        self.shared.set_step_budget(ticks);
+    }
+
+    fn tick_rate(&self) -> Duration {
+        self.shared.tick_rate()
+    }
+
+    fn settings_widgets(&self) -> Arc<Vec<Widget>> {
+        self.backend().settings_widgets()
+    }
+
+    fn settings_map(&self) -> SettingsMap {
+        self.backend().settings_map()
+    }
+
+    fn set_settings_map(&self, settings_map: SettingsMap) {
+        self.backend().set_settings_map(settings_map);
+    }
+
+    /// Stores the value under the key provided in the settings map of the
+    /// auto splitter, or removes the key if there is no value.
+    pub fn update_setting(&self, key: Arc<str>, value: Option<SettingValue>) {
+        self.backend().update_setting(key, value);
+    }
+
+    /// Replaces the setting at the path provided in the settings map of the
+    /// auto splitter. Returns false and stores the last error if the path
+    /// doesn't lead to a setting.
+    pub fn set_setting_at_path(&self, path: &str, value: SettingValue) -> bool {
+        self.backend().set_setting_at_path(path, value)
+    }
+}
+
+/// Creates a new runtime by loading the auto splitter at the path provided.
+/// The timer is controlled through the callbacks in the vtable, which all
+/// receive the user data pointer provided. Returns null if the vtable is
+/// invalid or the auto splitter couldn't be loaded.
+///
 /// # Safety
-/// TODO:
+/// `_path_ptr` must be a valid nul-terminated string. `_timer_vtable`
+/// must point to a vtable that is valid for as many bytes as specified by its
+/// `size` field. The callbacks and the user data must stay valid for as long
+/// as the runtime exists.
 #[no_mangle]
 pub unsafe extern "C" fn Runtime_new(
     _path_ptr: *const u8,
     _settings_map: Option<Box<SettingsMap>>,
-    _state: unsafe extern "C" fn() -> i32,
-    _index: unsafe extern "C" fn() -> i32,
-    _segment_splitted: unsafe extern "C" fn(i32) -> i32,
-    _start: unsafe extern "C" fn(),
-    _split: unsafe extern "C" fn(),
-    _skip_split: unsafe extern "C" fn(),
-    _undo_split: unsafe extern "C" fn(),
-    _reset: unsafe extern "C" fn(),
-    _set_game_time: unsafe extern "C" fn(i64),
-    _pause_game_time: unsafe extern "C" fn(),
-    _resume_game_time: unsafe extern "C" fn(),
-    _set_custom_variable: unsafe extern "C" fn(*const u8, usize, *const u8, usize),
-    _log: unsafe extern "C" fn(*const u8, usize),
+    _timer_vtable: *const TimerVTable,
+    _timer_user_data: *mut c_void,
+) -> Option<Box<Runtime>> {
+    Runtime_new_with_config(
+        _path_ptr,
+        _settings_map,
+        &RuntimeConfig::default(),
+        _timer_vtable,
+        _timer_user_data,
+    )
+}
+
+/// Creates a new runtime like `Runtime_new`, but uses the engine configuration
+/// provided instead of the default one. The configuration is not consumed and
+/// may be reused for creating further runtimes.
+///
+/// # Safety
+/// Same as `Runtime_new`.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_new_with_config(
+    _path_ptr: *const u8,
+    _settings_map: Option<Box<SettingsMap>>,
+    _config: &RuntimeConfig,
+    _timer_vtable: *const TimerVTable,
+    _timer_user_data: *mut c_void,
 ) -> Option<Box<Runtime>> {
+    let vtable = match TimerVTable::read(_timer_vtable) {
+        Ok(vtable) => vtable,
+        Err(err) => {
+            set_last_error(ErrorKind::InvalidInput, &err);
+            return None;
+        }
+    };
+    let logger = Arc::new(Logger::new(&vtable, _timer_user_data));
+
+    let path = str(_path_ptr)?;
+    let file = match fs::read(path) {
+        Ok(file) => file,
+        Err(err) => {
+            report_error(
+                &logger,
+                ErrorKind::FileRead,
+                anyhow::Error::from(err).context("Failed reading the file for the auto splitter."),
+            );
+            return None;
+        }
+    };
+
+    new_runtime(
+        &file,
+        _settings_map,
+        Some(path.into()),
+        _config,
+        vtable,
+        _timer_user_data,
+        logger,
+    )
+}
+
+/// Creates a new runtime from a WebAssembly module that is already loaded into
+/// memory, instead of reading it from a file.
+///
+/// # Safety
+/// `_module_ptr` must point to `_module_len` readable bytes. The bytes only
+/// need to stay valid for the duration of the call. The same requirements as
+/// for `Runtime_new` apply to the vtable and the user data.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_new_from_bytes(
+    _module_ptr: *const u8,
+    _module_len: usize,
+    _settings_map: Option<Box<SettingsMap>>,
+    _timer_vtable: *const TimerVTable,
+    _timer_user_data: *mut c_void,
+) -> Option<Box<Runtime>> {
+    Runtime_new_from_bytes_with_config(
+        _module_ptr,
+        _module_len,
+        _settings_map,
+        &RuntimeConfig::default(),
+        _timer_vtable,
+        _timer_user_data,
+    )
+}
+
+/// Creates a new runtime like `Runtime_new_from_bytes`, but uses the engine
+/// configuration provided instead of the default one.
+///
+/// # Safety
+/// Same as `Runtime_new_from_bytes`.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_new_from_bytes_with_config(
+    _module_ptr: *const u8,
+    _module_len: usize,
+    _settings_map: Option<Box<SettingsMap>>,
+    _config: &RuntimeConfig,
+    _timer_vtable: *const TimerVTable,
+    _timer_user_data: *mut c_void,
+) -> Option<Box<Runtime>> {
+    let vtable = match TimerVTable::read(_timer_vtable) {
+        Ok(vtable) => vtable,
+        Err(err) => {
+            set_last_error(ErrorKind::InvalidInput, &err);
+            return None;
+        }
+    };
+    let logger = Arc::new(Logger::new(&vtable, _timer_user_data));
+
+    let module = if _module_len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(_module_ptr, _module_len)
+    };
+
+    new_runtime(
+        module,
+        _settings_map,
+        None,
+        _config,
+        vtable,
+        _timer_user_data,
+        logger,
+    )
+}
+
+fn new_runtime(
+    module: &[u8],
+    settings_map: Option<Box<SettingsMap>>,
+    path: Option<PathBuf>,
+    config: &RuntimeConfig,
+    vtable: TimerVTable,
+    user_data: *mut c_void,
+    logger: Arc<Logger>,
+) -> Option<Box<Runtime>> {
+    let timer = Arc::new(TimerTap::new(vtable, user_data, logger.clone()));
+    let backend = new_backend(
+        module,
+        settings_map.map(|settings_map| *settings_map),
+        config,
+        &timer,
+        logger.clone(),
+    )?;
+    Some(Box::new(Runtime::new(Shared {
+        backend: Mutex::new(Arc::new(backend)),
+        path,
+        config: *config,
+        logger,
+        step_budget: AtomicU64::new(0),
+        stats: StepStats::default(),
+        timer,
+    })))
+}
+
+fn new_backend(
+    module: &[u8],
+    settings_map: Option<SettingsMap>,
+    config: &RuntimeConfig,
+    timer: &TimerTap,
+    logger: Arc<Logger>,
+) -> Option<Backend> {
+    let (vtable, user_data) = (*TimerTap::vtable(), timer.user_data());
+
     #[cfg(target_pointer_width = "64")]
-    {
-        let path = str(_path_ptr);
-        let file = match fs::read(path) {
-            Ok(file) => file,
-            Err(err) => {
-                log(
-                    _log,
-                    format_args!(
-                        "{:?}",
-                        anyhow::Error::from(err)
-                            .context("Failed reading the file for the auto splitter."),
-                    ),
-                );
-                return None;
-            }
-        };
+    let helper_config = if config.sandboxed() {
+        config.helper_config()
+    } else {
+        return new_in_process(module, settings_map, config, vtable, user_data, logger);
+    };
+    #[cfg(not(target_pointer_width = "64"))]
+    let helper_config = *config;
 
-        match livesplit_auto_splitting::Runtime::new(Config::default())
-            .context("Failed creating the runtime.")
-            .and_then(|runtime| {
-                runtime
-                    .compile(&file)
-                    .context("Failed compiling the auto splitter.")?
-                    .instantiate(
-                        CTimer {
-                            state: _state,
-                            start: _start,
-                            index: _index,
-                            segment_splitted: _segment_splitted,
-                            split: _split,
-                            skip_split: _skip_split,
-                            undo_split: _undo_split,
-                            reset: _reset,
-                            set_game_time: _set_game_time,
-                            pause_game_time: _pause_game_time,
-                            resume_game_time: _resume_game_time,
-                            set_custom_variable: _set_custom_variable,
-                            log: _log,
-                        },
-                        _settings_map.map(|settings_map| *settings_map),
-                        None,
-                    )
-                    .context("Failed instantiating the auto splitter.")
-            }) {
-            Ok(runtime) => Some(Box::new(Runtime { runtime, log: _log })),
-            Err(err) => {
-                log(_log, format_args!("{err:?}"));
-                None
-            }
+    RemoteRuntime::new(
+        module,
+        settings_map,
+        helper_config,
+        vtable,
+        user_data,
+        logger,
+    )
+    .map(|remote| Backend::Sandboxed(Box::new(remote)))
+}
+
+#[cfg(target_pointer_width = "64")]
+fn new_in_process(
+    module: &[u8],
+    settings_map: Option<SettingsMap>,
+    config: &RuntimeConfig,
+    vtable: TimerVTable,
+    user_data: *mut c_void,
+    logger: Arc<Logger>,
+) -> Option<Backend> {
+    let timer = CTimer::new(vtable, user_data, logger.clone());
+    let runtime = match livesplit_auto_splitting::Runtime::new(config.to_config()) {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            report_error(
+                &logger,
+                ErrorKind::RuntimeCreation,
+                anyhow::Error::from(err).context("Failed creating the runtime."),
+            );
+            return None;
+        }
+    };
+
+    let compiled = match runtime.compile(module) {
+        Ok(compiled) => compiled,
+        Err(err) => {
+            report_error(
+                &logger,
+                ErrorKind::Compile,
+                anyhow::Error::from(err).context("Failed compiling the auto splitter."),
+            );
+            return None;
+        }
+    };
+
+    match compiled.instantiate(timer, settings_map, None) {
+        Ok(runtime) => Some(Backend::InProcess {
+            inner: Arc::new(Inner::new(runtime, logger)),
+            watchdog_thread: Mutex::new(None),
+        }),
+        Err(err) => {
+            report_error(
+                &logger,
+                ErrorKind::Instantiate,
+                anyhow::Error::from(err).context("Failed instantiating the auto splitter."),
+            );
+            None
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    Some(Box::new(()))
 }
 
 #[no_mangle]
 pub extern "C" fn Runtime_drop(_: Box<Runtime>) {}
 
+/// Sets the path of the helper executable that runs sandboxed auto splitters,
+/// which are all auto splitters on targets other than 64-bit ones. By default
+/// the helper is expected to be called `asr-helper` and to be located next to
+/// this library. This needs to be called before any runtimes are created.
+/// Returns false if the path is not valid UTF-8.
+///
+/// # Safety
+/// `_path_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_set_helper_path(_path_ptr: *const u8) -> bool {
+    let Some(path) = str(_path_ptr) else {
+        return false;
+    };
+    remote::set_helper_path(path.into());
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn Runtime_step(_this: &Runtime) -> bool {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this.runtime.lock().update() {
-            Ok(_) => true,
-            Err(err) => {
-                log(
-                    _this.log,
-                    format_args!("{:?}", err.context("Failed executing the auto splitter.")),
-                );
-                false
-            }
+    _this.step()
+}
+
+/// Spawns a thread owned by the runtime that steps the auto splitter at its
+/// own tick rate, so the host doesn't need to call `Runtime_step` itself. The
+/// timer callbacks are then called from that thread. Returns false if the
+/// thread is already running or couldn't be spawned.
+#[no_mangle]
+pub extern "C" fn Runtime_start_thread(_this: &Runtime) -> bool {
+    let mut step_thread = _this.step_thread.lock().unwrap();
+    if step_thread.is_some() {
+        return false;
+    }
+    let shared = _this.shared.clone();
+    match StepThread::spawn(move || shared.step(true).1) {
+        Ok(thread) => {
+            *step_thread = Some(thread);
+            true
+        }
+        Err(err) => {
+            _this.logger().log_with_level(
+                LogSource::Runtime,
+                ERROR_LEVEL,
+                format_args!(
+                    "{:?}",
+                    anyhow::Error::from(err).context("Failed spawning the step thread."),
+                ),
+            );
+            false
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    true
 }
 
+/// Stops the thread started by `Runtime_start_thread` and waits for it to
+/// finish its current step. Does nothing if the thread isn't running. The
+/// thread is also stopped automatically when the runtime is dropped.
 #[no_mangle]
-pub extern "C" fn Runtime_tick_rate(_this: &Runtime) -> u64 {
+pub extern "C" fn Runtime_stop_thread(_this: &Runtime) {
+    let step_thread = _this.step_thread.lock().unwrap().take();
+    if let Some(step_thread) = step_thread {
+        step_thread.join();
+    }
+}
+
+/// Reloads the auto splitter from the file it was originally loaded from. The
+/// current settings map is carried over to the new instance, which replaces
+/// the current one once it's ready. If the file can't be loaded, the current
+/// instance keeps running and false is returned. This also fails for runtimes
+/// that were created from bytes.
+#[no_mangle]
+pub extern "C" fn Runtime_reload(_this: &Runtime) -> bool {
+    _this.shared.reload_from_path()
+}
+
+/// Reloads the auto splitter from a WebAssembly module that is already loaded
+/// into memory, like `Runtime_reload`.
+///
+/// # Safety
+/// `_module_ptr` must point to `_module_len` readable bytes. The bytes only
+/// need to stay valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_reload_from_bytes(
+    _this: &Runtime,
+    _module_ptr: *const u8,
+    _module_len: usize,
+) -> bool {
+    let module = if _module_len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(_module_ptr, _module_len)
+    };
+    _this.shared.reload(module)
+}
+
+/// Spawns a thread owned by the runtime that watches the file the auto
+/// splitter was loaded from and reloads the auto splitter whenever the file
+/// changes. Returns false if the runtime wasn't loaded from a file, if the file
+/// is already being watched or if the thread couldn't be spawned.
+#[no_mangle]
+pub extern "C" fn Runtime_start_watching(_this: &Runtime) -> bool {
+    let Some(path) = _this.shared.path.clone() else {
+        return false;
+    };
+    let mut watcher = _this.watcher.lock().unwrap();
+    if watcher.is_some() {
+        return false;
+    }
+    match Watcher::spawn(_this.shared.clone(), path) {
+        Ok(thread) => {
+            *watcher = Some(thread);
+            true
+        }
+        Err(err) => {
+            _this.logger().log_with_level(
+                LogSource::Runtime,
+                ERROR_LEVEL,
+                format_args!(
+                    "{:?}",
+                    anyhow::Error::from(err).context("Failed spawning the watcher thread."),
+                ),
+            );
+            false
+        }
+    }
+}
+
+/// Stops the thread started by `Runtime_start_watching`. Does nothing if the
+/// file isn't being watched.
+#[no_mangle]
+pub extern "C" fn Runtime_stop_watching(_this: &Runtime) {
+    let watcher = _this.watcher.lock().unwrap().take();
+    if let Some(watcher) = watcher {
+        watcher.join();
+    }
+}
+
+/// Interrupts the step of the auto splitter that is currently running, making
+/// it fail. This is safe to call from any thread and can be used to recover
+/// from an auto splitter that is stuck in an infinite loop. Whether a failed
+/// step got interrupted can be queried with `Runtime_was_interrupted`.
+#[no_mangle]
+pub extern "C" fn Runtime_interrupt(_this: &Runtime) {
+    _this.interrupt();
+}
+
+/// Returns whether the most recent step failed because it got interrupted,
+/// either through `Runtime_interrupt` or by exceeding the step budget.
+#[no_mangle]
+pub extern "C" fn Runtime_was_interrupted(_this: &Runtime) -> bool {
+    _this.was_interrupted()
+}
+
+/// Sets the maximum wall-clock time a single step of the auto splitter may take
+/// before it gets interrupted automatically. The budget is specified in ticks
+/// of 100 nanoseconds, like the tick rate. A budget of 0 disables the limit,
+/// which is the default.
+#[no_mangle]
+pub extern "C" fn Runtime_set_step_budget(_this: &Runtime, _ticks: u64) {
+    _this.set_step_budget(_ticks);
+}
+
+/// Sets the minimum level of the log messages that are forwarded to the host.
+/// Messages below this level are not forwarded, but they are still kept in the
+/// log records, see `Runtime_logs_get`. The levels range from 0 for trace
+/// messages up to 4 for errors. Messages printed by the auto splitter itself
+/// are considered to be info messages.
+#[no_mangle]
+pub extern "C" fn Runtime_set_log_level(_this: &Runtime, _level: u32) {
+    _this.logger().set_min_level(_level);
+}
+
+/// Returns the amount of log records that the runtime currently keeps around.
+/// Only the most recent records are kept.
+#[no_mangle]
+pub extern "C" fn Runtime_logs_len(_this: &Runtime) -> usize {
+    _this.logger().records_len()
+}
+
+/// Returns a copy of the log record at the index provided, where 0 is the
+/// oldest record. Returns null if the index is out of bounds.
+#[no_mangle]
+pub extern "C" fn Runtime_logs_get(_this: &Runtime, _index: usize) -> Option<Box<LogRecord>> {
+    _this.logger().record(_index).map(Box::new)
+}
+
+#[no_mangle]
+pub extern "C" fn Runtime_logs_clear(_this: &Runtime) {
+    _this.logger().clear_records();
+}
+
+/// Starts recording every query and action of the auto splitter on the timer
+/// into the file at the path provided, beginning with the next step. Each of
+/// them is stored with its tick and the wall-clock time it happened at, so the
+/// recording can be replayed to the auto splitter later on, for example with
+/// `asr-run --replay`. An existing file is overwritten and a recording that is
+/// already active gets stopped first. Returns false if the path is not valid
+/// UTF-8 or the file couldn't be created.
+///
+/// # Safety
+/// `_path_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_start_recording(_this: &Runtime, _path_ptr: *const u8) -> bool {
+    let Some(path) = str(_path_ptr) else {
+        return false;
+    };
+    match _this.shared.timer.start_recording(Path::new(path)) {
+        Ok(()) => true,
+        Err(err) => {
+            report_error(
+                _this.logger(),
+                ErrorKind::Recording,
+                anyhow::Error::from(err).context("Failed starting the recording."),
+            );
+            false
+        }
+    }
+}
+
+/// Stops the recording started by `Runtime_start_recording` and finishes
+/// writing its file. Returns false if the file couldn't be written. Does
+/// nothing if there is no active recording. The recording is also stopped
+/// automatically when the runtime is dropped.
+#[no_mangle]
+pub extern "C" fn Runtime_stop_recording(_this: &Runtime) -> bool {
+    match _this.shared.timer.stop_recording() {
+        Ok(()) => true,
+        Err(err) => {
+            report_error(
+                _this.logger(),
+                ErrorKind::Recording,
+                anyhow::Error::from(err).context("Failed finishing the recording."),
+            );
+            false
+        }
+    }
+}
+
+/// Converts the duration to ticks of 100 nanoseconds.
+fn to_ticks(duration: Duration) -> u64 {
     const TICKS_PER_SEC: u64 = 10_000_000;
     const NANOS_PER_SEC: u64 = 1_000_000_000;
     const NANOS_PER_TICK: u64 = NANOS_PER_SEC / TICKS_PER_SEC;
 
-    #[cfg(target_pointer_width = "64")]
-    let tick_rate = _this.runtime.tick_rate();
-    #[cfg(not(target_pointer_width = "64"))]
-    let tick_rate = std::time::Duration::new(1, 0) / 120;
-
-    let (secs, nanos) = (tick_rate.as_secs(), tick_rate.subsec_nanos());
+    let (secs, nanos) = (duration.as_secs(), duration.subsec_nanos());
 
     secs * TICKS_PER_SEC + nanos as u64 / NANOS_PER_TICK
 }
 
+#[no_mangle]
+pub extern "C" fn Runtime_tick_rate(_this: &Runtime) -> u64 {
+    to_ticks(_this.tick_rate())
+}
+
+/// Returns the amount of steps of the auto splitter so far, regardless of
+/// whether they were run by `Runtime_step` or by the step thread. The
+/// statistics about the steps can be reset with `Runtime_stats_reset`.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_steps(_this: &Runtime) -> u64 {
+    _this.shared.stats.steps()
+}
+
+/// Returns the amount of steps that failed, for example because the auto
+/// splitter trapped or got interrupted.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_failures(_this: &Runtime) -> u64 {
+    _this.shared.stats.failures()
+}
+
+/// Returns the amount of steps that took longer than the tick rate of the auto
+/// splitter, which means the auto splitter can't keep up with its tick rate.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_overruns(_this: &Runtime) -> u64 {
+    _this.shared.stats.overruns()
+}
+
+/// Returns how long the most recent step took in ticks of 100 nanoseconds.
+/// Sandboxed auto splitters include the time the communication with the
+/// helper process takes.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_last(_this: &Runtime) -> u64 {
+    to_ticks(_this.shared.stats.last())
+}
+
+/// Returns how long the steps took on average in ticks of 100 nanoseconds.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_mean(_this: &Runtime) -> u64 {
+    to_ticks(_this.shared.stats.mean())
+}
+
+/// Returns the duration in ticks of 100 nanoseconds that 99% of the most
+/// recent 1024 steps didn't exceed.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_p99(_this: &Runtime) -> u64 {
+    to_ticks(_this.shared.stats.percentile(99))
+}
+
+/// Returns how long the slowest step took in ticks of 100 nanoseconds.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_max(_this: &Runtime) -> u64 {
+    to_ticks(_this.shared.stats.max())
+}
+
+/// Resets all the statistics about the steps of the auto splitter.
+#[no_mangle]
+pub extern "C" fn Runtime_stats_reset(_this: &Runtime) {
+    _this.shared.stats.reset();
+}
+
 #[no_mangle]
 pub extern "C" fn Runtime_get_settings_widgets(_this: &Runtime) -> Box<Widgets> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(Widgets {
-            inner: _this.runtime.settings_widgets(),
-        })
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(Widgets {
+        inner: _this.settings_widgets(),
+    })
 }
 
+/// Stores the bool under the key provided in the settings map of the auto
+/// splitter. Returns false and leaves the settings map unchanged if the key is
+/// not valid UTF-8.
+///
 /// # Safety
-/// TODO:
+/// `_key` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn Runtime_settings_map_set_bool(
     _this: &Runtime,
     _key: *const u8,
     _value: bool,
-) {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let key = Arc::<str>::from(str(_key));
-        loop {
-            let mut map = _this.runtime.settings_map();
-            let old = map.clone();
-            map.insert(key.clone(), SettingValue::Bool(_value));
-            if _this.runtime.set_settings_map_if_unchanged(&old, map) {
-                break;
-            }
-        }
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::Bool(_value)));
+    true
 }
 
+/// Stores the string under the key provided in the settings map of the auto
+/// splitter. Returns false and leaves the settings map unchanged if the key or
+/// the value is not valid UTF-8.
+///
 /// # Safety
-/// TODO:
+/// `_key` and `_value` must be valid nul-terminated strings.
 #[no_mangle]
 pub unsafe extern "C" fn Runtime_settings_map_set_string(
     _this: &Runtime,
     _key: *const u8,
     _value: *const u8,
-) {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let key = Arc::<str>::from(str(_key));
-        let value = Arc::<str>::from(str(_value));
-        loop {
-            let mut map = _this.runtime.settings_map();
-            let old = map.clone();
-            map.insert(key.clone(), SettingValue::String(value.clone()));
-            if _this.runtime.set_settings_map_if_unchanged(&old, map) {
-                break;
-            }
+) -> bool {
+    let (Some(key), Some(value)) = (str(_key), str(_value)) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::String(value.into())));
+    true
+}
+
+/// Stores the integer under the key provided in the settings map of the auto
+/// splitter. Returns false and leaves the settings map unchanged if the key is
+/// not valid UTF-8.
+///
+/// # Safety
+/// `_key` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_set_i64(
+    _this: &Runtime,
+    _key: *const u8,
+    _value: i64,
+) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::I64(_value)));
+    true
+}
+
+/// Stores the floating point number under the key provided in the settings map
+/// of the auto splitter. Returns false and leaves the settings map unchanged if
+/// the key is not valid UTF-8.
+///
+/// # Safety
+/// `_key` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_set_f64(
+    _this: &Runtime,
+    _key: *const u8,
+    _value: f64,
+) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::F64(_value)));
+    true
+}
+
+/// Stores the map under the key provided in the settings map of the auto
+/// splitter. The map is consumed either way. Returns false and leaves the
+/// settings map unchanged if the key is not valid UTF-8.
+///
+/// # Safety
+/// `_key` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_set_map(
+    _this: &Runtime,
+    _key: *const u8,
+    _value: Box<SettingsMap>,
+) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::Map(*_value)));
+    true
+}
+
+/// Stores the list under the key provided in the settings map of the auto
+/// splitter. The list is consumed either way. Returns false and leaves the
+/// settings map unchanged if the key is not valid UTF-8.
+///
+/// # Safety
+/// `_key` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_set_list(
+    _this: &Runtime,
+    _key: *const u8,
+    _value: Box<SettingsList>,
+) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), Some(SettingValue::List(*_value)));
+    true
+}
+
+/// Returns a copy of the setting at the path provided in the settings map of
+/// the auto splitter, like `SettingsMap_get_value_by_path`. The value needs to
+/// be dropped with `SettingValue_drop`. Returns null and stores the last error
+/// if the path doesn't lead to a setting or is not valid UTF-8.
+///
+/// # Safety
+/// `_path` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_get_value_by_path(
+    _this: &Runtime,
+    _path: *const u8,
+) -> Option<Box<SettingValue>> {
+    let path = str(_path)?;
+    match setting_path::get(&_this.settings_map(), path) {
+        Ok(value) => Some(Box::new(value.clone())),
+        Err(err) => {
+            set_path_error(err);
+            None
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+}
+
+/// Replaces the setting at the path provided in the settings map of the auto
+/// splitter, like `SettingsMap_set_value_by_path`. The value is consumed either
+/// way. Returns false, leaves the settings map unchanged and stores the last
+/// error if the path doesn't lead to a setting or is not valid UTF-8.
+///
+/// # Safety
+/// `_path` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_set_value_by_path(
+    _this: &Runtime,
+    _path: *const u8,
+    _value: Box<SettingValue>,
+) -> bool {
+    let Some(path) = str(_path) else {
+        return false;
+    };
+    _this.set_setting_at_path(path, *_value)
+}
+
+/// Removes the key provided from the settings map of the auto splitter. Keys
+/// that are not in the settings map are ignored. Returns false if the key is
+/// not valid UTF-8.
+///
+/// # Safety
+/// `_key` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn Runtime_settings_map_remove(_this: &Runtime, _key: *const u8) -> bool {
+    let Some(key) = str(_key) else {
+        return false;
+    };
+    _this.update_setting(key.into(), None);
+    true
 }
 
 #[no_mangle]
 pub extern "C" fn Runtime_get_settings_map(_this: &Runtime) -> Box<SettingsMap> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(_this.runtime.settings_map())
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(_this.settings_map())
 }
 
 #[no_mangle]
 pub extern "C" fn Runtime_set_settings_map(_this: &Runtime, _settings_map: Box<SettingsMap>) {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.runtime.set_settings_map(*_settings_map);
-    }
+    _this.set_settings_map(*_settings_map);
 }
 
 #[no_mangle]
@@ -217,14 +1345,22 @@ pub extern "C" fn Runtime_are_settings_changed(
     _previous_settings_map: &SettingsMap,
     _previous_widgets: &Widgets,
 ) -> bool {
-    #[cfg(target_pointer_width = "64")]
-    {
-        !_this
-            .runtime
-            .settings_map()
-            .is_unchanged(_previous_settings_map)
-            || !Arc::ptr_eq(&_this.runtime.settings_widgets(), &_previous_widgets.inner)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    false
+    !_this.settings_map().is_unchanged(_previous_settings_map)
+        || !Arc::ptr_eq(&_this.settings_widgets(), &_previous_widgets.inner)
+}
+
+/// Compares a settings map previously returned by `Runtime_get_settings_map`
+/// to the current settings map of the auto splitter, like `SettingsMap_diff`.
+/// Unlike `Runtime_are_settings_changed`, this tells which settings changed, so
+/// only the affected parts of a settings UI need to be updated. The diff needs
+/// to be dropped with `SettingsDiff_drop`.
+#[no_mangle]
+pub extern "C" fn Runtime_diff_settings(
+    _this: &Runtime,
+    _previous_settings_map: &SettingsMap,
+) -> Box<SettingsDiff> {
+    Box::new(settings_diff::diff(
+        _previous_settings_map,
+        &_this.settings_map(),
+    ))
 }
@@ -0,0 +1,127 @@
+#[cfg(target_pointer_width = "64")]
+use livesplit_auto_splitting::Config;
+
+/// The configuration of the engine that compiles and runs an auto splitter.
+/// It can be passed to `Runtime_new_with_config` and
+/// `Runtime_new_from_bytes_with_config`.
+#[cfg(target_pointer_width = "64")]
+#[derive(Copy, Clone)]
+pub struct RuntimeConfig {
+    debug_info: bool,
+    optimize: bool,
+    backtrace_details: bool,
+    sandboxed: bool,
+}
+
+/// On other targets the engine always runs in the helper process, so the
+/// configuration only consists of the settings sent over to it.
+#[cfg(not(target_pointer_width = "64"))]
+pub type RuntimeConfig = HelperConfig;
+
+/// The settings of the engine that are sent to the helper process. Settings
+/// that are `None` use the helper's defaults.
+#[derive(Copy, Clone, Default)]
+pub struct HelperConfig {
+    pub debug_info: Option<bool>,
+    pub optimize: Option<bool>,
+    pub backtrace_details: Option<bool>,
+}
+
+#[cfg(target_pointer_width = "64")]
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            debug_info: config.debug_info,
+            optimize: config.optimize,
+            backtrace_details: config.backtrace_details,
+            sandboxed: false,
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl RuntimeConfig {
+    pub fn to_config(self) -> Config {
+        let mut config = Config::default();
+        config.debug_info = self.debug_info;
+        config.optimize = self.optimize;
+        config.backtrace_details = self.backtrace_details;
+        config
+    }
+
+    pub fn sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    pub fn helper_config(&self) -> HelperConfig {
+        HelperConfig {
+            debug_info: Some(self.debug_info),
+            optimize: Some(self.optimize),
+            backtrace_details: Some(self.backtrace_details),
+        }
+    }
+}
+
+/// Creates a new configuration with the default settings of the runtime.
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_new() -> Box<RuntimeConfig> {
+    Box::default()
+}
+
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_drop(_: Box<RuntimeConfig>) {}
+
+/// Enables debug information for the WebAssembly module, which allows
+/// debuggers to step through the auto splitter's source code.
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_set_debug_info(_this: &mut RuntimeConfig, _value: bool) {
+    #[cfg(target_pointer_width = "64")]
+    {
+        _this.debug_info = _value;
+    }
+    #[cfg(not(target_pointer_width = "64"))]
+    {
+        _this.debug_info = Some(_value);
+    }
+}
+
+/// Enables optimizations when compiling the WebAssembly module. You may want
+/// to disable this when debugging an auto splitter.
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_set_optimize(_this: &mut RuntimeConfig, _value: bool) {
+    #[cfg(target_pointer_width = "64")]
+    {
+        _this.optimize = _value;
+    }
+    #[cfg(not(target_pointer_width = "64"))]
+    {
+        _this.optimize = Some(_value);
+    }
+}
+
+/// Enables more detailed backtraces when the auto splitter traps.
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_set_backtrace_details(_this: &mut RuntimeConfig, _value: bool) {
+    #[cfg(target_pointer_width = "64")]
+    {
+        _this.backtrace_details = _value;
+    }
+    #[cfg(not(target_pointer_width = "64"))]
+    {
+        _this.backtrace_details = Some(_value);
+    }
+}
+
+/// Runs the auto splitter in a separate process, so a misbehaving auto splitter
+/// or a bug in the engine can't crash the host. The process is restarted
+/// automatically if it crashes. It needs the `asr-helper` executable, see
+/// `Runtime_set_helper_path`. On targets other than 64-bit ones, the auto
+/// splitter always runs in a separate process.
+#[no_mangle]
+pub extern "C" fn RuntimeConfig_set_sandboxed(_this: &mut RuntimeConfig, _value: bool) {
+    #[cfg(target_pointer_width = "64")]
+    {
+        _this.sandboxed = _value;
+    }
+}
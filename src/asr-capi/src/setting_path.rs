@@ -0,0 +1,409 @@
+//! Paths like `routes/0/name` that address settings nested inside of maps and
+//! lists. The segments of a path are separated by slashes, and each one is
+//! either the key of a map or the index of a list. Like in JSON Pointers, a
+//! `~` in a key is escaped as `~0` and a `/` as `~1`, and indices are written
+//! without a sign or leading zeros. Unlike JSON Pointers, segments can't be
+//! empty, so settings stored under an empty key can't be addressed by a path.
+
+use crate::{
+    error::{set_last_error, ErrorKind},
+    setting_value::SettingValue,
+    settings_list::SettingsList,
+    settings_map::SettingsMap,
+};
+use std::{borrow::Cow, fmt};
+
+/// Why a path doesn't lead to a setting. The paths stored in the variants are
+/// the part of the path up to where it went wrong.
+#[derive(Debug)]
+pub enum PathError {
+    /// The path is empty, contains an empty segment or contains a `~` that
+    /// isn't followed by `0` or `1`.
+    Malformed { path: Box<str> },
+    /// There is no setting at the path.
+    NotFound { path: Box<str> },
+    /// The setting at the path is neither a map nor a list, so there is
+    /// nothing nested inside of it.
+    NotAContainer { path: Box<str> },
+    /// The setting at the path is a list, but the segment is not an index.
+    NotAnIndex { path: Box<str>, segment: Box<str> },
+    /// The index is beyond the end of the list at the path.
+    OutOfRange {
+        path: Box<str>,
+        index: usize,
+        len: usize,
+    },
+}
+
+impl PathError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PathError::Malformed { .. } => ErrorKind::InvalidInput,
+            PathError::NotFound { .. } => ErrorKind::SettingNotFound,
+            PathError::NotAContainer { .. } | PathError::NotAnIndex { .. } => {
+                ErrorKind::SettingTypeMismatch
+            }
+            PathError::OutOfRange { .. } => ErrorKind::SettingIndexOutOfRange,
+        }
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Malformed { path } => {
+                write!(
+                    f,
+                    "The setting path `{path}` is empty, has an empty segment or has an invalid \
+                     escape sequence."
+                )
+            }
+            PathError::NotFound { path } => write!(f, "There is no setting at `{path}`."),
+            PathError::NotAContainer { path } => {
+                write!(f, "The setting at `{path}` is neither a map nor a list.")
+            }
+            PathError::NotAnIndex { path, segment } => write!(
+                f,
+                "The setting at `{path}` is a list, so `{segment}` needs to be an index.",
+            ),
+            PathError::OutOfRange { path, index, len } => write!(
+                f,
+                "The index {index} is out of range for the list at `{path}`, which has {len} \
+                 elements.",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Stores the error as the last error of the current thread.
+pub fn set_path_error(err: PathError) {
+    set_last_error(err.kind(), &anyhow::Error::new(err));
+}
+
+/// Escapes the key, so it can be used as a segment of a path.
+pub fn escape(key: &str) -> Cow<'_, str> {
+    if !key.contains(['~', '/']) {
+        return Cow::Borrowed(key);
+    }
+    Cow::Owned(key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Turns the segment back into the key it escapes. Returns [`None`] if it
+/// contains an invalid escape sequence.
+fn unescape(segment: &str) -> Option<Cow<'_, str>> {
+    if !segment.contains('~') {
+        return Some(Cow::Borrowed(segment));
+    }
+    let mut key = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        key.push(match c {
+            '~' => match chars.next()? {
+                '0' => '~',
+                '1' => '/',
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+    Some(Cow::Owned(key))
+}
+
+struct Segment<'a> {
+    /// The unescaped key or index.
+    name: Cow<'a, str>,
+    /// The path up to and including this segment.
+    path: &'a str,
+    /// The path of the map or list that this segment is looked up in.
+    parent: &'a str,
+}
+
+impl Segment<'_> {
+    fn not_found(&self) -> PathError {
+        PathError::NotFound {
+            path: self.path.into(),
+        }
+    }
+
+    fn not_a_container(&self) -> PathError {
+        PathError::NotAContainer {
+            path: self.parent.into(),
+        }
+    }
+
+    /// Parses the segment as an index into a list of the length provided. Only
+    /// plain digits are accepted, without a leading zero unless the index is 0.
+    fn index(&self, len: usize) -> Result<usize, PathError> {
+        let is_canonical = self.name.bytes().all(|b| b.is_ascii_digit())
+            && (!self.name.starts_with('0') || self.name == "0");
+        let index = is_canonical
+            .then(|| self.name.parse().ok())
+            .flatten()
+            .ok_or_else(|| PathError::NotAnIndex {
+                path: self.parent.into(),
+                segment: (*self.name).into(),
+            })?;
+        if index >= len {
+            return Err(PathError::OutOfRange {
+                path: self.parent.into(),
+                index,
+                len,
+            });
+        }
+        Ok(index)
+    }
+}
+
+fn segments(path: &str) -> Result<Vec<Segment<'_>>, PathError> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for segment in path.split('/') {
+        let Some(name) = unescape(segment).filter(|name| !segment.is_empty() && !name.is_empty())
+        else {
+            return Err(PathError::Malformed { path: path.into() });
+        };
+        let end = start + segment.len();
+        segments.push(Segment {
+            name,
+            path: &path[..end],
+            parent: &path[..start.saturating_sub(1)],
+        });
+        start = end + 1;
+    }
+    Ok(segments)
+}
+
+/// Returns the setting at the path provided.
+pub fn get<'a>(map: &'a SettingsMap, path: &str) -> Result<&'a SettingValue, PathError> {
+    let segments = segments(path)?;
+    let (first, rest) = segments.split_first().unwrap();
+    let mut value = map.get(&first.name).ok_or_else(|| first.not_found())?;
+    for segment in rest {
+        value = match value {
+            SettingValue::Map(map) => map.get(&segment.name).ok_or_else(|| segment.not_found())?,
+            SettingValue::List(list) => list.get(segment.index(list.len())?).unwrap(),
+            _ => return Err(segment.not_a_container()),
+        };
+    }
+    Ok(value)
+}
+
+/// Returns a copy of the map with the setting at the path provided replaced by
+/// the value. If the last segment is a key that doesn't exist yet, the value
+/// is inserted under it, but everything leading up to it needs to exist.
+pub fn set(map: &SettingsMap, path: &str, value: SettingValue) -> Result<SettingsMap, PathError> {
+    let segments = segments(path)?;
+    let mut map = map.clone();
+    set_in_map(&mut map, &segments, value)?;
+    Ok(map)
+}
+
+fn set_in_map(
+    map: &mut SettingsMap,
+    segments: &[Segment<'_>],
+    value: SettingValue,
+) -> Result<(), PathError> {
+    let (segment, rest) = segments.split_first().unwrap();
+    let value = if rest.is_empty() {
+        value
+    } else {
+        let child = map.get(&segment.name).ok_or_else(|| segment.not_found())?;
+        replace_in(child, rest, value)?
+    };
+    map.insert((*segment.name).into(), value);
+    Ok(())
+}
+
+/// Returns a copy of the map or list with the setting at the path provided
+/// replaced by the value.
+fn replace_in(
+    container: &SettingValue,
+    segments: &[Segment<'_>],
+    value: SettingValue,
+) -> Result<SettingValue, PathError> {
+    match container {
+        SettingValue::Map(map) => {
+            let mut map = map.clone();
+            set_in_map(&mut map, segments, value)?;
+            Ok(SettingValue::Map(map))
+        }
+        SettingValue::List(list) => {
+            let (segment, rest) = segments.split_first().unwrap();
+            let index = segment.index(list.len())?;
+            let mut value = Some(if rest.is_empty() {
+                value
+            } else {
+                replace_in(list.get(index).unwrap(), rest, value)?
+            });
+
+            // Lists can't be modified in place, so they are rebuilt.
+            let mut replaced = SettingsList::new();
+            let mut i = 0;
+            while let Some(element) = list.get(i) {
+                replaced.push(if i == index {
+                    value.take().unwrap()
+                } else {
+                    element.clone()
+                });
+                i += 1;
+            }
+            Ok(SettingValue::List(replaced))
+        }
+        _ => Err(segments[0].not_a_container()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(path: &str) -> Vec<(String, &str, &str)> {
+        segments(path)
+            .unwrap()
+            .into_iter()
+            .map(|segment| (segment.name.into_owned(), segment.path, segment.parent))
+            .collect()
+    }
+
+    fn settings() -> SettingsMap {
+        let mut list = SettingsList::new();
+        list.push(SettingValue::I64(1));
+
+        let mut nested = SettingsMap::new();
+        nested.insert("list".into(), SettingValue::List(list));
+        nested.insert("flag".into(), SettingValue::Bool(true));
+
+        let mut map = SettingsMap::new();
+        map.insert("nested".into(), SettingValue::Map(nested));
+        map
+    }
+
+    #[test]
+    fn splits_paths_into_segments() {
+        assert_eq!(parse("key"), [("key".into(), "key", "")]);
+        assert_eq!(
+            parse("routes/0/name"),
+            [
+                ("routes".into(), "routes", ""),
+                ("0".into(), "routes/0", "routes"),
+                ("name".into(), "routes/0/name", "routes/0"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rejects_empty_segments() {
+        for path in ["", "/", "a//b", "/a", "a/"] {
+            assert!(
+                matches!(segments(path), Err(PathError::Malformed { path: p }) if &*p == path),
+                "{path}",
+            );
+        }
+    }
+
+    #[test]
+    fn unescapes_segments() {
+        assert_eq!(
+            parse("a~1b/c~0d/~01"),
+            [
+                ("a/b".into(), "a~1b", ""),
+                ("c~d".into(), "a~1b/c~0d", "a~1b"),
+                ("~1".into(), "a~1b/c~0d/~01", "a~1b/c~0d"),
+            ],
+        );
+    }
+
+    #[test]
+    fn escapes_keys_so_they_round_trip() {
+        assert!(matches!(escape("key"), Cow::Borrowed("key")));
+        assert_eq!(escape("a/b~c"), "a~1b~0c");
+        assert_eq!(escape("~1"), "~01");
+        for key in ["a/b~c", "~1", "/", "~~//"] {
+            assert_eq!(parse(&escape(key))[0].0, key);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_escape_sequences() {
+        for path in ["~", "a~", "a~2", "a/~b"] {
+            assert!(
+                matches!(segments(path), Err(PathError::Malformed { path: p }) if &*p == path),
+                "{path}",
+            );
+        }
+    }
+
+    #[test]
+    fn reports_where_the_path_went_wrong() {
+        let map = settings();
+
+        assert!(matches!(
+            get(&map, "nested/missing/key"),
+            Err(PathError::NotFound { path }) if &*path == "nested/missing",
+        ));
+        assert!(matches!(
+            get(&map, "nested/flag/key"),
+            Err(PathError::NotAContainer { path }) if &*path == "nested/flag",
+        ));
+        assert!(matches!(
+            get(&map, "nested/list/first"),
+            Err(PathError::NotAnIndex { path, segment }) if &*path == "nested/list" && &*segment == "first",
+        ));
+        assert!(matches!(
+            get(&map, "nested/list/1"),
+            Err(PathError::OutOfRange { path, index: 1, len: 1 }) if &*path == "nested/list",
+        ));
+    }
+
+    #[test]
+    fn rejects_indices_that_are_not_canonical() {
+        let map = settings();
+
+        assert!(matches!(
+            get(&map, "nested/list/0"),
+            Ok(SettingValue::I64(1))
+        ));
+        for index in ["+0", "00", "01", "-0", " 0", "0x0"] {
+            let path = format!("nested/list/{index}");
+            assert!(
+                matches!(
+                    get(&map, &path),
+                    Err(PathError::NotAnIndex { segment, .. }) if &*segment == index,
+                ),
+                "{path}",
+            );
+        }
+        assert!(matches!(
+            set(&map, "nested/list/+0", SettingValue::I64(2)),
+            Err(PathError::NotAnIndex { .. }),
+        ));
+    }
+
+    #[test]
+    fn replaces_nested_settings() {
+        let map = settings();
+
+        let replaced = set(&map, "nested/list/0", SettingValue::I64(2)).unwrap();
+        assert!(matches!(
+            get(&replaced, "nested/list/0"),
+            Ok(SettingValue::I64(2))
+        ));
+        // The original map is left untouched.
+        assert!(matches!(
+            get(&map, "nested/list/0"),
+            Ok(SettingValue::I64(1))
+        ));
+
+        let inserted = set(&map, "nested/new", SettingValue::Bool(false)).unwrap();
+        assert!(matches!(
+            get(&inserted, "nested/new"),
+            Ok(SettingValue::Bool(false))
+        ));
+        assert!(matches!(
+            set(&map, "missing/new", SettingValue::Bool(false)),
+            Err(PathError::NotFound { path }) if &*path == "missing",
+        ));
+    }
+}
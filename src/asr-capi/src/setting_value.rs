@@ -1,74 +1,45 @@
-use crate::{settings_list::SettingsList, settings_map::SettingsMap};
+use crate::{
+    error::set_type_mismatch, output_into, output_str, output_utf16_into,
+    settings_list::SettingsList, settings_map::SettingsMap, str,
+};
 
-#[cfg(target_pointer_width = "64")]
-use crate::{output_str, str};
-
-#[cfg(target_pointer_width = "64")]
-pub use livesplit_auto_splitting::settings::Value as SettingValue;
-
-#[cfg(not(target_pointer_width = "64"))]
-pub type SettingValue = ();
+pub use crate::settings::Value as SettingValue;
 
 #[no_mangle]
 pub extern "C" fn SettingValue_new_map(_map: Box<SettingsMap>) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::Map(*_map))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingValue::Map(*_map))
 }
 
 #[no_mangle]
 pub extern "C" fn SettingValue_new_list(_list: Box<SettingsList>) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::List(*_list))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingValue::List(*_list))
 }
 
 #[no_mangle]
 pub extern "C" fn SettingValue_new_bool(_value: bool) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::Bool(_value))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingValue::Bool(_value))
 }
 
 #[no_mangle]
 pub extern "C" fn SettingValue_new_i64(_value: i64) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::I64(_value))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingValue::I64(_value))
 }
 
 #[no_mangle]
 pub extern "C" fn SettingValue_new_f64(_value: f64) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::F64(_value))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingValue::F64(_value))
 }
 
+/// Creates a new string setting value. Returns null if the string is not valid
+/// UTF-8.
+///
 /// # Safety
-/// TODO:
+/// `value_ptr` must be a valid nul-terminated string.
 #[no_mangle]
-pub unsafe extern "C" fn SettingValue_new_string(_value_ptr: *const u8) -> Box<SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingValue::String(str(_value_ptr).into()))
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+pub unsafe extern "C" fn SettingValue_new_string(
+    _value_ptr: *const u8,
+) -> Option<Box<SettingValue>> {
+    Some(Box::new(SettingValue::String(str(_value_ptr)?.into())))
 }
 
 #[no_mangle]
@@ -76,96 +47,134 @@ pub extern "C" fn SettingValue_drop(_: Box<SettingValue>) {}
 
 #[no_mangle]
 pub extern "C" fn SettingValue_get_type(_this: &SettingValue) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::Map(_) => 1,
-            SettingValue::List(_) => 2,
-            SettingValue::Bool(_) => 3,
-            SettingValue::I64(_) => 4,
-            SettingValue::F64(_) => 5,
-            SettingValue::String(_) => 6,
-            _ => 0,
-        }
+    // The values of the runtime are non-exhaustive, unlike the ones used on
+    // other targets.
+    #[allow(unreachable_patterns)]
+    match _this {
+        SettingValue::Map(_) => 1,
+        SettingValue::List(_) => 2,
+        SettingValue::Bool(_) => 3,
+        SettingValue::I64(_) => 4,
+        SettingValue::F64(_) => 5,
+        SettingValue::String(_) => 6,
+        _ => 0,
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    0
 }
 
+/// Returns the map stored in the setting value. Returns null and sets the last
+/// error to a type mismatch if the value is not a map.
 #[no_mangle]
-pub extern "C" fn SettingValue_get_map(_this: &SettingValue) -> &SettingsMap {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::Map(v) => v,
-            _ => panic!("Wrong type"),
+pub extern "C" fn SettingValue_get_map(_this: &SettingValue) -> Option<&SettingsMap> {
+    match _this {
+        SettingValue::Map(v) => Some(v),
+        _ => {
+            set_type_mismatch("a map");
+            None
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
 
+/// Returns the list stored in the setting value. Returns null and sets the
+/// last error to a type mismatch if the value is not a list.
 #[no_mangle]
-pub extern "C" fn SettingValue_get_list(_this: &SettingValue) -> &SettingsList {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::List(v) => v,
-            _ => panic!("Wrong type"),
+pub extern "C" fn SettingValue_get_list(_this: &SettingValue) -> Option<&SettingsList> {
+    match _this {
+        SettingValue::List(v) => Some(v),
+        _ => {
+            set_type_mismatch("a list");
+            None
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
 
+/// Returns the bool stored in the setting value. Returns false and sets the
+/// last error to a type mismatch if the value is not a bool, so either the
+/// type should be checked with `SettingValue_get_type` or the last error
+/// should be cleared beforehand.
 #[no_mangle]
 pub extern "C" fn SettingValue_get_bool(_this: &SettingValue) -> bool {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::Bool(v) => *v,
-            _ => panic!("Wrong type"),
+    match _this {
+        SettingValue::Bool(v) => *v,
+        _ => {
+            set_type_mismatch("a bool");
+            false
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
 
+/// Returns the i64 stored in the setting value. Returns 0 and sets the last
+/// error to a type mismatch if the value is not an i64, like
+/// `SettingValue_get_bool`.
 #[no_mangle]
 pub extern "C" fn SettingValue_get_i64(_this: &SettingValue) -> i64 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::I64(v) => *v,
-            _ => panic!("Wrong type"),
+    match _this {
+        SettingValue::I64(v) => *v,
+        _ => {
+            set_type_mismatch("an i64");
+            0
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
 
+/// Returns the f64 stored in the setting value. Returns 0 and sets the last
+/// error to a type mismatch if the value is not an f64, like
+/// `SettingValue_get_bool`.
 #[no_mangle]
 pub extern "C" fn SettingValue_get_f64(_this: &SettingValue) -> f64 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::F64(v) => *v,
-            _ => panic!("Wrong type"),
+    match _this {
+        SettingValue::F64(v) => *v,
+        _ => {
+            set_type_mismatch("an f64");
+            0.0
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
 
+/// Returns the string stored in the setting value. Returns an empty string and
+/// sets the last error to a type mismatch if the value is not a string, like
+/// `SettingValue_get_bool`.
 #[no_mangle]
 pub extern "C" fn SettingValue_get_string(_this: &SettingValue) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this {
-            SettingValue::String(v) => output_str(v),
-            _ => panic!("Wrong type"),
+    output_str(string(_this))
+}
+
+/// Like `SettingValue_get_string`, but writes the string into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the string
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn SettingValue_get_string_into(
+    _this: &SettingValue,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(string(_this).as_bytes(), _buf, _cap)
+}
+
+/// Like `SettingValue_get_string`, but writes the string as UTF-16 into a
+/// buffer owned by the caller. See `get_buf_len` for how the buffer is filled.
+/// If the string doesn't fit, only a nul-terminator is written at the start of
+/// the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn SettingValue_get_string_utf16(
+    _this: &SettingValue,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(string(_this), _buf, _cap)
+}
+
+fn string(value: &SettingValue) -> &str {
+    match value {
+        SettingValue::String(v) => v,
+        _ => {
+            set_type_mismatch("a string");
+            ""
         }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Wrong type")
 }
@@ -0,0 +1,134 @@
+//! The settings types of `livesplit_auto_splitting` can't be compiled for
+//! targets other than 64-bit ones, so these mirror the parts of their API that
+//! are used by the C API. The values are plain data that get transferred to
+//! and from the helper process that runs the auto splitter.
+
+use std::sync::Arc;
+
+/// A map of settings. Cloning it is cheap, as the entries are shared until
+/// either of the maps gets modified.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Arc<Vec<(Arc<str>, Value)>>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the value under the key provided. An existing value for the
+    /// same key gets replaced, keeping its position in the map.
+    pub fn insert(&mut self, key: Arc<str>, value: Value) {
+        let entries = Arc::make_mut(&mut self.entries);
+        match entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => entries.push((key, value)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, _)| &**k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<(&str, &Value)> {
+        self.entries.get(index).map(|(k, v)| (&**k, v))
+    }
+
+    /// Returns whether this map is the same map as `old`, without any
+    /// modifications in between.
+    pub fn is_unchanged(&self, old: &Self) -> bool {
+        Arc::ptr_eq(&self.entries, &old.entries)
+    }
+}
+
+/// A list of settings.
+#[derive(Debug, Clone, Default)]
+pub struct List {
+    values: Arc<Vec<Value>>,
+}
+
+impl List {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: Value) {
+        Arc::make_mut(&mut self.values).push(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+}
+
+/// The value of a setting.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Map(Map),
+    List(List),
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+    String(Arc<str>),
+}
+
+/// A widget that the auto splitter wants to show in its settings.
+#[derive(Debug, Clone)]
+pub struct Widget {
+    pub key: Arc<str>,
+    pub description: Arc<str>,
+    pub tooltip: Option<Arc<str>>,
+    pub kind: WidgetKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum WidgetKind {
+    Title {
+        heading_level: u32,
+    },
+    Bool {
+        default_value: bool,
+    },
+    Choice {
+        default_option_key: Arc<str>,
+        options: Arc<[ChoiceOption]>,
+    },
+    FileSelect {
+        filters: Arc<[FileFilter]>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceOption {
+    pub key: Arc<str>,
+    pub description: Arc<str>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FileFilter {
+    Name {
+        description: Option<Arc<str>>,
+        pattern: Arc<str>,
+    },
+    MimeType(Arc<str>),
+}
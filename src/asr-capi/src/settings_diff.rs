@@ -0,0 +1,224 @@
+//! The differences between two settings maps, so a host can update only the
+//! parts of its settings UI that are affected by a change, or show which
+//! settings an auto splitter changed.
+
+use crate::{
+    output_into, output_str, output_utf16_into, setting_path, setting_value::SettingValue,
+    settings_list::SettingsList, settings_map::SettingsMap,
+};
+
+/// The changes that turn a previous settings map into the current one. Nested
+/// maps are compared key by key and nested lists index by index, so a change
+/// deep inside of them is reported at the path of the setting that actually
+/// changed, like `SettingsMap_get_value_by_path` expects it.
+pub struct SettingsDiff {
+    changes: Vec<Change>,
+}
+
+struct Change {
+    kind: ChangeKind,
+    path: Box<str>,
+    previous: Option<SettingValue>,
+    current: Option<SettingValue>,
+}
+
+#[derive(Copy, Clone)]
+enum ChangeKind {
+    Added = 1,
+    Removed = 2,
+    Changed = 3,
+}
+
+/// Compares the maps. Removed and changed settings are reported in the order
+/// of the previous map, followed by the added ones in the order of the current
+/// map.
+pub fn diff(previous: &SettingsMap, current: &SettingsMap) -> SettingsDiff {
+    let mut diff = SettingsDiff {
+        changes: Vec::new(),
+    };
+    diff.maps(&mut String::new(), previous, current);
+    diff
+}
+
+impl SettingsDiff {
+    fn push(
+        &mut self,
+        kind: ChangeKind,
+        path: &str,
+        previous: Option<&SettingValue>,
+        current: Option<&SettingValue>,
+    ) {
+        self.changes.push(Change {
+            kind,
+            path: path.into(),
+            previous: previous.cloned(),
+            current: current.cloned(),
+        });
+    }
+
+    fn maps(&mut self, path: &mut String, previous: &SettingsMap, current: &SettingsMap) {
+        if current.is_unchanged(previous) {
+            return;
+        }
+        let mut index = 0;
+        while let Some((key, previous_value)) = previous.get_by_index(index) {
+            let len = enter(path, key);
+            match current.get(key) {
+                Some(current_value) => self.values(path, previous_value, current_value),
+                None => self.push(ChangeKind::Removed, path, Some(previous_value), None),
+            }
+            path.truncate(len);
+            index += 1;
+        }
+        let mut index = 0;
+        while let Some((key, current_value)) = current.get_by_index(index) {
+            if previous.get(key).is_none() {
+                let len = enter(path, key);
+                self.push(ChangeKind::Added, path, None, Some(current_value));
+                path.truncate(len);
+            }
+            index += 1;
+        }
+    }
+
+    fn lists(&mut self, path: &mut String, previous: &SettingsList, current: &SettingsList) {
+        for index in 0..previous.len().max(current.len()) {
+            let len = enter(path, &index.to_string());
+            match (previous.get(index), current.get(index)) {
+                (Some(previous), Some(current)) => self.values(path, previous, current),
+                (Some(previous), None) => {
+                    self.push(ChangeKind::Removed, path, Some(previous), None)
+                }
+                (None, current) => self.push(ChangeKind::Added, path, None, current),
+            }
+            path.truncate(len);
+        }
+    }
+
+    fn values(&mut self, path: &mut String, previous: &SettingValue, current: &SettingValue) {
+        // The values of the runtime are non-exhaustive, unlike the ones used
+        // on other targets. Values of unknown types are always considered
+        // changed.
+        #[allow(unreachable_patterns)]
+        let unchanged = match (previous, current) {
+            (SettingValue::Map(previous), SettingValue::Map(current)) => {
+                return self.maps(path, previous, current);
+            }
+            (SettingValue::List(previous), SettingValue::List(current)) => {
+                return self.lists(path, previous, current);
+            }
+            (SettingValue::Bool(previous), SettingValue::Bool(current)) => previous == current,
+            (SettingValue::I64(previous), SettingValue::I64(current)) => previous == current,
+            // Comparing the bits treats NaN as unchanged, as long as it stays
+            // the same NaN.
+            (SettingValue::F64(previous), SettingValue::F64(current)) => {
+                previous.to_bits() == current.to_bits()
+            }
+            (SettingValue::String(previous), SettingValue::String(current)) => previous == current,
+            _ => false,
+        };
+        if !unchanged {
+            self.push(ChangeKind::Changed, path, Some(previous), Some(current));
+        }
+    }
+}
+
+/// Appends the escaped segment to the path and returns the length of the path
+/// before that, so it can be truncated back to it.
+fn enter(path: &mut String, segment: &str) -> usize {
+    let len = path.len();
+    if len != 0 {
+        path.push('/');
+    }
+    path.push_str(&setting_path::escape(segment));
+    len
+}
+
+#[no_mangle]
+pub extern "C" fn SettingsDiff_drop(_: Box<SettingsDiff>) {}
+
+/// Returns the number of changes. A diff without any changes means the maps
+/// hold the same settings.
+#[no_mangle]
+pub extern "C" fn SettingsDiff_len(_this: &SettingsDiff) -> usize {
+    _this.changes.len()
+}
+
+/// Returns what kind of change is at the index provided: 1 if the setting was
+/// added, 2 if it was removed and 3 if its value changed. Settings that change
+/// their type are reported as changed too. Returns 0 if the index is out of
+/// bounds.
+#[no_mangle]
+pub extern "C" fn SettingsDiff_get_kind(_this: &SettingsDiff, _index: usize) -> usize {
+    _this
+        .changes
+        .get(_index)
+        .map_or(0, |change| change.kind as usize)
+}
+
+/// Returns the path of the setting that changed, which consists of the keys of
+/// nested maps and the indices of nested lists, separated by slashes, like
+/// `routes/0/name`. A `/` in a key is escaped as `~1` and a `~` as `~0`, like
+/// `SettingsMap_get_value_by_path` expects it. Returns an empty string if the
+/// index is out of bounds.
+#[no_mangle]
+pub extern "C" fn SettingsDiff_get_path(_this: &SettingsDiff, _index: usize) -> *const u8 {
+    output_str(path(_this, _index))
+}
+
+/// Like `SettingsDiff_get_path`, but writes the path into a buffer owned by the
+/// caller. See `get_buf_len` for how the buffer is filled. If the path doesn't
+/// fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsDiff_get_path_into(
+    _this: &SettingsDiff,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(path(_this, _index).as_bytes(), _buf, _cap)
+}
+
+/// Like `SettingsDiff_get_path`, but writes the path as UTF-16 into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// path doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsDiff_get_path_utf16(
+    _this: &SettingsDiff,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(path(_this, _index), _buf, _cap)
+}
+
+fn path(diff: &SettingsDiff, index: usize) -> &str {
+    diff.changes.get(index).map_or("", |change| &change.path)
+}
+
+/// Returns the value the setting had in the previous map. Returns null if the
+/// setting was added or the index is out of bounds.
+#[no_mangle]
+pub extern "C" fn SettingsDiff_get_previous_value(
+    _this: &SettingsDiff,
+    _index: usize,
+) -> Option<&SettingValue> {
+    _this.changes.get(_index)?.previous.as_ref()
+}
+
+/// Returns the value the setting has in the current map. Returns null if the
+/// setting was removed or the index is out of bounds.
+#[no_mangle]
+pub extern "C" fn SettingsDiff_get_current_value(
+    _this: &SettingsDiff,
+    _index: usize,
+) -> Option<&SettingValue> {
+    _this.changes.get(_index)?.current.as_ref()
+}
@@ -0,0 +1,185 @@
+//! Converts settings maps to and from JSON, so hosts other than LiveSplit can
+//! store and share them. Maps become objects that keep the order of their
+//! keys and lists become arrays. Integers are written without a fractional
+//! part, while floating point numbers always get one or an exponent, so the two
+//! can be told apart when the JSON is read back.
+
+use crate::{setting_value::SettingValue, settings_list::SettingsList, settings_map::SettingsMap};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// Encodes the map as JSON. This fails if the map contains floating point
+/// numbers that are infinite or NaN, as JSON can't represent those.
+pub fn to_json(map: &SettingsMap) -> serde_json::Result<String> {
+    serde_json::to_string(&Json(map))
+}
+
+/// Decodes a map that was encoded with [`to_json`]. The JSON needs to be an
+/// object and can't contain any nulls or integers that don't fit into an
+/// `i64`.
+pub fn from_json(json: &str) -> serde_json::Result<SettingsMap> {
+    let map = serde_json::from_str::<Owned<SettingsMap>>(json)?.0;
+    match out_of_range_integer(json) {
+        Some(integer) => Err(integer_out_of_range(integer)),
+        None => Ok(map),
+    }
+}
+
+/// Returns the first integer in the valid JSON that doesn't fit into an `i64`.
+/// serde_json passes integers beyond the range of a `u64` on as floating point
+/// numbers, so they can only be told apart from actual floating point numbers
+/// by looking at how they are written.
+fn out_of_range_integer(json: &str) -> Option<&str> {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = None;
+    for (index, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9') {
+            start.get_or_insert(index);
+        } else {
+            if let Some(start) = start.take() {
+                let number = &json[start..index];
+                if !number.contains(['.', 'e', 'E']) && number.parse::<i64>().is_err() {
+                    return Some(number);
+                }
+            }
+            in_string = c == '"';
+        }
+    }
+    None
+}
+
+fn integer_out_of_range<E: de::Error>(integer: impl fmt::Display) -> E {
+    E::custom(format_args!(
+        "The integer {integer} doesn't fit into 64 bits. Write it as {integer}.0 to store it \
+         as a floating point number.",
+    ))
+}
+
+/// Serializes the settings type it wraps.
+struct Json<'a, T>(&'a T);
+
+/// Deserializes into the settings type it wraps.
+struct Owned<T>(T);
+
+impl Serialize for Json<'_, SettingsMap> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        let mut index = 0;
+        while let Some((key, value)) = self.0.get_by_index(index) {
+            map.serialize_entry(key, &Json(value))?;
+            index += 1;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Json<'_, SettingsList> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut list = serializer.serialize_seq(Some(self.0.len()))?;
+        let mut index = 0;
+        while let Some(value) = self.0.get(index) {
+            list.serialize_element(&Json(value))?;
+            index += 1;
+        }
+        list.end()
+    }
+}
+
+impl Serialize for Json<'_, SettingValue> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The values of the runtime are non-exhaustive, unlike the ones used
+        // on other targets.
+        #[allow(unreachable_patterns)]
+        match self.0 {
+            SettingValue::Map(v) => Json(v).serialize(serializer),
+            SettingValue::List(v) => Json(v).serialize(serializer),
+            SettingValue::Bool(v) => serializer.serialize_bool(*v),
+            SettingValue::I64(v) => serializer.serialize_i64(*v),
+            SettingValue::F64(v) if v.is_finite() => serializer.serialize_f64(*v),
+            SettingValue::F64(v) => Err(ser::Error::custom(format_args!(
+                "The number {v} can't be represented in JSON.",
+            ))),
+            SettingValue::String(v) => serializer.serialize_str(v),
+            _ => Err(ser::Error::custom(
+                "The setting value is of an unknown type.",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Owned<SettingsMap> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(ValueVisitor)
+            .and_then(|value| match value {
+                SettingValue::Map(map) => Ok(Owned(map)),
+                _ => Err(de::Error::custom("The settings need to be a JSON object.")),
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for Owned<SettingValue> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor).map(Owned)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = SettingValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object, array, bool, number or string")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(SettingValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(SettingValue::I64(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v)
+            .map(SettingValue::I64)
+            .map_err(|_| integer_out_of_range(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(SettingValue::F64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(SettingValue::String(v.into()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut list = SettingsList::new();
+        while let Some(Owned(value)) = seq.next_element()? {
+            list.push(value);
+        }
+        Ok(SettingValue::List(list))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = SettingsMap::new();
+        while let Some((key, Owned(value))) = access.next_entry::<String, _>()? {
+            map.insert(key.into(), value);
+        }
+        Ok(SettingValue::Map(map))
+    }
+}
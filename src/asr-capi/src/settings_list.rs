@@ -1,19 +1,10 @@
-use crate::setting_value::SettingValue;
+use crate::{error::set_index_out_of_range, setting_value::SettingValue};
 
-#[cfg(target_pointer_width = "64")]
-pub use livesplit_auto_splitting::settings::List as SettingsList;
-
-#[cfg(not(target_pointer_width = "64"))]
-pub type SettingsList = ();
+pub use crate::settings::List as SettingsList;
 
 #[no_mangle]
 pub extern "C" fn SettingsList_new() -> Box<SettingsList> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingsList::new())
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingsList::new())
 }
 
 #[no_mangle]
@@ -21,28 +12,21 @@ pub extern "C" fn SettingsList_drop(_: Box<SettingsList>) {}
 
 #[no_mangle]
 pub extern "C" fn SettingsList_push(_this: &mut SettingsList, _value: Box<SettingValue>) {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.push(*_value);
-    }
+    _this.push(*_value);
 }
 
 #[no_mangle]
 pub extern "C" fn SettingsList_len(_this: &SettingsList) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.len()
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    0
+    _this.len()
 }
 
+/// Returns the value at the index provided. Returns null and sets the last
+/// error if the index is out of bounds.
 #[no_mangle]
-pub extern "C" fn SettingsList_get(_this: &SettingsList, _index: usize) -> &SettingValue {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.get(_index).unwrap()
+pub extern "C" fn SettingsList_get(_this: &SettingsList, _index: usize) -> Option<&SettingValue> {
+    let value = _this.get(_index);
+    if value.is_none() {
+        set_index_out_of_range(_index, _this.len());
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    value
 }
@@ -1,80 +1,351 @@
-use crate::setting_value::SettingValue;
+use crate::{
+    error::{set_index_out_of_range, set_last_error, ErrorKind},
+    output_into, output_str, output_utf16_into,
+    setting_path::{self, set_path_error},
+    setting_value::SettingValue,
+    settings_diff::{self, SettingsDiff},
+    settings_json, settings_xml, str,
+};
+use std::ptr;
 
-#[cfg(target_pointer_width = "64")]
-use crate::{output_str, str};
-
-#[cfg(target_pointer_width = "64")]
-pub use livesplit_auto_splitting::settings::Map as SettingsMap;
-
-#[cfg(not(target_pointer_width = "64"))]
-pub type SettingsMap = ();
+pub use crate::settings::Map as SettingsMap;
 
 #[no_mangle]
 pub extern "C" fn SettingsMap_new() -> Box<SettingsMap> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        Box::new(SettingsMap::new())
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    Box::new(())
+    Box::new(SettingsMap::new())
 }
 
 #[no_mangle]
 pub extern "C" fn SettingsMap_drop(_: Box<SettingsMap>) {}
 
+/// Inserts the value into the map under the key provided. The value is
+/// consumed either way. Returns false and leaves the map unchanged if the key
+/// is not valid UTF-8.
+///
 /// # Safety
-/// TODO:
+/// `key_ptr` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn SettingsMap_insert(
     _this: &mut SettingsMap,
     _key_ptr: *const u8,
     _value: Box<SettingValue>,
-) {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.insert(str(_key_ptr).into(), *_value);
+) -> bool {
+    let Some(key) = str(_key_ptr) else {
+        return false;
+    };
+    _this.insert(key.into(), *_value);
+    true
+}
+
+/// Removes the value stored under the key provided, keeping the order of the
+/// remaining entries. Returns whether there was such a value.
+pub fn remove(map: &mut SettingsMap, key: &str) -> bool {
+    if map.get(key).is_none() {
+        return false;
+    }
+    let mut remaining = SettingsMap::new();
+    let mut index = 0;
+    while let Some((k, value)) = map.get_by_index(index) {
+        if k != key {
+            remaining.insert(k.into(), value.clone());
+        }
+        index += 1;
     }
+    *map = remaining;
+    true
+}
+
+/// Removes the value stored under the key provided from the map. Returns false
+/// if there is no such key or the key is not valid UTF-8.
+///
+/// # Safety
+/// `key_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_remove(_this: &mut SettingsMap, _key_ptr: *const u8) -> bool {
+    let Some(key) = str(_key_ptr) else {
+        return false;
+    };
+    remove(_this, key)
 }
 
 #[no_mangle]
 pub extern "C" fn SettingsMap_len(_this: &SettingsMap) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.len()
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    0
+    _this.len()
 }
 
+/// Returns the key at the index provided. Returns an empty string and sets the
+/// last error if the index is out of bounds.
 #[no_mangle]
 pub extern "C" fn SettingsMap_get_key(_this: &SettingsMap, _index: usize) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        output_str(_this.get_by_index(_index).unwrap().0)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(key(_this, _index))
+}
+
+/// Like `SettingsMap_get_key`, but writes the key into a buffer owned by the
+/// caller. See `get_buf_len` for how the buffer is filled. If the key doesn't
+/// fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_get_key_into(
+    _this: &SettingsMap,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(key(_this, _index).as_bytes(), _buf, _cap)
 }
 
+/// Like `SettingsMap_get_key`, but writes the key as UTF-16 into a buffer owned
+/// by the caller. See `get_buf_len` for how the buffer is filled. If the key
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
 #[no_mangle]
-pub extern "C" fn SettingsMap_get_value(_this: &SettingsMap, _index: usize) -> &SettingValue {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.get_by_index(_index).unwrap().1
+pub unsafe extern "C" fn SettingsMap_get_key_utf16(
+    _this: &SettingsMap,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(key(_this, _index), _buf, _cap)
+}
+
+fn key(map: &SettingsMap, index: usize) -> &str {
+    entry(map, index).map_or("", |(key, _)| key)
+}
+
+/// Returns the value at the index provided. Returns null and sets the last
+/// error if the index is out of bounds.
+#[no_mangle]
+pub extern "C" fn SettingsMap_get_value(
+    _this: &SettingsMap,
+    _index: usize,
+) -> Option<&SettingValue> {
+    entry(_this, _index).map(|(_, value)| value)
+}
+
+fn entry(map: &SettingsMap, index: usize) -> Option<(&str, &SettingValue)> {
+    let entry = map.get_by_index(index);
+    if entry.is_none() {
+        set_index_out_of_range(index, map.len());
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    entry
 }
 
+/// Returns the value stored under the key provided. Returns null if there is
+/// no such key or the key is not valid UTF-8.
+///
+/// # Safety
+/// `key_ptr` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn SettingsMap_get_value_by_key(
     _this: &SettingsMap,
     _key_ptr: *const u8,
 ) -> Option<&SettingValue> {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.get(str(_key_ptr))
+    _this.get(str(_key_ptr)?)
+}
+
+/// Returns the value at the path provided, which consists of the keys of
+/// nested maps and the indices of nested lists, separated by slashes, like
+/// `routes/0/name`. Like in JSON Pointers, a `/` in a key is written as `~1`
+/// and a `~` as `~0`, and indices are written without a sign or leading zeros.
+/// Segments can't be empty, so values stored under an empty key can't be
+/// reached through a path. Returns null and stores the last error if the path
+/// doesn't lead to a value or is not valid UTF-8.
+///
+/// # Safety
+/// `path_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_get_value_by_path(
+    _this: &SettingsMap,
+    _path_ptr: *const u8,
+) -> Option<&SettingValue> {
+    match setting_path::get(_this, str(_path_ptr)?) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            set_path_error(err);
+            None
+        }
+    }
+}
+
+/// Replaces the value at the path provided, like the paths of
+/// `SettingsMap_get_value_by_path`. If the last segment of the path is a key
+/// that doesn't exist yet, the value is inserted under it, but all the maps and
+/// lists leading up to it need to exist. The value is consumed either way.
+/// Returns false, leaves the map unchanged and stores the last error if the
+/// path doesn't lead to a value or is not valid UTF-8.
+///
+/// # Safety
+/// `path_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_set_value_by_path(
+    _this: &mut SettingsMap,
+    _path_ptr: *const u8,
+    _value: Box<SettingValue>,
+) -> bool {
+    let Some(path) = str(_path_ptr) else {
+        return false;
+    };
+    match setting_path::set(_this, path, *_value) {
+        Ok(map) => {
+            *_this = map;
+            true
+        }
+        Err(err) => {
+            set_path_error(err);
+            false
+        }
+    }
+}
+
+/// Compares the previous map to the current one and returns the settings that
+/// were added, removed or changed, including the ones nested inside of maps
+/// and lists. The diff needs to be dropped with `SettingsDiff_drop`.
+#[no_mangle]
+pub extern "C" fn SettingsMap_diff(
+    _previous: &SettingsMap,
+    _current: &SettingsMap,
+) -> Box<SettingsDiff> {
+    Box::new(settings_diff::diff(_previous, _current))
+}
+
+/// Encodes the map as JSON. Maps become objects and lists become arrays.
+/// Integers are encoded without a fractional part, while floating point
+/// numbers always have one or an exponent, so `SettingsMap_from_json` restores
+/// the same types. Returns null and stores the last error if the map contains
+/// floating point numbers that are infinite or NaN, as JSON can't represent
+/// those.
+#[no_mangle]
+pub extern "C" fn SettingsMap_to_json(_this: &SettingsMap) -> *const u8 {
+    match to_json(_this) {
+        Some(json) => output_str(&json),
+        None => ptr::null(),
+    }
+}
+
+/// Like `SettingsMap_to_json`, but writes the JSON into a buffer owned by the
+/// caller. See `get_buf_len` for how the buffer is filled. If the JSON doesn't
+/// fit, only a nul-terminator is written at the start of the buffer. Returns 0
+/// if the map can't be encoded, as the JSON is never empty otherwise.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_to_json_into(
+    _this: &SettingsMap,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    match to_json(_this) {
+        Some(json) => output_into(json.as_bytes(), _buf, _cap),
+        None => 0,
+    }
+}
+
+fn to_json(map: &SettingsMap) -> Option<String> {
+    match settings_json::to_json(map) {
+        Ok(json) => Some(json),
+        Err(err) => {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::Error::from(err).context("Failed encoding the settings as JSON."),
+            );
+            None
+        }
+    }
+}
+
+/// Decodes a map from the JSON provided, which needs to be an object. Whole
+/// numbers become integers, unless they have a fractional part or an exponent,
+/// like `1.0`, which makes them floating point numbers. Returns null and stores
+/// the last error if the JSON is invalid, contains nulls or integers that don't
+/// fit into 64 bits, or is not valid UTF-8.
+///
+/// # Safety
+/// `json_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_from_json(_json_ptr: *const u8) -> Option<Box<SettingsMap>> {
+    match settings_json::from_json(str(_json_ptr)?) {
+        Ok(map) => Some(Box::new(map)),
+        Err(err) => {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::Error::from(err).context("Failed decoding the settings from JSON."),
+            );
+            None
+        }
+    }
+}
+
+/// Encodes the map as the `CustomSettings` element that LiveSplit stores in
+/// its layouts for the settings of an auto splitter, so other frontends can
+/// share them. Every entry is a `Setting` element with an `id` and a `type`
+/// attribute, and lists contain `Setting` elements without an `id`. Returns
+/// null and stores the last error if a key or string contains characters that
+/// XML can't represent, like most control characters.
+#[no_mangle]
+pub extern "C" fn SettingsMap_to_xml(_this: &SettingsMap) -> *const u8 {
+    match to_xml(_this) {
+        Some(xml) => output_str(&xml),
+        None => ptr::null(),
+    }
+}
+
+/// Like `SettingsMap_to_xml`, but writes the XML into a buffer owned by the
+/// caller. See `get_buf_len` for how the buffer is filled. If the XML doesn't
+/// fit, only a nul-terminator is written at the start of the buffer. Returns 0
+/// if the map can't be encoded, as the XML is never empty otherwise.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_to_xml_into(
+    _this: &SettingsMap,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    match to_xml(_this) {
+        Some(xml) => output_into(xml.as_bytes(), _buf, _cap),
+        None => 0,
+    }
+}
+
+fn to_xml(map: &SettingsMap) -> Option<String> {
+    match settings_xml::to_xml(map) {
+        Ok(xml) => Some(xml),
+        Err(err) => {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::Error::from(err).context("Failed encoding the settings as XML."),
+            );
+            None
+        }
+    }
+}
+
+/// Decodes a map from the first `CustomSettings` element in the XML provided,
+/// which may also be a whole layout or the settings of its auto splitting
+/// runtime component. Like LiveSplit itself, this fails on the first setting
+/// that can't be read instead of skipping it. Returns null and stores the last
+/// error if the XML is malformed, a setting is missing its `id` or `type`, has
+/// a type that is unknown or a value that doesn't match it, or the XML is not
+/// valid UTF-8.
+///
+/// # Safety
+/// `xml_ptr` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn SettingsMap_from_xml(_xml_ptr: *const u8) -> Option<Box<SettingsMap>> {
+    match settings_xml::from_xml(str(_xml_ptr)?) {
+        Ok(map) => Some(Box::new(map)),
+        Err(err) => {
+            set_last_error(
+                ErrorKind::InvalidInput,
+                &anyhow::Error::from(err).context("Failed decoding the settings from XML."),
+            );
+            None
+        }
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    None
 }
@@ -0,0 +1,547 @@
+//! Reads and writes settings maps in the XML layout that LiveSplit stores in
+//! its layout files:
+//!
+//! ```xml
+//! <CustomSettings>
+//!   <Setting id="category" type="string" value="Any%" />
+//!   <Setting id="splits" type="list">
+//!     <Setting type="bool">True</Setting>
+//!     <Setting type="f64">1.5</Setting>
+//!   </Setting>
+//! </CustomSettings>
+//! ```
+//!
+//! Every entry of a map is a `Setting` element with an `id`, while the
+//! elements of a list have none. Booleans, integers and floating point numbers
+//! are stored as text, formatted the way .NET Core formats them, and strings
+//! are stored in the `value` attribute. The XML itself is read by `quick-xml`,
+//! which doesn't expand the custom entities that a DTD may declare, so those
+//! are rejected.
+
+use crate::{setting_value::SettingValue, settings_list::SettingsList, settings_map::SettingsMap};
+use quick_xml::{
+    escape::unescape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use std::fmt;
+
+/// Why a map couldn't be read from or written as XML.
+#[derive(Debug)]
+pub struct XmlError {
+    message: Box<str>,
+    /// The line and column where reading the XML failed.
+    position: Option<(usize, usize)>,
+}
+
+impl XmlError {
+    fn new(message: impl fmt::Display) -> Self {
+        Self {
+            message: message.to_string().into(),
+            position: None,
+        }
+    }
+
+    fn at(xml: &str, mut offset: usize, message: impl fmt::Display) -> Self {
+        offset = offset.min(xml.len());
+        while !xml.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &xml[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+            .chars()
+            .count()
+            + 1;
+        Self {
+            message: message.to_string().into(),
+            position: Some((line, column)),
+        }
+    }
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some((line, column)) = self.position {
+            write!(f, " (line {line}, column {column})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+/// Encodes the map as a `CustomSettings` element, indented the way LiveSplit
+/// saves its layouts. Values of kinds that the layout can't store are left
+/// out. This fails if a key or string contains characters that XML can't
+/// represent, like most control characters.
+pub fn to_xml(map: &SettingsMap) -> Result<String, XmlError> {
+    let mut xml = String::from("<CustomSettings");
+    write_children(&mut xml, 0, "CustomSettings", |xml| write_map(xml, map, 1))?;
+    Ok(xml)
+}
+
+/// Decodes a map from the first `CustomSettings` element in the XML provided.
+/// This can either be the element itself or any part of a layout that
+/// contains it, like the `Settings` of the auto splitting runtime component.
+pub fn from_xml(xml: &str) -> Result<SettingsMap, XmlError> {
+    let mut reader = SettingsReader {
+        xml,
+        reader: Reader::from_str(xml),
+    };
+    loop {
+        let (offset, event) = reader.next()?;
+        let (start, is_empty) = match event {
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            Event::Eof => {
+                return Err(XmlError::at(
+                    xml,
+                    offset,
+                    "There is no `CustomSettings` element in the XML.",
+                ))
+            }
+            _ => continue,
+        };
+        if start.name().as_ref() == b"CustomSettings" {
+            let element = Element {
+                start,
+                is_empty,
+                offset,
+            };
+            return reader.read_map(&element, 0);
+        }
+    }
+}
+
+fn write_map(xml: &mut String, map: &SettingsMap, depth: usize) -> Result<(), XmlError> {
+    let mut index = 0;
+    while let Some((key, value)) = map.get_by_index(index) {
+        write_value(xml, Some(key), value, depth)?;
+        index += 1;
+    }
+    Ok(())
+}
+
+fn write_list(xml: &mut String, list: &SettingsList, depth: usize) -> Result<(), XmlError> {
+    let mut index = 0;
+    while let Some(value) = list.get(index) {
+        write_value(xml, None, value, depth)?;
+        index += 1;
+    }
+    Ok(())
+}
+
+fn write_value(
+    xml: &mut String,
+    id: Option<&str>,
+    value: &SettingValue,
+    depth: usize,
+) -> Result<(), XmlError> {
+    // The values of the runtime are non-exhaustive, unlike the ones used on
+    // other targets.
+    #[allow(unreachable_patterns)]
+    let kind = match value {
+        SettingValue::Map(_) => "map",
+        SettingValue::List(_) => "list",
+        SettingValue::Bool(_) => "bool",
+        SettingValue::I64(_) => "i64",
+        SettingValue::F64(_) => "f64",
+        SettingValue::String(_) => "string",
+        _ => return Ok(()),
+    };
+
+    write_indent(xml, depth);
+    xml.push_str("<Setting");
+    if let Some(id) = id {
+        write_attribute(xml, "id", id)?;
+    }
+    write_attribute(xml, "type", kind)?;
+
+    #[allow(unreachable_patterns)]
+    match value {
+        SettingValue::Map(map) => {
+            write_children(xml, depth, "Setting", |xml| write_map(xml, map, depth + 1))?
+        }
+        SettingValue::List(list) => write_children(xml, depth, "Setting", |xml| {
+            write_list(xml, list, depth + 1)
+        })?,
+        SettingValue::Bool(v) => write_text(xml, if *v { "True" } else { "False" }),
+        SettingValue::I64(v) => write_text(xml, &v.to_string()),
+        SettingValue::F64(v) => write_text(xml, &format_f64(*v)),
+        SettingValue::String(v) => {
+            write_attribute(xml, "value", v)?;
+            xml.push_str(" />");
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Finishes the start tag of an element and writes its children, or closes it
+/// right away if it ends up without any.
+fn write_children(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    write: impl FnOnce(&mut String) -> Result<(), XmlError>,
+) -> Result<(), XmlError> {
+    xml.push('>');
+    let start = xml.len();
+    write(xml)?;
+    if xml.len() == start {
+        xml.pop();
+        xml.push_str(" />");
+    } else {
+        write_indent(xml, depth);
+        xml.push_str("</");
+        xml.push_str(name);
+        xml.push('>');
+    }
+    Ok(())
+}
+
+fn write_text(xml: &mut String, text: &str) {
+    xml.push('>');
+    xml.push_str(text);
+    xml.push_str("</Setting>");
+}
+
+fn write_indent(xml: &mut String, depth: usize) {
+    xml.push('\n');
+    for _ in 0..depth {
+        xml.push_str("  ");
+    }
+}
+
+fn write_attribute(xml: &mut String, name: &str, value: &str) -> Result<(), XmlError> {
+    xml.push(' ');
+    xml.push_str(name);
+    xml.push_str("=\"");
+    for c in value.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            // Whitespace other than spaces is turned into spaces when
+            // attributes are read, unless it is escaped.
+            '\t' => xml.push_str("&#x9;"),
+            '\n' => xml.push_str("&#xA;"),
+            '\r' => xml.push_str("&#xD;"),
+            c if is_xml_char(c) => xml.push(c),
+            c => {
+                return Err(XmlError::new(format_args!(
+                    "The character U+{:04X} can't be represented in XML.",
+                    c as u32,
+                )))
+            }
+        }
+    }
+    xml.push('"');
+    Ok(())
+}
+
+/// Formats the number like .NET Core 3.0 and later format it with the
+/// invariant culture, or like the `R` format does on .NET Framework, so it's
+/// the shortest text that parses back to the same number. It switches to
+/// scientific notation like `1E+15` for very large or small numbers. The
+/// default format of .NET Framework only keeps 15 significant digits, which
+/// would lose precision, but it reads the shortest text back all the same.
+fn format_f64(v: f64) -> String {
+    if v.is_nan() {
+        return "NaN".into();
+    }
+    if v.is_infinite() {
+        return if v > 0.0 { "Infinity" } else { "-Infinity" }.into();
+    }
+    let scientific = format!("{v:e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if v == 0.0 || (-5 < exponent && exponent < 15) {
+        format!("{v}")
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}E{sign}{:02}", exponent.unsigned_abs())
+    }
+}
+
+/// Whether XML 1.0 allows the character to appear in a document.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}') || c >= '\u{10000}'
+}
+
+/// How deeply settings may be nested, so a malicious document can't overflow
+/// the stack.
+const MAX_DEPTH: usize = 128;
+
+/// An element whose start tag was just read.
+struct Element<'a> {
+    start: BytesStart<'a>,
+    /// Whether the element is written as `<Setting />`, so it has no content
+    /// and no end tag.
+    is_empty: bool,
+    /// Where the element starts in the XML.
+    offset: usize,
+}
+
+impl Element<'_> {
+    fn name(&self) -> String {
+        String::from_utf8_lossy(self.start.name().as_ref()).into_owned()
+    }
+}
+
+/// Maps the `Setting` elements that the XML reader encounters to settings.
+struct SettingsReader<'a> {
+    xml: &'a str,
+    reader: Reader<&'a [u8]>,
+}
+
+impl<'a> SettingsReader<'a> {
+    fn error(&self, offset: usize, message: impl fmt::Display) -> XmlError {
+        XmlError::at(self.xml, offset, message)
+    }
+
+    /// Reads the next event, along with where it starts in the XML.
+    fn next(&mut self) -> Result<(usize, Event<'a>), XmlError> {
+        let offset = self.reader.buffer_position() as usize;
+        match self.reader.read_event() {
+            Ok(event) => Ok((offset, event)),
+            Err(err) => Err(self.error(self.reader.error_position() as usize, err)),
+        }
+    }
+
+    fn read_map(&mut self, element: &Element<'a>, depth: usize) -> Result<SettingsMap, XmlError> {
+        let mut map = SettingsMap::new();
+        self.read_settings(element, |reader, setting| {
+            let id = reader.attribute(&setting, "id")?;
+            map.insert(id.into(), reader.read_value(setting, depth + 1)?);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+
+    fn read_list(&mut self, element: &Element<'a>, depth: usize) -> Result<SettingsList, XmlError> {
+        let mut list = SettingsList::new();
+        self.read_settings(element, |reader, setting| {
+            list.push(reader.read_value(setting, depth + 1)?);
+            Ok(())
+        })?;
+        Ok(list)
+    }
+
+    fn read_value(&mut self, element: Element<'a>, depth: usize) -> Result<SettingValue, XmlError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(
+                element.offset,
+                format_args!("The settings are nested more than {MAX_DEPTH} levels deep."),
+            ));
+        }
+        let kind = self.attribute(&element, "type")?;
+        let (xml, offset) = (self.xml, element.offset);
+        let invalid = |text: &str| {
+            XmlError::at(
+                xml,
+                offset,
+                format_args!("`{text}` is not a valid value for a setting of type `{kind}`."),
+            )
+        };
+        Ok(match &*kind {
+            "map" => SettingValue::Map(self.read_map(&element, depth)?),
+            "list" => SettingValue::List(self.read_list(&element, depth)?),
+            "bool" => {
+                // .NET writes `True` and `False`, but accepts any casing.
+                let text = self.read_text(&element)?;
+                if text.eq_ignore_ascii_case("true") {
+                    SettingValue::Bool(true)
+                } else if text.eq_ignore_ascii_case("false") {
+                    SettingValue::Bool(false)
+                } else {
+                    return Err(invalid(&text));
+                }
+            }
+            "i64" => {
+                let text = self.read_text(&element)?;
+                SettingValue::I64(text.parse().map_err(|_| invalid(&text))?)
+            }
+            "f64" => {
+                // This also accepts .NET's `NaN`, `Infinity` and `-Infinity`.
+                let text = self.read_text(&element)?;
+                SettingValue::F64(text.parse().map_err(|_| invalid(&text))?)
+            }
+            "string" => {
+                let value = self.attribute(&element, "value")?;
+                if !element.is_empty {
+                    self.reader
+                        .read_to_end(element.start.name())
+                        .map_err(|err| self.error(self.reader.error_position() as usize, err))?;
+                }
+                SettingValue::String(value.into())
+            }
+            _ => {
+                return Err(self.error(
+                    element.offset,
+                    format_args!("`{kind}` is not a known type of setting."),
+                ))
+            }
+        })
+    }
+
+    /// Reads the content of the element up to its end tag, which needs to
+    /// consist of `Setting` elements. Whitespace and comments between them are
+    /// ignored.
+    fn read_settings(
+        &mut self,
+        element: &Element<'a>,
+        mut read: impl FnMut(&mut Self, Element<'a>) -> Result<(), XmlError>,
+    ) -> Result<(), XmlError> {
+        if element.is_empty {
+            return Ok(());
+        }
+        loop {
+            let (offset, event) = self.next()?;
+            let (start, is_empty) = match event {
+                Event::Start(start) => (start, false),
+                Event::Empty(start) => (start, true),
+                Event::Text(text) if is_whitespace(&text) => continue,
+                Event::CData(text) if is_whitespace(&text) => continue,
+                Event::Text(_) | Event::CData(_) => {
+                    return Err(self.error(offset, "Expected a `Setting` element, found text."))
+                }
+                Event::End(_) => return Ok(()),
+                Event::Eof => return Err(self.unclosed(element)),
+                _ => continue,
+            };
+            let child = Element {
+                start,
+                is_empty,
+                offset,
+            };
+            if child.start.name().as_ref() != b"Setting" {
+                return Err(self.error(
+                    offset,
+                    format_args!("Expected a `Setting` element, found `{}`.", child.name()),
+                ));
+            }
+            read(self, child)?;
+        }
+    }
+
+    /// Reads the text of an element that contains no other elements up to its
+    /// end tag, with surrounding whitespace removed.
+    fn read_text(&mut self, element: &Element<'a>) -> Result<String, XmlError> {
+        let mut text = String::new();
+        if element.is_empty {
+            return Ok(text);
+        }
+        loop {
+            let (offset, event) = self.next()?;
+            match event {
+                Event::Text(raw) => {
+                    text.push_str(&raw.unescape().map_err(|err| self.error(offset, err))?)
+                }
+                Event::CData(raw) => text.push_str(&String::from_utf8_lossy(&raw)),
+                Event::Start(_) | Event::Empty(_) => {
+                    return Err(self.error(
+                        element.offset,
+                        format_args!("The `{}` element can only contain text.", element.name()),
+                    ))
+                }
+                Event::End(_) => return Ok(text.trim().into()),
+                Event::Eof => return Err(self.unclosed(element)),
+                _ => {}
+            }
+        }
+    }
+
+    fn attribute(&self, element: &Element<'_>, name: &str) -> Result<String, XmlError> {
+        let attribute = element
+            .start
+            .try_get_attribute(name)
+            .map_err(|err| self.error(element.offset, err))?
+            .ok_or_else(|| {
+                self.error(
+                    element.offset,
+                    format_args!(
+                        "The `{}` element is missing the `{name}` attribute.",
+                        element.name()
+                    ),
+                )
+            })?;
+        // Like any XML parser, this turns line breaks and tabs into spaces,
+        // unless they are written as character references.
+        let value = String::from_utf8_lossy(&attribute.value)
+            .replace("\r\n", " ")
+            .replace(['\t', '\n', '\r'], " ");
+        match unescape(&value) {
+            Ok(value) => Ok(value.into_owned()),
+            Err(err) => Err(self.error(element.offset, err)),
+        }
+    }
+
+    fn unclosed(&self, element: &Element<'_>) -> XmlError {
+        self.error(
+            element.offset,
+            format_args!("The `{}` element is never closed.", element.name()),
+        )
+    }
+}
+
+fn is_whitespace(text: &[u8]) -> bool {
+    text.iter()
+        .all(|c| matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_floats() {
+        assert_eq!(format_f64(0.0), "0");
+        assert_eq!(format_f64(-0.0), "-0");
+        assert_eq!(format_f64(1.0), "1");
+        assert_eq!(format_f64(-123.5), "-123.5");
+        assert_eq!(format_f64(0.0001), "0.0001");
+        assert_eq!(format_f64(1e14), "100000000000000");
+    }
+
+    #[test]
+    fn formats_very_large_and_small_floats_in_scientific_notation() {
+        assert_eq!(format_f64(1e15), "1E+15");
+        assert_eq!(format_f64(1.5e300), "1.5E+300");
+        assert_eq!(format_f64(1e-5), "1E-05");
+        assert_eq!(format_f64(-2.5e-7), "-2.5E-07");
+    }
+
+    #[test]
+    fn formats_special_floats() {
+        assert_eq!(format_f64(f64::NAN), "NaN");
+        assert_eq!(format_f64(f64::INFINITY), "Infinity");
+        assert_eq!(format_f64(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn formats_floats_so_they_round_trip() {
+        assert_eq!(format_f64(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format_f64(0.1 + 0.2).parse::<f64>().unwrap(), 0.1 + 0.2);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_settings() {
+        let nested = |depth| {
+            format!(
+                "<CustomSettings>{}{}</CustomSettings>",
+                r#"<Setting id="a" type="map">"#.repeat(depth),
+                "</Setting>".repeat(depth),
+            )
+        };
+        assert!(from_xml(&nested(MAX_DEPTH)).is_ok());
+        let Err(err) = from_xml(&nested(MAX_DEPTH + 1)) else {
+            panic!("The nesting is too deep.");
+        };
+        assert!(err.to_string().contains("nested"), "{err}");
+        assert!(from_xml(&nested(100_000)).is_err());
+    }
+}
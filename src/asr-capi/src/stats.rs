@@ -0,0 +1,164 @@
+use std::{sync::Mutex, time::Duration};
+
+/// The amount of most recent steps that the percentiles are calculated from.
+const RECENT_STEPS: usize = 1024;
+
+/// Statistics about how long the steps of an auto splitter take.
+#[derive(Default)]
+pub struct StepStats {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    steps: u64,
+    failures: u64,
+    overruns: u64,
+    total: Duration,
+    last: Duration,
+    max: Duration,
+    /// A ring buffer of the durations of the most recent steps.
+    recent: Vec<Duration>,
+    next: usize,
+    tick_rate: Option<Duration>,
+}
+
+impl StepStats {
+    /// Records a step that took the duration provided. Steps that take longer
+    /// than the tick rate are counted as overruns.
+    pub fn record(&self, duration: Duration, succeeded: bool, tick_rate: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.steps += 1;
+        if !succeeded {
+            state.failures += 1;
+        }
+        if duration > tick_rate {
+            state.overruns += 1;
+        }
+        state.total = state.total.saturating_add(duration);
+        state.last = duration;
+        state.max = state.max.max(duration);
+        if state.recent.len() < RECENT_STEPS {
+            state.recent.push(duration);
+        } else {
+            let next = state.next;
+            state.recent[next] = duration;
+        }
+        state.next = (state.next + 1) % RECENT_STEPS;
+    }
+
+    /// Returns the most recently known tick rate of the auto splitter.
+    pub fn tick_rate(&self) -> Option<Duration> {
+        self.state.lock().unwrap().tick_rate
+    }
+
+    pub fn set_tick_rate(&self, tick_rate: Duration) {
+        self.state.lock().unwrap().tick_rate = Some(tick_rate);
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.state.lock().unwrap().steps
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.state.lock().unwrap().failures
+    }
+
+    pub fn overruns(&self) -> u64 {
+        self.state.lock().unwrap().overruns
+    }
+
+    pub fn last(&self) -> Duration {
+        self.state.lock().unwrap().last
+    }
+
+    pub fn mean(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        match u32::try_from(state.steps) {
+            Ok(0) => Duration::ZERO,
+            Ok(steps) => state.total / steps,
+            Err(_) => Duration::from_secs_f64(state.total.as_secs_f64() / state.steps as f64),
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.state.lock().unwrap().max
+    }
+
+    /// Returns the duration that the percentage of the most recent steps
+    /// provided didn't exceed.
+    pub fn percentile(&self, percentage: usize) -> Duration {
+        let mut recent = self.state.lock().unwrap().recent.clone();
+        if recent.is_empty() {
+            return Duration::ZERO;
+        }
+        let index = (recent.len() * percentage.min(100)).div_ceil(100).max(1) - 1;
+        *recent.select_nth_unstable(index).1
+    }
+
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        let tick_rate = state.tick_rate;
+        *state = State {
+            tick_rate,
+            ..State::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_RATE: Duration = Duration::from_millis(8);
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn calculates_percentiles() {
+        let stats = StepStats::default();
+        assert_eq!(stats.percentile(50), Duration::ZERO);
+
+        // Recorded out of order, so the percentiles don't depend on it.
+        for step in (1..=100).rev() {
+            stats.record(millis(step), true, TICK_RATE);
+        }
+
+        assert_eq!(stats.percentile(0), millis(1));
+        assert_eq!(stats.percentile(1), millis(1));
+        assert_eq!(stats.percentile(50), millis(50));
+        assert_eq!(stats.percentile(99), millis(99));
+        assert_eq!(stats.percentile(100), millis(100));
+        assert_eq!(stats.percentile(200), millis(100));
+    }
+
+    #[test]
+    fn rounds_percentiles_up_to_the_next_step() {
+        let stats = StepStats::default();
+        for step in 1..=3 {
+            stats.record(millis(step), true, TICK_RATE);
+        }
+
+        assert_eq!(stats.percentile(33), millis(1));
+        assert_eq!(stats.percentile(34), millis(2));
+        assert_eq!(stats.percentile(67), millis(3));
+    }
+
+    #[test]
+    fn calculates_percentiles_of_the_most_recent_steps() {
+        let stats = StepStats::default();
+        for _ in 0..RECENT_STEPS {
+            stats.record(millis(10), true, TICK_RATE);
+        }
+        for _ in 0..RECENT_STEPS {
+            stats.record(millis(1), true, TICK_RATE);
+        }
+
+        assert_eq!(stats.percentile(100), millis(1));
+        // The maximum covers all the steps.
+        assert_eq!(stats.max(), millis(10));
+        assert_eq!(stats.overruns(), RECENT_STEPS as u64);
+    }
+}
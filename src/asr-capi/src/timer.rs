@@ -0,0 +1,270 @@
+use std::{ffi::c_void, mem, ptr, slice};
+
+#[cfg(target_pointer_width = "64")]
+use {
+    crate::log::{level_to_u32, LogSource, Logger},
+    livesplit_auto_splitting::{time, LogLevel, Timer, TimerState},
+    std::{fmt, sync::Arc},
+};
+
+/// The callbacks that the runtime uses to query and control the timer of the
+/// host. Every callback receives the user data pointer that was passed
+/// alongside the vtable when creating the runtime, so each runtime can target
+/// its own timer instance.
+///
+/// The host needs to store the size of the struct it was compiled against in
+/// `size`. This allows appending new callbacks in the future without breaking
+/// hosts built against an older version of this struct. The size needs to
+/// cover a whole number of callbacks. All callbacks up to and including `log`
+/// are required and must not be null.
+///
+/// If `log_leveled` is provided, it is used instead of `log` and additionally
+/// receives the source of the message (0 for the auto splitter, 1 for the
+/// runtime) and its level (0 for trace, 1 for debug, 2 for info, 3 for warning
+/// and 4 for error messages).
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimerVTable {
+    pub size: usize,
+    pub state: unsafe extern "C" fn(*mut c_void) -> i32,
+    pub index: unsafe extern "C" fn(*mut c_void) -> i32,
+    pub segment_splitted: unsafe extern "C" fn(*mut c_void, i32) -> i32,
+    pub start: unsafe extern "C" fn(*mut c_void),
+    pub split: unsafe extern "C" fn(*mut c_void),
+    pub skip_split: unsafe extern "C" fn(*mut c_void),
+    pub undo_split: unsafe extern "C" fn(*mut c_void),
+    pub reset: unsafe extern "C" fn(*mut c_void),
+    pub set_game_time: unsafe extern "C" fn(*mut c_void, i64),
+    pub pause_game_time: unsafe extern "C" fn(*mut c_void),
+    pub resume_game_time: unsafe extern "C" fn(*mut c_void),
+    pub set_custom_variable: unsafe extern "C" fn(*mut c_void, *const u8, usize, *const u8, usize),
+    pub log: unsafe extern "C" fn(*mut c_void, *const u8, usize),
+    pub log_leveled: Option<unsafe extern "C" fn(*mut c_void, u32, u32, *const u8, usize)>,
+}
+
+/// Every field of the [`TimerVTable`] is the size of a pointer, which allows
+/// reading it field by field.
+const FIELD_SIZE: usize = mem::size_of::<usize>();
+
+/// The amount of fields of the [`TimerVTable`], including its size.
+const FIELDS: usize = mem::size_of::<TimerVTable>() / FIELD_SIZE;
+
+const _: () = assert!(mem::size_of::<TimerVTable>() == FIELDS * FIELD_SIZE);
+
+/// The size of the first version of the [`TimerVTable`]. Any callbacks after
+/// these need to be optional, as older hosts don't provide them.
+const TIMER_VTABLE_V1_SIZE: usize = mem::offset_of!(TimerVTable, log) + FIELD_SIZE;
+
+/// The names of the callbacks of the first version of the [`TimerVTable`], in
+/// the order they are stored in.
+const REQUIRED_CALLBACKS: [&str; TIMER_VTABLE_V1_SIZE / FIELD_SIZE - 1] = [
+    "state",
+    "index",
+    "segment_splitted",
+    "start",
+    "split",
+    "skip_split",
+    "undo_split",
+    "reset",
+    "set_game_time",
+    "pause_game_time",
+    "resume_game_time",
+    "set_custom_variable",
+    "log",
+];
+
+impl TimerVTable {
+    /// Reads the vtable provided by the host. Callbacks that are not covered by
+    /// the size the host specified are treated as missing, while callbacks
+    /// beyond the ones known to this version are ignored. Returns an error if
+    /// the size doesn't cover a whole number of callbacks or if any of the
+    /// required callbacks are missing.
+    ///
+    /// # Safety
+    /// `vtable` needs to either be null or point to a vtable that is valid for
+    /// at least as many bytes as specified by its `size` field.
+    pub unsafe fn read(vtable: *const TimerVTable) -> anyhow::Result<Self> {
+        if vtable.is_null() {
+            anyhow::bail!("The timer vtable is missing.");
+        }
+        let size = ptr::addr_of!((*vtable).size).read_unaligned();
+        if size < TIMER_VTABLE_V1_SIZE || !size.is_multiple_of(FIELD_SIZE) {
+            anyhow::bail!("The size of the timer vtable is invalid: {size} bytes.");
+        }
+
+        // Callbacks are read as raw pointers first, so null pointers can be
+        // rejected before they turn into function pointers.
+        let fields = vtable.cast::<*const c_void>();
+        let mut read = [ptr::null::<c_void>(); FIELDS];
+        for (index, field) in read.iter_mut().enumerate().take(size / FIELD_SIZE).skip(1) {
+            *field = fields.add(index).read_unaligned();
+        }
+        if let Some((name, _)) = REQUIRED_CALLBACKS
+            .iter()
+            .zip(&read[1..])
+            .find(|(_, callback)| callback.is_null())
+        {
+            anyhow::bail!("The timer vtable is missing the required `{name}` callback.");
+        }
+
+        let mut vtable = mem::transmute::<[*const c_void; FIELDS], TimerVTable>(read);
+        vtable.size = size;
+        Ok(vtable)
+    }
+}
+
+/// Copies a string passed to a timer callback, replacing invalid UTF-8. Empty
+/// strings may be passed as null pointers.
+///
+/// # Safety
+/// `ptr` needs to be valid for reads of `len` bytes, unless `len` is 0.
+pub unsafe fn lossy_str(ptr: *const u8, len: usize) -> Box<str> {
+    if len == 0 {
+        return "".into();
+    }
+    String::from_utf8_lossy(slice::from_raw_parts(ptr, len)).into()
+}
+
+/// Adapts the callbacks of a [`TimerVTable`] to the timer of the runtime.
+#[cfg(target_pointer_width = "64")]
+pub struct CTimer {
+    vtable: TimerVTable,
+    user_data: *mut c_void,
+    logger: Arc<Logger>,
+}
+
+// SAFETY: The host is responsible for making its callbacks and the user data
+// usable from the threads that it drives the runtime from.
+#[cfg(target_pointer_width = "64")]
+unsafe impl Send for CTimer {}
+
+#[cfg(target_pointer_width = "64")]
+impl CTimer {
+    pub fn new(vtable: TimerVTable, user_data: *mut c_void, logger: Arc<Logger>) -> Self {
+        Self {
+            vtable,
+            user_data,
+            logger,
+        }
+    }
+
+    /// Creates a timer that forwards everything to the callbacks provided,
+    /// including the log messages at or above the level provided. This allows
+    /// running an auto splitter with a `MockTimer` or a `Replay` without going
+    /// through the C API.
+    ///
+    /// # Safety
+    /// The callbacks need to be callable with the user data for as long as the
+    /// timer is in use.
+    pub unsafe fn from_vtable(
+        vtable: &TimerVTable,
+        user_data: *mut c_void,
+        min_log_level: LogLevel,
+    ) -> Self {
+        let logger = Logger::new(vtable, user_data);
+        logger.set_min_level(level_to_u32(min_log_level));
+        Self::new(*vtable, user_data, Arc::new(logger))
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl Timer for CTimer {
+    fn state(&self) -> TimerState {
+        match unsafe { (self.vtable.state)(self.user_data) } {
+            1 => TimerState::Running,
+            2 => TimerState::Paused,
+            3 => TimerState::Ended,
+            _ => TimerState::NotRunning,
+        }
+    }
+
+    fn current_split_index(&self) -> Option<usize> {
+        let i = unsafe { (self.vtable.index)(self.user_data) };
+        if i >= 0 {
+            Some(i as usize)
+        } else {
+            None
+        }
+    }
+
+    fn segment_splitted(&self, idx: usize) -> Option<bool> {
+        match unsafe { (self.vtable.segment_splitted)(self.user_data, i32::try_from(idx).ok()?) } {
+            1 => Some(true),
+            0 => Some(false),
+            _ => None,
+        }
+    }
+
+    fn start(&mut self) {
+        unsafe { (self.vtable.start)(self.user_data) }
+    }
+
+    fn split(&mut self) {
+        unsafe { (self.vtable.split)(self.user_data) }
+    }
+
+    fn skip_split(&mut self) {
+        unsafe { (self.vtable.skip_split)(self.user_data) }
+    }
+
+    fn undo_split(&mut self) {
+        unsafe { (self.vtable.undo_split)(self.user_data) }
+    }
+
+    fn reset(&mut self) {
+        unsafe { (self.vtable.reset)(self.user_data) }
+    }
+
+    fn set_game_time(&mut self, time: time::Duration) {
+        const TICKS_PER_SEC: i64 = 10_000_000;
+        const NANOS_PER_SEC: i64 = 1_000_000_000;
+        const NANOS_PER_TICK: i64 = NANOS_PER_SEC / TICKS_PER_SEC;
+
+        let (secs, nanos) = (time.whole_seconds(), time.subsec_nanoseconds());
+        let ticks = secs * TICKS_PER_SEC + nanos as i64 / NANOS_PER_TICK;
+        unsafe { (self.vtable.set_game_time)(self.user_data, ticks) }
+    }
+
+    fn pause_game_time(&mut self) {
+        unsafe { (self.vtable.pause_game_time)(self.user_data) }
+    }
+
+    fn resume_game_time(&mut self) {
+        unsafe { (self.vtable.resume_game_time)(self.user_data) }
+    }
+
+    fn set_variable(&mut self, name: &str, value: &str) {
+        unsafe {
+            (self.vtable.set_custom_variable)(
+                self.user_data,
+                name.as_ptr(),
+                name.len(),
+                value.as_ptr(),
+                value.len(),
+            )
+        }
+    }
+
+    fn log_auto_splitter(&mut self, message: fmt::Arguments<'_>) {
+        self.logger
+            .log(LogSource::AutoSplitter, LogLevel::Info, message);
+    }
+
+    fn log_runtime(&mut self, message: fmt::Arguments<'_>, log_level: LogLevel) {
+        self.logger.log(LogSource::Runtime, log_level, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_strings_passed_to_callbacks() {
+        unsafe {
+            assert_eq!(&*lossy_str("äb".as_ptr(), 3), "äb");
+            assert_eq!(&*lossy_str(b"a\xffb".as_ptr(), 3), "a\u{fffd}b");
+            assert_eq!(&*lossy_str(ptr::null(), 0), "");
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Watches the steps of an auto splitter and interrupts any step that runs for
+/// longer than the configured budget.
+pub struct Watchdog {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    budget: Option<Duration>,
+    /// The id and start time of the step that is currently running.
+    step: Option<(u64, Instant)>,
+    /// When the step got paused, if it is.
+    paused_at: Option<Instant>,
+    next_step_id: u64,
+    /// The id of the last step that got interrupted, so it doesn't get
+    /// interrupted over and over again.
+    interrupted_step_id: Option<u64>,
+    shutdown: bool,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    pub fn set_budget(&self, budget: Option<Duration>) {
+        self.state.lock().unwrap().budget = budget;
+        self.condvar.notify_all();
+    }
+
+    pub fn begin_step(&self) {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_step_id;
+        state.next_step_id += 1;
+        state.step = Some((id, Instant::now()));
+        state.paused_at = None;
+        drop(state);
+        self.condvar.notify_all();
+    }
+
+    pub fn end_step(&self) {
+        self.state.lock().unwrap().step = None;
+    }
+
+    /// Stops counting the time of the current step towards the budget, until
+    /// it gets resumed.
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.step.is_some() && state.paused_at.is_none() {
+            state.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let (Some(paused_at), Some((_, started))) = (state.paused_at.take(), &mut state.step) {
+            *started += paused_at.elapsed();
+        }
+        drop(state);
+        self.condvar.notify_all();
+    }
+
+    pub fn shutdown(&self) {
+        self.state.lock().unwrap().shutdown = true;
+        self.condvar.notify_all();
+    }
+
+    /// Watches the steps until the watchdog is shut down. The function
+    /// provided is called whenever a step exceeds the budget.
+    pub fn run(&self, interrupt: impl Fn()) {
+        let mut state = self.state.lock().unwrap();
+        while !state.shutdown {
+            state = match (state.budget, state.step) {
+                (Some(budget), Some((id, started)))
+                    if state.paused_at.is_none() && state.interrupted_step_id != Some(id) =>
+                {
+                    let deadline = started + budget;
+                    let now = Instant::now();
+                    if now >= deadline {
+                        state.interrupted_step_id = Some(id);
+                        interrupt();
+                        continue;
+                    }
+                    self.condvar.wait_timeout(state, deadline - now).unwrap().0
+                }
+                _ => self.condvar.wait(state).unwrap(),
+            };
+        }
+    }
+}
@@ -1,76 +1,155 @@
-use crate::settings_map::SettingsMap;
-
-#[cfg(target_pointer_width = "64")]
-use crate::{output_str, output_vec, setting_value::SettingValue};
-#[cfg(target_pointer_width = "64")]
-use livesplit_auto_splitting::settings::{FileFilter, Widget, WidgetKind};
-#[cfg(target_pointer_width = "64")]
+use crate::{
+    error::{set_index_out_of_range, set_type_mismatch},
+    output_into, output_str, output_utf16_into, output_vec,
+    setting_value::SettingValue,
+    settings::{ChoiceOption, FileFilter, Widget, WidgetKind},
+    settings_map::SettingsMap,
+};
 use std::sync::Arc;
 
-#[cfg(target_pointer_width = "64")]
 pub struct Widgets {
     pub inner: Arc<Vec<Widget>>,
 }
 
-#[cfg(not(target_pointer_width = "64"))]
-pub type Widgets = ();
-
 #[no_mangle]
 pub extern "C" fn Widgets_drop(_: Box<Widgets>) {}
 
 #[no_mangle]
 pub extern "C" fn Widgets_len(_this: &Widgets) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        _this.inner.len()
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    0
+    _this.inner.len()
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_key(_this: &Widgets, _index: usize) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        output_str(&_this.inner[_index].key)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(key(_this, _index))
+}
+
+/// Like `Widgets_get_key`, but writes the key into a buffer owned by the
+/// caller. See `get_buf_len` for how the buffer is filled. If the key doesn't
+/// fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_key_into(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(key(_this, _index).as_bytes(), _buf, _cap)
+}
+
+/// Like `Widgets_get_key`, but writes the key as UTF-16 into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the key
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_key_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(key(_this, _index), _buf, _cap)
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_description(_this: &Widgets, _index: usize) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        output_str(&_this.inner[_index].description)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(description(_this, _index))
+}
+
+/// Like `Widgets_get_description`, but writes the description into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// description doesn't fit, only a nul-terminator is written at the start of
+/// the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_description_into(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(description(_this, _index).as_bytes(), _buf, _cap)
+}
+
+/// Like `Widgets_get_description`, but writes the description as UTF-16 into a
+/// buffer owned by the caller. See `get_buf_len` for how the buffer is filled.
+/// If the description doesn't fit, only a nul-terminator is written at the
+/// start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_description_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(description(_this, _index), _buf, _cap)
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_tooltip(_this: &Widgets, _index: usize) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        output_str(_this.inner[_index].tooltip.as_deref().unwrap_or_default())
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(tooltip(_this, _index))
+}
+
+/// Like `Widgets_get_tooltip`, but writes the tooltip into a buffer owned by
+/// the caller. See `get_buf_len` for how the buffer is filled. If the tooltip
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_tooltip_into(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    output_into(tooltip(_this, _index).as_bytes(), _buf, _cap)
+}
+
+/// Like `Widgets_get_tooltip`, but writes the tooltip as UTF-16 into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// tooltip doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_tooltip_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    output_utf16_into(tooltip(_this, _index), _buf, _cap)
 }
 
+/// Returns the type of the widget at the index provided: 1 for a bool, 2 for a
+/// title, 3 for a choice and 4 for a file select widget. Returns 0 and sets the
+/// last error if the index is out of bounds.
+///
+/// The getters that are specific to a type of widget return a default value
+/// and set the last error to a type mismatch when called for a widget of a
+/// different type. All getters set the last error if the index is out of
+/// bounds.
 #[no_mangle]
 pub extern "C" fn Widgets_get_type(_this: &Widgets, _index: usize) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        match _this.inner[_index].kind {
-            WidgetKind::Bool { .. } => 1,
-            WidgetKind::Title { .. } => 2,
-            WidgetKind::Choice { .. } => 3,
-            WidgetKind::FileSelect { .. } => 4,
-        }
+    match widget(_this, _index).map(|w| &w.kind) {
+        Some(WidgetKind::Bool { .. }) => 1,
+        Some(WidgetKind::Title { .. }) => 2,
+        Some(WidgetKind::Choice { .. }) => 3,
+        Some(WidgetKind::FileSelect { .. }) => 4,
+        None => 0,
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
 }
 
 #[no_mangle]
@@ -79,19 +158,17 @@ pub extern "C" fn Widgets_get_bool(
     _index: usize,
     _settings_map: &SettingsMap,
 ) -> bool {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Bool { default_value } = setting.kind else {
-            return false;
-        };
-        match _settings_map.get(&setting.key) {
-            Some(SettingValue::Bool(stored)) => *stored,
-            _ => default_value,
-        }
+    let Some(setting) = widget(_this, _index) else {
+        return false;
+    };
+    let WidgetKind::Bool { default_value } = setting.kind else {
+        set_type_mismatch("a bool widget");
+        return false;
+    };
+    match _settings_map.get(&setting.key) {
+        Some(SettingValue::Bool(stored)) => *stored,
+        _ => default_value,
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
 }
 
 #[no_mangle]
@@ -100,46 +177,35 @@ pub extern "C" fn Widgets_get_choice_current_index(
     _index: usize,
     _settings_map: &SettingsMap,
 ) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Choice {
-            default_option_key,
-            options,
-        } = &setting.kind
-        else {
-            return 0;
-        };
-        let key = match _settings_map.get(&setting.key) {
-            Some(SettingValue::String(stored)) => stored,
-            _ => default_option_key,
-        };
-        options
-            .iter()
-            .position(|option| option.key == *key)
-            .or_else(|| {
-                options
-                    .iter()
-                    .position(|option| option.key == *default_option_key)
-            })
-            .unwrap_or_default()
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    let Some(setting) = widget(_this, _index) else {
+        return 0;
+    };
+    let WidgetKind::Choice {
+        default_option_key,
+        options,
+    } = &setting.kind
+    else {
+        set_type_mismatch("a choice widget");
+        return 0;
+    };
+    let key = match _settings_map.get(&setting.key) {
+        Some(SettingValue::String(stored)) => stored,
+        _ => default_option_key,
+    };
+    options
+        .iter()
+        .position(|option| option.key == *key)
+        .or_else(|| {
+            options
+                .iter()
+                .position(|option| option.key == *default_option_key)
+        })
+        .unwrap_or_default()
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_choice_options_len(_this: &Widgets, _index: usize) -> usize {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Choice { options, .. } = &setting.kind else {
-            return 0;
-        };
-        options.len()
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    choice_options(_this, _index).map_or(0, |options| options.len())
 }
 
 #[no_mangle]
@@ -148,16 +214,44 @@ pub extern "C" fn Widgets_get_choice_option_key(
     _index: usize,
     _option_index: usize,
 ) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Choice { options, .. } = &setting.kind else {
-            return output_str("");
-        };
-        output_str(&options[_option_index].key)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(choice_option(_this, _index, _option_index).map_or("", |o| &o.key))
+}
+
+/// Like `Widgets_get_choice_option_key`, but writes the key into a buffer owned
+/// by the caller. See `get_buf_len` for how the buffer is filled. If the key
+/// doesn't fit, only a nul-terminator is written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_choice_option_key_into(
+    _this: &Widgets,
+    _index: usize,
+    _option_index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    let key = choice_option(_this, _index, _option_index).map_or("", |o| &o.key);
+    output_into(key.as_bytes(), _buf, _cap)
+}
+
+/// Like `Widgets_get_choice_option_key`, but writes the key as UTF-16 into a
+/// buffer owned by the caller. See `get_buf_len` for how the buffer is filled.
+/// If the key doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_choice_option_key_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _option_index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    let key = choice_option(_this, _index, _option_index).map_or("", |o| &o.key);
+    output_utf16_into(key, _buf, _cap)
 }
 
 #[no_mangle]
@@ -166,49 +260,159 @@ pub extern "C" fn Widgets_get_choice_option_description(
     _index: usize,
     _option_index: usize,
 ) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Choice { options, .. } = &setting.kind else {
-            return output_str("");
-        };
-        output_str(&options[_option_index].description)
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    output_str(choice_option(_this, _index, _option_index).map_or("", |o| &o.description))
+}
+
+/// Like `Widgets_get_choice_option_description`, but writes the description
+/// into a buffer owned by the caller. See `get_buf_len` for how the buffer is
+/// filled. If the description doesn't fit, only a nul-terminator is written at
+/// the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_choice_option_description_into(
+    _this: &Widgets,
+    _index: usize,
+    _option_index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    let description = choice_option(_this, _index, _option_index).map_or("", |o| &o.description);
+    output_into(description.as_bytes(), _buf, _cap)
+}
+
+/// Like `Widgets_get_choice_option_description`, but writes the description as
+/// UTF-16 into a buffer owned by the caller. See `get_buf_len` for how the
+/// buffer is filled. If the description doesn't fit, only a nul-terminator is
+/// written at the start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_choice_option_description_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _option_index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    let description = choice_option(_this, _index, _option_index).map_or("", |o| &o.description);
+    output_utf16_into(description, _buf, _cap)
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_heading_level(_this: &Widgets, _index: usize) -> u32 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::Title { heading_level } = setting.kind else {
-            return 0;
-        };
-        heading_level
-    }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    let Some(setting) = widget(_this, _index) else {
+        return 0;
+    };
+    let WidgetKind::Title { heading_level } = setting.kind else {
+        set_type_mismatch("a title widget");
+        return 0;
+    };
+    heading_level
 }
 
 #[no_mangle]
 pub extern "C" fn Widgets_get_file_select_filter(_this: &Widgets, _index: usize) -> *const u8 {
-    #[cfg(target_pointer_width = "64")]
-    {
-        let setting = &_this.inner[_index];
-        let WidgetKind::FileSelect { filters } = &setting.kind else {
-            return output_str("");
-        };
-        output_vec(|o| {
+    output_vec(|o| {
+        if let Some(filters) = file_select_filters(_this, _index) {
             build_filter(filters, o);
-        })
+        }
+    })
+}
+
+/// Like `Widgets_get_file_select_filter`, but writes the filter into a buffer
+/// owned by the caller. See `get_buf_len` for how the buffer is filled. If the
+/// filter doesn't fit, only a nul-terminator is written at the start of the
+/// buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_file_select_filter_into(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u8,
+    _cap: usize,
+) -> usize {
+    let mut output = Vec::new();
+    if let Some(filters) = file_select_filters(_this, _index) {
+        build_filter(filters, &mut output);
+    }
+    output_into(&output, _buf, _cap)
+}
+
+/// Like `Widgets_get_file_select_filter`, but writes the filter as UTF-16 into
+/// a buffer owned by the caller. See `get_buf_len` for how the buffer is
+/// filled. If the filter doesn't fit, only a nul-terminator is written at the
+/// start of the buffer.
+///
+/// # Safety
+/// `_buf` must be valid for writes of `_cap` code units.
+#[no_mangle]
+pub unsafe extern "C" fn Widgets_get_file_select_filter_utf16(
+    _this: &Widgets,
+    _index: usize,
+    _buf: *mut u16,
+    _cap: usize,
+) -> usize {
+    let mut output = Vec::new();
+    if let Some(filters) = file_select_filters(_this, _index) {
+        build_filter(filters, &mut output);
+    }
+    output_utf16_into(std::str::from_utf8(&output).unwrap_or_default(), _buf, _cap)
+}
+
+/// Returns the widget at the index provided, setting the last error if the
+/// index is out of bounds.
+fn widget(widgets: &Widgets, index: usize) -> Option<&Widget> {
+    let widget = widgets.inner.get(index);
+    if widget.is_none() {
+        set_index_out_of_range(index, widgets.inner.len());
     }
-    #[cfg(not(target_pointer_width = "64"))]
-    panic!("Index out of bounds")
+    widget
+}
+
+fn key(widgets: &Widgets, index: usize) -> &str {
+    widget(widgets, index).map_or("", |w| &w.key)
+}
+
+fn description(widgets: &Widgets, index: usize) -> &str {
+    widget(widgets, index).map_or("", |w| &w.description)
+}
+
+fn tooltip(widgets: &Widgets, index: usize) -> &str {
+    widget(widgets, index)
+        .and_then(|w| w.tooltip.as_deref())
+        .unwrap_or_default()
+}
+
+fn choice_options(widgets: &Widgets, index: usize) -> Option<&[ChoiceOption]> {
+    let WidgetKind::Choice { options, .. } = &widget(widgets, index)?.kind else {
+        set_type_mismatch("a choice widget");
+        return None;
+    };
+    Some(options)
+}
+
+fn choice_option(widgets: &Widgets, index: usize, option_index: usize) -> Option<&ChoiceOption> {
+    let options = choice_options(widgets, index)?;
+    let option = options.get(option_index);
+    if option.is_none() {
+        set_index_out_of_range(option_index, options.len());
+    }
+    option
+}
+
+fn file_select_filters(widgets: &Widgets, index: usize) -> Option<&[FileFilter]> {
+    let WidgetKind::FileSelect { filters } = &widget(widgets, index)?.kind else {
+        set_type_mismatch("a file select widget");
+        return None;
+    };
+    Some(filters)
 }
 
-#[cfg(target_pointer_width = "64")]
 fn build_filter(filters: &[FileFilter], output: &mut Vec<u8>) {
     for filter in filters.iter() {
         match filter {
@@ -337,7 +541,6 @@ fn build_filter(filters: &[FileFilter], output: &mut Vec<u8>) {
     output.extend_from_slice(b"All files (*.*)|*.*");
 }
 
-#[cfg(target_pointer_width = "64")]
 fn append_mime_desc(top: &str, sub: &str, output: &mut Vec<u8>) {
     let mut char_buf = [0; 4];
 
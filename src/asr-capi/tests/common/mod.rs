@@ -0,0 +1,63 @@
+//! Drives the runtime through its C API, like a host would. The fixtures are
+//! compiled from the `.wat` files next to them, for example with `wat2wasm`.
+
+#![allow(dead_code)]
+
+use asr_capi::{ErrorKind, Runtime_clear_last_error, Runtime_last_error_kind};
+use std::{
+    ffi::{CStr, CString},
+    path::PathBuf,
+};
+
+mod runtime;
+mod settings;
+mod timer;
+
+// Not every test uses all of them.
+#[allow(unused_imports)]
+pub use {runtime::*, settings::*, timer::*};
+
+// The levels of the log messages passed to the timer.
+pub const INFO: u32 = 2;
+pub const ERROR: u32 = 4;
+
+/// The source of log messages logged by the runtime itself.
+pub const RUNTIME_SOURCE: u32 = 1;
+
+// The kinds of changes returned by `SettingsDiff_get_kind`.
+pub const ADDED: usize = 1;
+pub const REMOVED: usize = 2;
+pub const CHANGED: usize = 3;
+
+/// Calls the function provided and returns its result along with the kind of
+/// the last error it reported, if any.
+pub fn with_last_error<R>(f: impl FnOnce() -> R) -> (R, Option<ErrorKind>) {
+    Runtime_clear_last_error();
+    let result = f();
+    (result, ErrorKind::from_u32(Runtime_last_error_kind()))
+}
+
+/// Returns the kind of the last error, which a call that failed reported.
+pub fn last_error() -> ErrorKind {
+    ErrorKind::from_u32(Runtime_last_error_kind()).expect("No error was reported.")
+}
+
+/// Copies the string returned by a getter.
+///
+/// # Safety
+/// The string must be valid.
+pub unsafe fn string(s: *const u8) -> String {
+    CStr::from_ptr(s.cast()).to_str().unwrap().to_owned()
+}
+
+fn c_string(s: &str) -> CString {
+    CString::new(s).unwrap()
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+        .with_extension("wasm")
+}
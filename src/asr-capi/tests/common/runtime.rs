@@ -0,0 +1,306 @@
+//! Runtimes and their configurations, owned by the tests.
+
+use super::*;
+use asr_capi::*;
+use std::{ffi::CString, path::Path};
+
+/// A runtime configuration owned by the test.
+pub struct TestConfig(Box<RuntimeConfig>);
+
+impl TestConfig {
+    pub fn new() -> Self {
+        Self(RuntimeConfig_new())
+    }
+
+    pub fn set_debug_info(&mut self, value: bool) {
+        RuntimeConfig_set_debug_info(&mut self.0, value)
+    }
+
+    pub fn set_optimize(&mut self, value: bool) {
+        RuntimeConfig_set_optimize(&mut self.0, value)
+    }
+
+    pub fn set_backtrace_details(&mut self, value: bool) {
+        RuntimeConfig_set_backtrace_details(&mut self.0, value)
+    }
+
+    pub fn set_sandboxed(&mut self, value: bool) {
+        RuntimeConfig_set_sandboxed(&mut self.0, value)
+    }
+}
+
+/// A runtime that drives the timer it borrows.
+pub struct TestRuntime<'timer> {
+    runtime: Box<Runtime>,
+    timer: &'timer dyn TestTimer,
+}
+
+impl<'timer> TestRuntime<'timer> {
+    /// Loads the fixture with the name provided. Returns the kind of the last
+    /// error if that fails.
+    pub fn load(fixture: &str, timer: &'timer dyn TestTimer) -> Result<Self, ErrorKind> {
+        let path = CString::new(fixture_path(fixture).to_str().unwrap()).unwrap();
+        let runtime = unsafe {
+            Runtime_new(
+                path.as_ptr().cast(),
+                None,
+                timer.vtable(),
+                timer.user_data(),
+            )
+        };
+        Self::new(runtime, timer)
+    }
+
+    /// Loads the module provided. Returns the kind of the last error if that
+    /// fails.
+    pub fn from_bytes(module: &[u8], timer: &'timer dyn TestTimer) -> Result<Self, ErrorKind> {
+        let runtime = unsafe {
+            Runtime_new_from_bytes(
+                module.as_ptr(),
+                module.len(),
+                None,
+                timer.vtable(),
+                timer.user_data(),
+            )
+        };
+        Self::new(runtime, timer)
+    }
+
+    /// Loads the fixture with the name provided, using the configuration
+    /// provided. Returns the kind of the last error if that fails.
+    pub fn load_with_config(
+        fixture: &str,
+        config: &TestConfig,
+        timer: &'timer dyn TestTimer,
+    ) -> Result<Self, ErrorKind> {
+        let path = CString::new(fixture_path(fixture).to_str().unwrap()).unwrap();
+        let runtime = unsafe {
+            Runtime_new_with_config(
+                path.as_ptr().cast(),
+                None,
+                &config.0,
+                timer.vtable(),
+                timer.user_data(),
+            )
+        };
+        Self::new(runtime, timer)
+    }
+
+    /// Loads the module provided, using the configuration provided. Returns
+    /// the kind of the last error if that fails.
+    pub fn from_bytes_with_config(
+        module: &[u8],
+        config: &TestConfig,
+        timer: &'timer dyn TestTimer,
+    ) -> Result<Self, ErrorKind> {
+        let runtime = unsafe {
+            Runtime_new_from_bytes_with_config(
+                module.as_ptr(),
+                module.len(),
+                None,
+                &config.0,
+                timer.vtable(),
+                timer.user_data(),
+            )
+        };
+        Self::new(runtime, timer)
+    }
+
+    fn new(runtime: Option<Box<Runtime>>, timer: &'timer dyn TestTimer) -> Result<Self, ErrorKind> {
+        let runtime = runtime.ok_or_else(last_error)?;
+        Ok(Self { runtime, timer })
+    }
+
+    pub fn as_ptr(&self) -> *const Runtime {
+        &*self.runtime
+    }
+
+    /// Runs a single step of the auto splitter after advancing the timer to
+    /// it. Returns the kind of the last error if the step failed.
+    pub fn step(&self) -> Result<(), ErrorKind> {
+        self.timer.begin_step();
+        result(Runtime_step(&self.runtime))
+    }
+
+    /// Runs the amount of steps provided, stopping at the first failure.
+    pub fn steps(&self, count: usize) -> Result<(), ErrorKind> {
+        (0..count).try_for_each(|_| self.step())
+    }
+
+    /// Lets the runtime step the auto splitter on its own thread. The timer is
+    /// not advanced for these steps.
+    pub fn start_thread(&self) -> bool {
+        Runtime_start_thread(&self.runtime)
+    }
+
+    pub fn stop_thread(&self) {
+        Runtime_stop_thread(&self.runtime)
+    }
+
+    pub fn set_step_budget(&self, ticks: u64) {
+        Runtime_set_step_budget(&self.runtime, ticks)
+    }
+
+    pub fn interrupt(&self) {
+        Runtime_interrupt(&self.runtime)
+    }
+
+    /// Returns a handle for interrupting the runtime from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle<'_> {
+        InterruptHandle(&self.runtime)
+    }
+
+    pub fn was_interrupted(&self) -> bool {
+        Runtime_was_interrupted(&self.runtime)
+    }
+
+    pub fn set_log_level(&self, level: u32) {
+        Runtime_set_log_level(&self.runtime, level)
+    }
+
+    /// Returns the log records the runtime keeps, starting with the oldest.
+    pub fn logs(&self) -> Vec<Log> {
+        let len = Runtime_logs_len(&self.runtime);
+        (0..len).map(|index| self.log(index).unwrap()).collect()
+    }
+
+    pub fn log(&self, index: usize) -> Option<Log> {
+        let record = Runtime_logs_get(&self.runtime, index)?;
+        Some(Log {
+            timestamp: LogRecord_get_timestamp(&record),
+            level: LogRecord_get_level(&record),
+            source: LogRecord_get_source(&record),
+            message: unsafe { string(LogRecord_get_message(&record)) },
+        })
+    }
+
+    pub fn clear_logs(&self) {
+        Runtime_logs_clear(&self.runtime)
+    }
+
+    /// Reloads the auto splitter from its file. Returns the kind of the last
+    /// error if that fails.
+    pub fn reload(&self) -> Result<(), ErrorKind> {
+        result(Runtime_reload(&self.runtime))
+    }
+
+    /// Replaces the auto splitter with the module provided. Returns the kind
+    /// of the last error if that fails.
+    pub fn reload_from_bytes(&self, module: &[u8]) -> Result<(), ErrorKind> {
+        result(unsafe { Runtime_reload_from_bytes(&self.runtime, module.as_ptr(), module.len()) })
+    }
+
+    pub fn settings_map(&self) -> TestMap {
+        TestMap(Runtime_get_settings_map(&self.runtime))
+    }
+
+    pub fn settings_widgets(&self) -> Box<Widgets> {
+        Runtime_get_settings_widgets(&self.runtime)
+    }
+
+    /// Returns the setting at the path provided. Returns the kind of the last
+    /// error if there is none.
+    pub fn get_path(&self, path: &str) -> Result<Value, ErrorKind> {
+        let value = unsafe {
+            Runtime_settings_map_get_value_by_path(&self.runtime, c_string(path).as_ptr().cast())
+        };
+        value
+            .map(|value| Value::from_setting_value(&value))
+            .ok_or_else(last_error)
+    }
+
+    /// Replaces the setting at the path provided. Returns the kind of the last
+    /// error if that fails.
+    pub fn set_path(&self, path: &str, value: &Value) -> Result<(), ErrorKind> {
+        result(unsafe {
+            Runtime_settings_map_set_value_by_path(
+                &self.runtime,
+                c_string(path).as_ptr().cast(),
+                value.to_setting_value(),
+            )
+        })
+    }
+
+    pub fn set_i64(&self, key: &str, value: i64) -> bool {
+        unsafe { Runtime_settings_map_set_i64(&self.runtime, c_string(key).as_ptr().cast(), value) }
+    }
+
+    pub fn set_f64(&self, key: &str, value: f64) -> bool {
+        unsafe { Runtime_settings_map_set_f64(&self.runtime, c_string(key).as_ptr().cast(), value) }
+    }
+
+    pub fn set_map(&self, key: &str, value: TestMap) -> bool {
+        unsafe {
+            Runtime_settings_map_set_map(&self.runtime, c_string(key).as_ptr().cast(), value.0)
+        }
+    }
+
+    /// Returns the changes from the previous settings map to the current one.
+    pub fn diff_settings(&self, previous: &TestMap) -> Vec<Change> {
+        changes(Runtime_diff_settings(&self.runtime, &previous.0))
+    }
+
+    pub fn remove_setting(&self, key: &str) -> bool {
+        unsafe { Runtime_settings_map_remove(&self.runtime, c_string(key).as_ptr().cast()) }
+    }
+
+    pub fn start_recording(&self, path: &Path) -> bool {
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe { Runtime_start_recording(&self.runtime, path.as_ptr().cast()) }
+    }
+
+    pub fn stop_recording(&self) -> bool {
+        Runtime_stop_recording(&self.runtime)
+    }
+
+    pub fn stats_steps(&self) -> u64 {
+        Runtime_stats_steps(&self.runtime)
+    }
+
+    pub fn stats_failures(&self) -> u64 {
+        Runtime_stats_failures(&self.runtime)
+    }
+
+    pub fn stats_mean(&self) -> u64 {
+        Runtime_stats_mean(&self.runtime)
+    }
+
+    pub fn stats_max(&self) -> u64 {
+        Runtime_stats_max(&self.runtime)
+    }
+
+    pub fn stats_reset(&self) {
+        Runtime_stats_reset(&self.runtime)
+    }
+}
+
+/// Turns the result of a call that reports its errors through the last error
+/// into a `Result`.
+fn result(succeeded: bool) -> Result<(), ErrorKind> {
+    if succeeded {
+        Ok(())
+    } else {
+        Err(last_error())
+    }
+}
+
+/// Interrupts the runtime it borrows, which may happen from any thread.
+pub struct InterruptHandle<'runtime>(&'runtime Runtime);
+
+// SAFETY: The runtime can be interrupted from any thread.
+unsafe impl Send for InterruptHandle<'_> {}
+
+impl InterruptHandle<'_> {
+    pub fn interrupt(&self) {
+        Runtime_interrupt(self.0)
+    }
+}
+
+/// A copy of a log record kept by the runtime.
+#[derive(Debug)]
+pub struct Log {
+    pub timestamp: i64,
+    pub level: u32,
+    pub source: u32,
+    pub message: String,
+}
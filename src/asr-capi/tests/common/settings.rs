@@ -0,0 +1,247 @@
+//! Settings maps, values and diffs, copied between the tests and the C side.
+
+use super::*;
+use asr_capi::*;
+use std::ops::{Deref, DerefMut};
+
+/// A setting value that lives on the Rust side, so it can be compared.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Map(Vec<(String, Value)>),
+    List(Vec<Value>),
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+    String(String),
+}
+
+impl Value {
+    pub fn map<const N: usize>(entries: [(&str, Value); N]) -> Self {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+
+    /// Returns the entries of a map value.
+    pub fn into_entries(self) -> Vec<(String, Value)> {
+        match self {
+            Value::Map(entries) => entries,
+            _ => panic!("The value is not a map."),
+        }
+    }
+
+    /// Creates the setting value on the C side.
+    pub fn to_setting_value(&self) -> Box<SettingValue> {
+        match self {
+            Value::Map(entries) => SettingValue_new_map(TestMap::from_entries(entries).0),
+            Value::List(values) => {
+                let mut list = SettingsList_new();
+                for value in values {
+                    SettingsList_push(&mut list, value.to_setting_value());
+                }
+                SettingValue_new_list(list)
+            }
+            Value::Bool(value) => SettingValue_new_bool(*value),
+            Value::I64(value) => SettingValue_new_i64(*value),
+            Value::F64(value) => SettingValue_new_f64(*value),
+            Value::String(value) => {
+                unsafe { SettingValue_new_string(c_string(value).as_ptr().cast()) }.unwrap()
+            }
+        }
+    }
+
+    /// Copies the setting value from the C side.
+    pub fn from_setting_value(value: &SettingValue) -> Self {
+        match SettingValue_get_type(value) {
+            1 => Value::Map(map_entries(SettingValue_get_map(value).unwrap())),
+            2 => {
+                let list = SettingValue_get_list(value).unwrap();
+                Value::List(
+                    (0..SettingsList_len(list))
+                        .map(|index| {
+                            Value::from_setting_value(SettingsList_get(list, index).unwrap())
+                        })
+                        .collect(),
+                )
+            }
+            3 => Value::Bool(SettingValue_get_bool(value)),
+            4 => Value::I64(SettingValue_get_i64(value)),
+            5 => Value::F64(SettingValue_get_f64(value)),
+            6 => Value::String(unsafe { string(SettingValue_get_string(value)) }),
+            kind => panic!("Unknown setting type {kind}."),
+        }
+    }
+}
+
+fn map_entries(map: &SettingsMap) -> Vec<(String, Value)> {
+    (0..SettingsMap_len(map))
+        .map(|index| {
+            (
+                unsafe { string(SettingsMap_get_key(map, index)) },
+                Value::from_setting_value(SettingsMap_get_value(map, index).unwrap()),
+            )
+        })
+        .collect()
+}
+
+/// A change reported by a `SettingsDiff`.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub kind: usize,
+    pub path: String,
+    pub previous: Option<Value>,
+    pub current: Option<Value>,
+}
+
+impl Change {
+    pub fn new(kind: usize, path: &str, previous: Option<Value>, current: Option<Value>) -> Self {
+        Self {
+            kind,
+            path: path.to_owned(),
+            previous,
+            current,
+        }
+    }
+}
+
+/// Reads the changes out of the diff.
+pub(super) fn changes(diff: Box<SettingsDiff>) -> Vec<Change> {
+    (0..SettingsDiff_len(&diff))
+        .map(|index| Change {
+            kind: SettingsDiff_get_kind(&diff, index),
+            path: unsafe { string(SettingsDiff_get_path(&diff, index)) },
+            previous: SettingsDiff_get_previous_value(&diff, index).map(Value::from_setting_value),
+            current: SettingsDiff_get_current_value(&diff, index).map(Value::from_setting_value),
+        })
+        .collect()
+}
+
+/// A settings map owned by the test.
+pub struct TestMap(pub(super) Box<SettingsMap>);
+
+impl TestMap {
+    pub fn new() -> Self {
+        Self(SettingsMap_new())
+    }
+
+    pub fn from_entries(entries: &[(String, Value)]) -> Self {
+        let mut map = Self::new();
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map
+    }
+
+    pub fn insert(&mut self, key: &str, value: &Value) -> bool {
+        unsafe {
+            SettingsMap_insert(
+                &mut self.0,
+                c_string(key).as_ptr().cast(),
+                value.to_setting_value(),
+            )
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        unsafe { SettingsMap_remove(&mut self.0, c_string(key).as_ptr().cast()) }
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.entries().into_iter().map(|(key, _)| key).collect()
+    }
+
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        map_entries(&self.0)
+    }
+
+    /// Decodes the map from JSON. Returns the kind of the last error if that
+    /// fails.
+    pub fn from_json(json: &str) -> Result<Self, ErrorKind> {
+        unsafe { SettingsMap_from_json(c_string(json).as_ptr().cast()) }
+            .map(Self)
+            .ok_or_else(last_error)
+    }
+
+    /// Encodes the map as JSON. Returns the kind of the last error if that
+    /// fails.
+    pub fn to_json(&self) -> Result<String, ErrorKind> {
+        let json = SettingsMap_to_json(&self.0);
+        if json.is_null() {
+            return Err(last_error());
+        }
+        Ok(unsafe { string(json) })
+    }
+
+    /// Decodes the map from the `CustomSettings` in the XML. Returns the kind
+    /// of the last error if that fails.
+    pub fn from_xml(xml: &str) -> Result<Self, ErrorKind> {
+        unsafe { SettingsMap_from_xml(c_string(xml).as_ptr().cast()) }
+            .map(Self)
+            .ok_or_else(last_error)
+    }
+
+    /// Encodes the map as XML. Returns the kind of the last error if that
+    /// fails.
+    pub fn to_xml(&self) -> Result<String, ErrorKind> {
+        let xml = SettingsMap_to_xml(&self.0);
+        if xml.is_null() {
+            return Err(last_error());
+        }
+        Ok(unsafe { string(xml) })
+    }
+
+    /// Returns the changes from this map to the current one.
+    pub fn diff(&self, current: &TestMap) -> Vec<Change> {
+        changes(SettingsMap_diff(&self.0, &current.0))
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        unsafe { SettingsMap_get_value_by_key(&self.0, c_string(key).as_ptr().cast()) }
+            .map(Value::from_setting_value)
+    }
+
+    /// Returns the value at the path provided. Returns the kind of the last
+    /// error if there is none.
+    pub fn get_path(&self, path: &str) -> Result<Value, ErrorKind> {
+        unsafe { SettingsMap_get_value_by_path(&self.0, c_string(path).as_ptr().cast()) }
+            .map(Value::from_setting_value)
+            .ok_or_else(last_error)
+    }
+
+    /// Replaces the value at the path provided. Returns the kind of the last
+    /// error if that fails.
+    pub fn set_path(&mut self, path: &str, value: &Value) -> Result<(), ErrorKind> {
+        if unsafe {
+            SettingsMap_set_value_by_path(
+                &mut self.0,
+                c_string(path).as_ptr().cast(),
+                value.to_setting_value(),
+            )
+        } {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+}
+
+impl Deref for TestMap {
+    type Target = SettingsMap;
+
+    fn deref(&self) -> &SettingsMap {
+        &self.0
+    }
+}
+
+impl DerefMut for TestMap {
+    fn deref_mut(&mut self) -> &mut SettingsMap {
+        &mut self.0
+    }
+}
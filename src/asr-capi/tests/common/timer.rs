@@ -0,0 +1,92 @@
+//! Timers that the runtime under test can control.
+
+use asr_capi::{MockTimer, Replay, TimerVTable};
+use std::{ffi::c_void, mem, ptr};
+
+/// A timer implemented in Rust that the runtime can control.
+pub trait TestTimer {
+    fn vtable(&self) -> *const TimerVTable;
+    fn user_data(&self) -> *mut c_void;
+    fn begin_step(&self);
+}
+
+impl TestTimer for MockTimer {
+    fn vtable(&self) -> *const TimerVTable {
+        MockTimer::vtable()
+    }
+
+    fn user_data(&self) -> *mut c_void {
+        self.user_data()
+    }
+
+    fn begin_step(&self) {
+        self.begin_step();
+    }
+}
+
+impl TestTimer for Replay {
+    fn vtable(&self) -> *const TimerVTable {
+        Replay::vtable()
+    }
+
+    fn user_data(&self) -> *mut c_void {
+        self.user_data()
+    }
+
+    fn begin_step(&self) {
+        self.begin_step();
+    }
+}
+
+const VTABLE_FIELDS: usize = mem::size_of::<TimerVTable>() / mem::size_of::<usize>();
+
+/// A mock timer that hands out a modified copy of its vtable, like hosts built
+/// against other versions of the vtable or broken hosts would.
+pub struct CustomVTable<'timer> {
+    timer: &'timer MockTimer,
+    fields: [*const c_void; VTABLE_FIELDS],
+}
+
+impl<'timer> CustomVTable<'timer> {
+    pub fn new(timer: &'timer MockTimer) -> Self {
+        Self {
+            timer,
+            fields: unsafe {
+                mem::transmute::<TimerVTable, [*const c_void; VTABLE_FIELDS]>(*MockTimer::vtable())
+            },
+        }
+    }
+
+    /// Changes the size stored in the vtable. The callbacks after it are kept,
+    /// as the runtime is not supposed to read them.
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.fields[0] = size as *const c_void;
+        self
+    }
+
+    /// Replaces the callback at the offset provided with null.
+    pub fn with_null(self, offset: usize) -> Self {
+        self.with_callback(offset, ptr::null())
+    }
+
+    /// Replaces the callback at the offset provided. The mock timer's user
+    /// data is passed to it.
+    pub fn with_callback(mut self, offset: usize, callback: *const c_void) -> Self {
+        self.fields[offset / mem::size_of::<usize>()] = callback;
+        self
+    }
+}
+
+impl TestTimer for CustomVTable<'_> {
+    fn vtable(&self) -> *const TimerVTable {
+        self.fields.as_ptr().cast()
+    }
+
+    fn user_data(&self) -> *mut c_void {
+        self.timer.user_data()
+    }
+
+    fn begin_step(&self) {
+        self.timer.begin_step();
+    }
+}
@@ -0,0 +1,25 @@
+;; Sets the game time to 1.5 seconds, pauses and resumes the game time, sets
+;; a variable and prints a message on every step.
+(module
+  (import "env" "timer_set_game_time" (func $set_game_time (param i64 i32)))
+  (import "env" "timer_pause_game_time" (func $pause_game_time))
+  (import "env" "timer_resume_game_time" (func $resume_game_time))
+  (import "env" "timer_set_variable"
+    (func $set_variable (param i32 i32 i32 i32)))
+  (import "env" "runtime_print_message" (func $print_message (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "keyvaluehello")
+  (func (export "update")
+    i64.const 1
+    i32.const 500000000
+    call $set_game_time
+    call $pause_game_time
+    call $resume_game_time
+    i32.const 0
+    i32.const 3
+    i32.const 3
+    i32.const 5
+    call $set_variable
+    i32.const 8
+    i32.const 5
+    call $print_message))
@@ -0,0 +1,7 @@
+;; Never finishes its first step.
+(module
+  (memory (export "memory") 1)
+  (func (export "update")
+    loop
+      br 0
+    end))
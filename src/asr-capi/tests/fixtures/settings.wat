@@ -0,0 +1,31 @@
+;; Registers a title and a bool setting on the first step.
+(module
+  (import "env" "user_settings_add_bool"
+    (func $add_bool (param i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "user_settings_add_title"
+    (func $add_title (param i32 i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "titleTitleflagFlag")
+  (func (export "update")
+    ;; Whether the settings are registered is stored at address 32.
+    i32.const 32
+    i32.load
+    i32.eqz
+    if
+      i32.const 0
+      i32.const 5
+      i32.const 5
+      i32.const 5
+      i32.const 0
+      call $add_title
+      i32.const 10
+      i32.const 4
+      i32.const 14
+      i32.const 4
+      i32.const 1
+      call $add_bool
+      drop
+      i32.const 32
+      i32.const 1
+      i32.store
+    end))
@@ -0,0 +1,14 @@
+;; Starts the timer if it isn't running and splits on every step otherwise.
+(module
+  (import "env" "timer_get_state" (func $get_state (result i32)))
+  (import "env" "timer_start" (func $start))
+  (import "env" "timer_split" (func $split))
+  (memory (export "memory") 1)
+  (func (export "update")
+    call $get_state
+    i32.eqz
+    if
+      call $start
+    else
+      call $split
+    end))
@@ -0,0 +1,5 @@
+;; Traps on every step.
+(module
+  (memory (export "memory") 1)
+  (func (export "update")
+    unreachable))
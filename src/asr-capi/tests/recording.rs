@@ -0,0 +1,92 @@
+mod common;
+
+use asr_capi::{Interaction, MockTimer, Recording, Replay, TimerEvent};
+use common::TestRuntime;
+use std::{env, fs, path::PathBuf, process};
+
+fn recording_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("asr-capi-{}-{name}.asrrec", process::id()))
+}
+
+/// Records the steps of the fixture provided, after running the amount of
+/// unrecorded steps provided.
+fn record(name: &str, fixture: &str, unrecorded: usize, recorded: usize) -> Recording {
+    let path = recording_path(name);
+    let timer = MockTimer::new(3);
+    let runtime = TestRuntime::load(fixture, &timer).unwrap();
+
+    runtime.steps(unrecorded).unwrap();
+    assert!(runtime.start_recording(&path));
+    runtime.steps(recorded).unwrap();
+    assert!(runtime.stop_recording());
+    drop(runtime);
+
+    let recording = Recording::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    recording
+}
+
+#[test]
+fn records_queries_and_actions() {
+    let recording = record("records", "start_split", 1, 3);
+
+    assert_eq!((recording.first_tick, recording.last_tick), (2, 4));
+    assert_eq!(
+        recording
+            .entries
+            .iter()
+            .map(|entry| (entry.tick, entry.interaction.clone()))
+            .collect::<Vec<_>>(),
+        [
+            (2, Interaction::State(1)),
+            (2, Interaction::Action(TimerEvent::Split)),
+            (3, Interaction::State(1)),
+            (3, Interaction::Action(TimerEvent::Split)),
+            (4, Interaction::State(1)),
+            (4, Interaction::Action(TimerEvent::Split)),
+        ],
+    );
+    assert!(recording
+        .entries
+        .windows(2)
+        .all(|entries| entries[0].wall_time <= entries[1].wall_time));
+}
+
+#[test]
+fn replays_without_divergences() {
+    let replay = Replay::new(record("matching", "start_split", 0, 5));
+    let runtime = TestRuntime::load("start_split", &replay).unwrap();
+
+    runtime.steps(5).unwrap();
+
+    assert_eq!(replay.begin_step(), None);
+    assert_eq!(replay.divergences(), []);
+}
+
+#[test]
+fn reports_divergences() {
+    let replay = Replay::new(record("diverging", "start_split", 0, 2));
+    let runtime = TestRuntime::load("game_time", &replay).unwrap();
+
+    runtime.steps(2).unwrap();
+
+    let divergences = replay.divergences();
+    assert_eq!(divergences.len(), 2);
+    assert_eq!(divergences[0].tick, 1);
+    assert_eq!(divergences[0].expected, [TimerEvent::Start]);
+    assert_eq!(
+        divergences[0].actual,
+        [
+            TimerEvent::SetGameTime(15_000_000),
+            TimerEvent::PauseGameTime,
+            TimerEvent::ResumeGameTime,
+            TimerEvent::SetVariable("key".into(), "value".into()),
+        ],
+    );
+    assert_eq!(divergences[1].expected, [TimerEvent::Split]);
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(Recording::decode(b"not a recording").is_err());
+}
@@ -0,0 +1,380 @@
+mod common;
+
+use asr_capi::{ErrorKind, MockTimer, MockTimerState, TimerAction, TimerEvent, TimerVTable};
+use common::{CustomVTable, TestConfig, TestRuntime, ERROR, INFO, RUNTIME_SOURCE};
+use std::{
+    mem,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn starts_and_splits_until_the_run_ends() {
+    let timer = MockTimer::new(3);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    runtime.steps(5).unwrap();
+
+    assert_eq!(
+        timer.events(),
+        [
+            (1, TimerEvent::Start),
+            (2, TimerEvent::Split),
+            (3, TimerEvent::Split),
+            (4, TimerEvent::Split),
+        ],
+    );
+    assert_eq!(timer.state(), MockTimerState::Ended);
+    assert_eq!(timer.split_index(), Some(3));
+    assert_eq!(timer.segment_splitted(2), Some(true));
+}
+
+#[test]
+fn follows_actions_of_the_runner() {
+    let timer = MockTimer::new(10);
+    timer.schedule(3, TimerAction::Reset);
+    timer.schedule(5, TimerAction::SkipSplit);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    runtime.steps(5).unwrap();
+
+    assert_eq!(
+        timer.event_list(),
+        [
+            TimerEvent::Start,
+            TimerEvent::Split,
+            TimerEvent::Start,
+            TimerEvent::Split,
+            TimerEvent::Split,
+        ],
+    );
+    assert_eq!(timer.split_index(), Some(3));
+    assert_eq!(timer.segment_splitted(1), Some(false));
+}
+
+#[test]
+fn doesnt_split_while_paused() {
+    let timer = MockTimer::new(10);
+    timer.schedule(2, TimerAction::Pause);
+    timer.schedule(4, TimerAction::Resume);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    runtime.steps(4).unwrap();
+
+    assert_eq!(
+        timer.events(),
+        [(1, TimerEvent::Start), (4, TimerEvent::Split)],
+    );
+}
+
+#[test]
+fn controls_game_time_and_variables() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("game_time", &timer).unwrap();
+
+    runtime.step().unwrap();
+
+    assert_eq!(
+        timer.event_list(),
+        [
+            TimerEvent::SetGameTime(15_000_000),
+            TimerEvent::PauseGameTime,
+            TimerEvent::ResumeGameTime,
+            TimerEvent::SetVariable("key".into(), "value".into()),
+            TimerEvent::Log("hello".into()),
+        ],
+    );
+    assert_eq!(timer.game_time(), Some(15_000_000));
+    assert!(!timer.is_game_time_paused());
+    assert_eq!(timer.variable("key").as_deref(), Some("value"));
+}
+
+#[test]
+fn reports_traps() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("trap", &timer).unwrap();
+
+    assert_eq!(runtime.step(), Err(ErrorKind::Trap));
+    assert!(!runtime.was_interrupted());
+    assert!(!timer.runtime_logs().is_empty());
+
+    // The auto splitter keeps running after a trap.
+    assert_eq!(runtime.step(), Err(ErrorKind::Trap));
+}
+
+#[test]
+fn collects_step_statistics() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("trap", &timer).unwrap();
+
+    assert_eq!(runtime.step(), Err(ErrorKind::Trap));
+    assert_eq!(runtime.step(), Err(ErrorKind::Trap));
+
+    assert_eq!(runtime.stats_steps(), 2);
+    assert_eq!(runtime.stats_failures(), 2);
+    assert!(runtime.stats_max() >= runtime.stats_mean());
+
+    runtime.stats_reset();
+    assert_eq!(runtime.stats_steps(), 0);
+    assert_eq!(runtime.stats_max(), 0);
+}
+
+#[test]
+fn interrupts_steps_exceeding_the_budget() {
+    const TICKS_PER_MILLI: u64 = 10_000;
+
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("infinite_loop", &timer).unwrap();
+    runtime.set_step_budget(100 * TICKS_PER_MILLI);
+
+    assert_eq!(runtime.step(), Err(ErrorKind::Interrupted));
+    assert!(runtime.was_interrupted());
+}
+
+#[test]
+fn interrupts_steps_from_another_thread() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("infinite_loop", &timer).unwrap();
+    let stepped = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let handle = runtime.interrupt_handle();
+        let stepped = &stepped;
+        // Keep interrupting, in case the step didn't start yet.
+        scope.spawn(move || {
+            while !stepped.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(20));
+                handle.interrupt();
+            }
+        });
+        let result = runtime.step();
+        stepped.store(true, Ordering::Relaxed);
+        assert_eq!(result, Err(ErrorKind::Interrupted));
+    });
+    assert!(runtime.was_interrupted());
+}
+
+#[test]
+fn ignores_interrupts_in_between_steps() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("trap", &timer).unwrap();
+
+    runtime.interrupt();
+    assert_eq!(runtime.step(), Err(ErrorKind::Trap));
+    assert!(!runtime.was_interrupted());
+}
+
+#[test]
+fn reports_missing_files() {
+    let timer = MockTimer::new(1);
+    assert_eq!(
+        TestRuntime::load("missing", &timer).err(),
+        Some(ErrorKind::FileRead),
+    );
+}
+
+#[test]
+fn reports_invalid_modules() {
+    let timer = MockTimer::new(1);
+    assert_eq!(
+        TestRuntime::from_bytes(b"not a module", &timer).err(),
+        Some(ErrorKind::Compile),
+    );
+}
+
+#[test]
+fn reloads_the_auto_splitter() {
+    let timer = MockTimer::new(3);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+    runtime.step().unwrap();
+
+    let module = std::fs::read(common::fixture_path("game_time")).unwrap();
+    runtime.reload_from_bytes(&module).unwrap();
+    runtime.step().unwrap();
+    assert_eq!(timer.events()[1], (2, TimerEvent::SetGameTime(15_000_000)));
+
+    // The previous auto splitter keeps running if the module is invalid.
+    assert_eq!(
+        runtime.reload_from_bytes(b"not a module"),
+        Err(ErrorKind::Compile)
+    );
+    runtime.reload().unwrap();
+    runtime.step().unwrap();
+    assert_eq!(timer.events().last(), Some(&(3, TimerEvent::Split)));
+}
+
+#[test]
+fn only_reloads_from_files() {
+    let timer = MockTimer::new(1);
+    let module = std::fs::read(common::fixture_path("start_split")).unwrap();
+    let runtime = TestRuntime::from_bytes(&module, &timer).unwrap();
+
+    assert_eq!(runtime.reload(), Err(ErrorKind::InvalidInput));
+}
+
+#[test]
+fn loads_with_a_config() {
+    let mut config = TestConfig::new();
+    config.set_debug_info(true);
+    config.set_optimize(false);
+    config.set_backtrace_details(true);
+
+    let timer = MockTimer::new(3);
+    let runtime = TestRuntime::load_with_config("start_split", &config, &timer).unwrap();
+    runtime.step().unwrap();
+    assert_eq!(timer.event_list(), [TimerEvent::Start]);
+
+    // The configuration can be reused for further runtimes.
+    let module = std::fs::read(common::fixture_path("start_split")).unwrap();
+    let other_timer = MockTimer::new(3);
+    let other = TestRuntime::from_bytes_with_config(&module, &config, &other_timer).unwrap();
+    other.step().unwrap();
+    assert_eq!(other_timer.event_list(), [TimerEvent::Start]);
+
+    assert_eq!(
+        TestRuntime::from_bytes_with_config(b"not a module", &config, &timer).err(),
+        Some(ErrorKind::Compile),
+    );
+    assert_eq!(
+        TestRuntime::load_with_config("missing", &config, &timer).err(),
+        Some(ErrorKind::FileRead),
+    );
+}
+
+#[test]
+fn logs_errors_with_their_level() {
+    let timer = MockTimer::new(1);
+    assert!(TestRuntime::load("missing", &timer).is_err());
+
+    let logs = timer.runtime_logs();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].0, ERROR);
+    assert!(logs[0].1.contains("Failed reading the file"));
+}
+
+#[test]
+fn drops_logs_below_the_log_level() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+    let module = std::fs::read(common::fixture_path("start_split")).unwrap();
+
+    runtime.reload_from_bytes(&module).unwrap();
+    assert_eq!(
+        timer.runtime_logs().last().map(|(level, _)| *level),
+        Some(INFO)
+    );
+
+    runtime.set_log_level(ERROR);
+    let len = timer.runtime_logs().len();
+    runtime.reload_from_bytes(&module).unwrap();
+    assert_eq!(timer.runtime_logs().len(), len);
+    assert_eq!(
+        runtime.reload_from_bytes(b"not a module"),
+        Err(ErrorKind::Compile)
+    );
+    assert_eq!(timer.runtime_logs().len(), len + 1);
+    assert_eq!(timer.runtime_logs()[len].0, ERROR);
+}
+
+#[test]
+fn falls_back_to_the_unleveled_log_callback() {
+    let timer = MockTimer::new(1);
+    assert!(TestRuntime::load(
+        "missing",
+        &CustomVTable::new(&timer).with_size(mem::offset_of!(TimerVTable, log_leveled))
+    )
+    .is_err());
+
+    // The mock timer treats unleveled messages as printed by the auto
+    // splitter.
+    assert!(timer.runtime_logs().is_empty());
+    match &*timer.event_list() {
+        [TimerEvent::Log(message)] => assert!(message.contains("Failed reading the file")),
+        events => panic!("Unexpected events {events:?}."),
+    }
+}
+
+#[test]
+fn rejects_invalid_vtables() {
+    let timer = MockTimer::new(1);
+    let module = std::fs::read(common::fixture_path("start_split")).unwrap();
+
+    for vtable in [
+        CustomVTable::new(&timer).with_null(mem::offset_of!(TimerVTable, split)),
+        CustomVTable::new(&timer).with_null(mem::offset_of!(TimerVTable, log)),
+        CustomVTable::new(&timer).with_size(mem::offset_of!(TimerVTable, log)),
+        CustomVTable::new(&timer).with_size(mem::offset_of!(TimerVTable, log_leveled) + 1),
+        CustomVTable::new(&timer).with_size(0),
+    ] {
+        assert_eq!(
+            TestRuntime::from_bytes(&module, &vtable).err(),
+            Some(ErrorKind::InvalidInput),
+        );
+    }
+
+    // Only the callbacks of the first version are required.
+    let vtable = CustomVTable::new(&timer).with_null(mem::offset_of!(TimerVTable, log_leveled));
+    assert!(TestRuntime::from_bytes(&module, &vtable).is_ok());
+}
+
+#[test]
+fn keeps_log_records_below_the_log_level() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+    let module = std::fs::read(common::fixture_path("start_split")).unwrap();
+    runtime.clear_logs();
+    runtime.set_log_level(ERROR);
+    let forwarded = timer.runtime_logs().len();
+
+    assert_eq!(
+        runtime.reload_from_bytes(b"not a module"),
+        Err(ErrorKind::Compile)
+    );
+    runtime.reload_from_bytes(&module).unwrap();
+
+    let logs = runtime.logs();
+    assert_eq!(logs.len(), 2);
+    assert_eq!((logs[0].level, logs[0].source), (ERROR, RUNTIME_SOURCE));
+    assert_eq!((logs[1].level, logs[1].source), (INFO, RUNTIME_SOURCE));
+    assert!(logs[0].timestamp > 0 && logs[0].timestamp <= logs[1].timestamp);
+    assert!(runtime.log(2).is_none());
+    // Only the error got forwarded to the timer.
+    assert_eq!(timer.runtime_logs().len(), forwarded + 1);
+
+    runtime.clear_logs();
+    assert!(runtime.logs().is_empty());
+}
+
+#[test]
+fn steps_on_its_own_thread() {
+    let timer = MockTimer::new(3);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    assert!(runtime.start_thread());
+    assert!(!runtime.start_thread());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while timer.state() != MockTimerState::Ended {
+        assert!(Instant::now() < deadline, "The run didn't end in time.");
+        thread::sleep(Duration::from_millis(10));
+    }
+    runtime.stop_thread();
+
+    assert_eq!(
+        timer.event_list(),
+        [
+            TimerEvent::Start,
+            TimerEvent::Split,
+            TimerEvent::Split,
+            TimerEvent::Split,
+        ],
+    );
+    let steps = runtime.stats_steps();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(runtime.stats_steps(), steps);
+
+    // The thread can be started again and is stopped when the runtime is
+    // dropped.
+    assert!(runtime.start_thread());
+    drop(runtime);
+}
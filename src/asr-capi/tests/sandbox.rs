@@ -0,0 +1,127 @@
+//! The helper processes are looked up through `/proc`.
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use asr_capi::*;
+use common::*;
+use std::{
+    ffi::{c_void, CString},
+    fs, mem,
+    process::Command,
+    ptr,
+    sync::{
+        atomic::{self, AtomicPtr, AtomicUsize},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+fn use_the_built_helper() {
+    let path = CString::new(env!("CARGO_BIN_EXE_asr-helper")).unwrap();
+    assert!(unsafe { Runtime_set_helper_path(path.as_ptr().cast()) });
+}
+
+/// Held by the tests, so they only see their own helper processes.
+static HELPERS: Mutex<()> = Mutex::new(());
+
+/// Returns the ids of the running helper processes spawned by this process.
+fn helper_pids() -> Vec<u32> {
+    let parent = std::process::id();
+    let mut pids = Vec::new();
+    for entry in fs::read_dir("/proc").unwrap() {
+        let Ok(stat) = fs::read_to_string(entry.unwrap().path().join("stat")) else {
+            continue;
+        };
+        // The name is in parentheses and followed by the state and the id of
+        // the parent process.
+        let (Some(pid), Some((name, rest))) = (
+            stat.split(' ').next().and_then(|pid| pid.parse().ok()),
+            stat.split_once(" (")
+                .and_then(|(_, rest)| rest.rsplit_once(") ")),
+        ) else {
+            continue;
+        };
+        let mut rest = rest.split(' ');
+        let (state, ppid) = (rest.next(), rest.next());
+        if name == "asr-helper" && state != Some("Z") && ppid == Some(&parent.to_string()) {
+            pids.push(pid);
+        }
+    }
+    pids
+}
+
+#[test]
+fn restarts_the_helper_when_it_gets_killed() {
+    let _helpers = HELPERS.lock().unwrap();
+    use_the_built_helper();
+    let timer = MockTimer::new(3);
+    let mut config = TestConfig::new();
+    config.set_sandboxed(true);
+    let runtime = TestRuntime::load_with_config("start_split", &config, &timer).unwrap();
+
+    runtime.step().unwrap();
+    let pids = helper_pids();
+    assert_eq!(pids.len(), 1);
+    assert!(Command::new("kill")
+        .args(["-9", &pids[0].to_string()])
+        .status()
+        .unwrap()
+        .success());
+
+    // The step that notices the helper being gone fails, while the auto
+    // splitter gets loaded in a new helper.
+    assert_eq!(runtime.step(), Err(ErrorKind::Helper));
+    let restarted = helper_pids();
+    assert_eq!(restarted.len(), 1);
+    assert_ne!(restarted, pids);
+
+    runtime.step().unwrap();
+    assert_eq!(
+        timer.events(),
+        [(1, TimerEvent::Start), (3, TimerEvent::Split)],
+    );
+}
+
+/// The runtime that `slow_state` reads the settings of.
+static RUNTIME: AtomicPtr<Runtime> = AtomicPtr::new(ptr::null_mut());
+static SETTINGS_READ: AtomicUsize = AtomicUsize::new(0);
+
+/// Takes longer than the step budget and the time the helper gets on top of it
+/// to respond, while reading the settings of the runtime.
+unsafe extern "C" fn slow_state(user_data: *mut c_void) -> i32 {
+    if let Some(runtime) = RUNTIME.load(atomic::Ordering::Relaxed).as_ref() {
+        Runtime_get_settings_map(runtime);
+        SETTINGS_READ.fetch_add(1, atomic::Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(1500));
+    }
+    (MockTimer::vtable().state)(user_data)
+}
+
+#[test]
+fn lets_slow_timer_callbacks_call_back_into_the_runtime() {
+    let _helpers = HELPERS.lock().unwrap();
+    use_the_built_helper();
+    let timer = MockTimer::new(3);
+    let vtable = CustomVTable::new(&timer).with_callback(
+        mem::offset_of!(TimerVTable, state),
+        slow_state as *const c_void,
+    );
+    let mut config = TestConfig::new();
+    config.set_sandboxed(true);
+    let runtime = TestRuntime::load_with_config("start_split", &config, &vtable).unwrap();
+    // 10 milliseconds.
+    runtime.set_step_budget(100_000);
+    runtime.step().unwrap();
+    let pids = helper_pids();
+
+    RUNTIME.store(runtime.as_ptr().cast_mut(), atomic::Ordering::Relaxed);
+    // The auto splitter may get interrupted for exceeding the step budget, but
+    // the helper isn't considered hung while the host handles the callback.
+    assert_ne!(runtime.step(), Err(ErrorKind::Helper));
+    RUNTIME.store(ptr::null_mut(), atomic::Ordering::Relaxed);
+
+    assert_ne!(SETTINGS_READ.load(atomic::Ordering::Relaxed), 0);
+    assert_eq!(helper_pids(), pids);
+}
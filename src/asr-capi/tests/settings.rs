@@ -0,0 +1,571 @@
+mod common;
+
+use asr_capi::*;
+use common::*;
+
+fn routes() -> TestMap {
+    let mut map = TestMap::new();
+    map.insert(
+        "routes",
+        &Value::List(vec![
+            Value::map([("name", Value::string("Any%")), ("splits", Value::I64(5))]),
+            Value::map([("name", Value::string("100%")), ("splits", Value::I64(12))]),
+        ]),
+    );
+    map.insert("enabled", &Value::Bool(true));
+    map
+}
+
+#[test]
+fn removes_keys_keeping_the_order() {
+    let mut map = TestMap::new();
+    map.insert("a", &Value::I64(1));
+    map.insert("b", &Value::I64(2));
+    map.insert("c", &Value::I64(3));
+
+    assert!(map.remove("b"));
+    assert!(!map.remove("b"));
+    assert_eq!(map.keys(), ["a", "c"]);
+    assert_eq!(map.get("c"), Some(Value::I64(3)));
+}
+
+#[test]
+fn reports_getters_called_with_the_wrong_type_or_index() {
+    let map = routes();
+
+    let enabled = SettingsMap_get_value(&map, 1).unwrap();
+    assert_eq!(
+        with_last_error(|| SettingValue_get_bool(enabled)),
+        (true, None)
+    );
+    assert_eq!(
+        with_last_error(|| SettingValue_get_i64(enabled)),
+        (0, Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| SettingValue_get_f64(enabled)),
+        (0.0, Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(SettingValue_get_string(enabled)) }),
+        (String::new(), Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| SettingValue_get_map(enabled).is_none()),
+        (true, Some(ErrorKind::SettingTypeMismatch)),
+    );
+
+    let list = SettingValue_get_list(SettingsMap_get_value(&map, 0).unwrap()).unwrap();
+    assert_eq!(
+        with_last_error(|| SettingValue_get_bool(SettingsList_get(list, 0).unwrap())),
+        (false, Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| SettingsList_get(list, 2).is_none()),
+        (true, Some(ErrorKind::SettingIndexOutOfRange)),
+    );
+    assert_eq!(
+        with_last_error(|| SettingsMap_get_value(&map, 2).is_none()),
+        (true, Some(ErrorKind::SettingIndexOutOfRange)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(SettingsMap_get_key(&map, usize::MAX)) }),
+        (String::new(), Some(ErrorKind::SettingIndexOutOfRange)),
+    );
+
+    // The map is still intact afterwards.
+    assert_eq!(map.entries(), routes().entries());
+}
+
+#[test]
+fn reports_widget_getters_called_with_the_wrong_type_or_index() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("settings", &timer).unwrap();
+    runtime.step().unwrap();
+    let widgets = runtime.settings_widgets();
+    let map = runtime.settings_map();
+
+    assert_eq!(Widgets_len(&widgets), 2);
+    assert_eq!(with_last_error(|| Widgets_get_type(&widgets, 0)), (2, None));
+    assert_eq!(
+        with_last_error(|| Widgets_get_heading_level(&widgets, 0)),
+        (0, None)
+    );
+    assert_eq!(
+        with_last_error(|| Widgets_get_bool(&widgets, 0, &map)),
+        (false, Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| Widgets_get_bool(&widgets, 1, &map)),
+        (true, None),
+    );
+    assert_eq!(
+        with_last_error(|| Widgets_get_choice_options_len(&widgets, 1)),
+        (0, Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(Widgets_get_choice_option_key(&widgets, 1, 0)) }),
+        (String::new(), Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(Widgets_get_file_select_filter(&widgets, 1)) }),
+        (String::new(), Some(ErrorKind::SettingTypeMismatch)),
+    );
+    assert_eq!(
+        with_last_error(|| Widgets_get_type(&widgets, 2)),
+        (0, Some(ErrorKind::SettingIndexOutOfRange)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(Widgets_get_key(&widgets, 2)) }),
+        (String::new(), Some(ErrorKind::SettingIndexOutOfRange)),
+    );
+    assert_eq!(
+        with_last_error(|| unsafe { string(Widgets_get_key(&widgets, 1)) }),
+        ("flag".to_owned(), None),
+    );
+}
+
+#[test]
+fn edits_the_settings_of_a_running_auto_splitter() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    let mut nested = TestMap::new();
+    nested.insert("inner", &Value::I64(1));
+    assert!(runtime.set_i64("count", 3));
+    assert!(runtime.set_f64("ratio", 0.5));
+    assert!(runtime.set_map("nested", nested));
+    assert!(runtime.set_i64("count", 4));
+    assert!(runtime.remove_setting("ratio"));
+    assert!(runtime.remove_setting("missing"));
+
+    assert_eq!(
+        runtime.settings_map().entries(),
+        Value::map([
+            ("count", Value::I64(4)),
+            ("nested", Value::map([("inner", Value::I64(1))])),
+        ])
+        .into_entries(),
+    );
+}
+
+#[test]
+fn gets_nested_settings_by_path() {
+    let map = routes();
+
+    assert_eq!(map.get_path("routes/1/name"), Ok(Value::string("100%")));
+    assert_eq!(map.get_path("enabled"), Ok(Value::Bool(true)));
+    assert_eq!(
+        map.get_path("routes/0/time"),
+        Err(ErrorKind::SettingNotFound)
+    );
+    assert_eq!(
+        map.get_path("routes/2/name"),
+        Err(ErrorKind::SettingIndexOutOfRange)
+    );
+    assert_eq!(
+        map.get_path("routes/first"),
+        Err(ErrorKind::SettingTypeMismatch)
+    );
+    assert_eq!(
+        map.get_path("routes/+1/name"),
+        Err(ErrorKind::SettingTypeMismatch)
+    );
+    assert_eq!(
+        map.get_path("routes/01/name"),
+        Err(ErrorKind::SettingTypeMismatch)
+    );
+    assert_eq!(
+        map.get_path("enabled/value"),
+        Err(ErrorKind::SettingTypeMismatch)
+    );
+    assert_eq!(map.get_path("routes//name"), Err(ErrorKind::InvalidInput));
+}
+
+#[test]
+fn sets_nested_settings_by_path() {
+    let mut map = routes();
+
+    map.set_path("routes/0/splits", &Value::I64(6)).unwrap();
+    map.set_path("routes/1/time", &Value::F64(1.5)).unwrap();
+    assert_eq!(map.get_path("routes/0/splits"), Ok(Value::I64(6)));
+    assert_eq!(map.get_path("routes/1/time"), Ok(Value::F64(1.5)));
+    assert_eq!(map.get_path("routes/1/name"), Ok(Value::string("100%")));
+
+    assert_eq!(
+        map.set_path("routes/2/name", &Value::Bool(false)),
+        Err(ErrorKind::SettingIndexOutOfRange),
+    );
+    assert_eq!(
+        map.set_path("missing/name", &Value::Bool(false)),
+        Err(ErrorKind::SettingNotFound),
+    );
+    assert_eq!(
+        map.set_path("enabled/value", &Value::Bool(false)),
+        Err(ErrorKind::SettingTypeMismatch),
+    );
+    assert_eq!(map.keys(), ["routes", "enabled"]);
+}
+
+#[test]
+fn escapes_slashes_and_tildes_in_paths() {
+    let mut map = TestMap::new();
+    map.insert("a/b", &Value::I64(1));
+    map.insert("a", &Value::map([("b", Value::I64(2))]));
+    map.insert("~", &Value::I64(3));
+
+    assert_eq!(map.get_path("a~1b"), Ok(Value::I64(1)));
+    assert_eq!(map.get_path("a/b"), Ok(Value::I64(2)));
+    assert_eq!(map.get_path("~0"), Ok(Value::I64(3)));
+    assert_eq!(map.get_path("~"), Err(ErrorKind::InvalidInput));
+    assert_eq!(map.get_path("a~2b"), Err(ErrorKind::InvalidInput));
+
+    map.set_path("a~1b", &Value::I64(4)).unwrap();
+    assert_eq!(map.get_path("a~1b"), Ok(Value::I64(4)));
+    assert_eq!(map.get_path("a/b"), Ok(Value::I64(2)));
+}
+
+#[test]
+fn sets_settings_of_a_running_auto_splitter_by_path() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+    assert!(runtime.set_map("routes", routes()));
+
+    runtime
+        .set_path("routes/routes/1/name", &Value::string("Low%"))
+        .unwrap();
+    assert_eq!(
+        runtime.get_path("routes/routes/1/name"),
+        Ok(Value::string("Low%")),
+    );
+    assert_eq!(
+        runtime.set_path("routes/routes/5", &Value::I64(0)),
+        Err(ErrorKind::SettingIndexOutOfRange),
+    );
+}
+
+#[test]
+fn round_trips_settings_through_json() {
+    let entries = Value::map([
+        (
+            "splits",
+            Value::List(vec![Value::Bool(true), Value::Bool(false)]),
+        ),
+        ("count", Value::I64(i64::MIN)),
+        ("whole", Value::F64(3.0)),
+        ("huge", Value::F64(1e300)),
+        ("tiny", Value::F64(-0.1)),
+        ("name", Value::string("\"Any%\" ✓")),
+        ("empty", Value::map([])),
+        ("nested", Value::map([("list", Value::List(vec![]))])),
+    ])
+    .into_entries();
+    let map = TestMap::from_entries(&entries);
+
+    let json = map.to_json().unwrap();
+    assert_eq!(TestMap::from_json(&json).unwrap().entries(), entries);
+}
+
+#[test]
+fn distinguishes_integers_from_floats_in_json() {
+    let map = TestMap::from_json(r#"{"b": 1, "a": 1.0, "c": 1e2}"#).unwrap();
+    assert_eq!(
+        map.entries(),
+        Value::map([
+            ("b", Value::I64(1)),
+            ("a", Value::F64(1.0)),
+            ("c", Value::F64(100.0)),
+        ])
+        .into_entries(),
+    );
+    assert_eq!(map.to_json().unwrap(), r#"{"b":1,"a":1.0,"c":100.0}"#);
+
+    // Integers need to fit into an i64, unlike the same numbers written as
+    // floating point numbers.
+    let map = TestMap::from_json(
+        r#"{"min": -9223372036854775808, "max": 9223372036854775807, "1e20": 1e20, "\"100000000000000000000": 100000000000000000000.0}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        map.entries(),
+        Value::map([
+            ("min", Value::I64(i64::MIN)),
+            ("max", Value::I64(i64::MAX)),
+            ("1e20", Value::F64(1e20)),
+            ("\"100000000000000000000", Value::F64(1e20)),
+        ])
+        .into_entries(),
+    );
+}
+
+#[test]
+fn rejects_what_json_cant_represent() {
+    for json in [
+        "[]",
+        r#"{"a": null}"#,
+        r#"{"a": 9223372036854775808}"#,
+        r#"{"a": 18446744073709551615}"#,
+        r#"{"a": 100000000000000000000}"#,
+        r#"{"a": -9223372036854775809}"#,
+        r#"{"a": -100000000000000000000}"#,
+        r#"{"a": {"b": [1, 100000000000000000000]}}"#,
+        r#"{"a": "#,
+    ] {
+        assert_eq!(
+            TestMap::from_json(json).err(),
+            Some(ErrorKind::InvalidInput),
+            "{json}"
+        );
+    }
+
+    let mut map = TestMap::new();
+    map.insert("a", &Value::F64(f64::NAN));
+    assert_eq!(map.to_json(), Err(ErrorKind::InvalidInput));
+}
+
+/// The settings of the auto splitting runtime component, as LiveSplit saves
+/// them in a layout.
+const LAYOUT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Layout version="1.6.1">
+  <Components>
+    <Component>
+      <Path>LiveSplit.AutoSplittingRuntime.dll</Path>
+      <Settings>
+        <Version>1.0</Version>
+        <ScriptPath>C:\Splitters\game.wasm</ScriptPath>
+        <CustomSettings>
+          <Setting id="start" type="bool">True</Setting>
+          <Setting id="reset" type="bool">False</Setting>
+          <Setting id="category" type="string" value="Any% &quot;No Major Glitches&quot;" />
+          <Setting id="offset" type="i64">-150</Setting>
+          <Setting id="scale" type="f64">1.5</Setting>
+          <Setting id="splits" type="list">
+            <Setting type="map">
+              <Setting id="name" type="string" value="Chapter 1 &amp; 2" />
+              <Setting id="enabled" type="bool">True</Setting>
+            </Setting>
+            <Setting type="f64">1E+20</Setting>
+          </Setting>
+          <Setting id="empty" type="map" />
+        </CustomSettings>
+      </Settings>
+    </Component>
+  </Components>
+</Layout>"#;
+
+#[test]
+fn reads_settings_from_layouts() {
+    let map = TestMap::from_xml(LAYOUT).unwrap();
+    assert_eq!(
+        map.entries(),
+        Value::map([
+            ("start", Value::Bool(true)),
+            ("reset", Value::Bool(false)),
+            ("category", Value::string("Any% \"No Major Glitches\"")),
+            ("offset", Value::I64(-150)),
+            ("scale", Value::F64(1.5)),
+            (
+                "splits",
+                Value::List(vec![
+                    Value::map([
+                        ("name", Value::string("Chapter 1 & 2")),
+                        ("enabled", Value::Bool(true)),
+                    ]),
+                    Value::F64(1e20),
+                ]),
+            ),
+            ("empty", Value::map([])),
+        ])
+        .into_entries(),
+    );
+}
+
+#[test]
+fn writes_settings_like_livesplit() {
+    let map = TestMap::from_entries(
+        &Value::map([
+            ("start", Value::Bool(true)),
+            ("category", Value::string("<Any%>\n")),
+            ("offset", Value::I64(-150)),
+            (
+                "floats",
+                Value::List(vec![
+                    Value::F64(1.0),
+                    Value::F64(0.0001),
+                    Value::F64(0.00001),
+                    Value::F64(1e15),
+                    Value::F64(f64::NEG_INFINITY),
+                ]),
+            ),
+            ("empty", Value::List(vec![])),
+        ])
+        .into_entries(),
+    );
+    assert_eq!(
+        map.to_xml().unwrap(),
+        r#"<CustomSettings>
+  <Setting id="start" type="bool">True</Setting>
+  <Setting id="category" type="string" value="&lt;Any%&gt;&#xA;" />
+  <Setting id="offset" type="i64">-150</Setting>
+  <Setting id="floats" type="list">
+    <Setting type="f64">1</Setting>
+    <Setting type="f64">0.0001</Setting>
+    <Setting type="f64">1E-05</Setting>
+    <Setting type="f64">1E+15</Setting>
+    <Setting type="f64">-Infinity</Setting>
+  </Setting>
+  <Setting id="empty" type="list" />
+</CustomSettings>"#,
+    );
+    assert_eq!(TestMap::new().to_xml().unwrap(), "<CustomSettings />");
+}
+
+#[test]
+fn round_trips_settings_through_xml() {
+    let entries = Value::map([
+        (
+            "splits",
+            Value::List(vec![Value::Bool(true), Value::Bool(false)]),
+        ),
+        ("count", Value::I64(i64::MIN)),
+        ("huge", Value::F64(1e300)),
+        ("precise", Value::F64(0.1 + 0.2)),
+        ("name", Value::string("'Any%'\t&\r\n ✓")),
+        ("empty", Value::map([])),
+        ("nested", Value::map([("list", Value::List(vec![]))])),
+    ])
+    .into_entries();
+    let map = TestMap::from_entries(&entries);
+
+    let xml = map.to_xml().unwrap();
+    assert_eq!(TestMap::from_xml(&xml).unwrap().entries(), entries);
+}
+
+#[test]
+fn rejects_settings_livesplit_cant_read() {
+    for xml in [
+        "<Settings><Version>1.0</Version></Settings>",
+        r#"<CustomSettings><Setting type="bool">True</Setting></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a">True</Setting></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="u8">1</Setting></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="i64">1.5</Setting></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="bool">yes</Setting></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="string" /></CustomSettings>"#,
+        r#"<CustomSettings><Other id="a" type="bool">True</Other></CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="bool">True</CustomSettings>"#,
+        r#"<CustomSettings><Setting id="a" type="string" value="&nbsp;" /></CustomSettings>"#,
+    ] {
+        assert_eq!(
+            TestMap::from_xml(xml).err(),
+            Some(ErrorKind::InvalidInput),
+            "{xml}"
+        );
+    }
+
+    let mut map = TestMap::new();
+    map.insert("a", &Value::string("\u{1}"));
+    assert_eq!(map.to_xml(), Err(ErrorKind::InvalidInput));
+}
+
+#[test]
+fn diffs_nested_settings() {
+    let previous = routes();
+    let mut current = routes();
+    current
+        .set_path("routes/1/splits", &Value::I64(13))
+        .unwrap();
+    current
+        .set_path("routes/0", &Value::map([("name", Value::string("Any%"))]))
+        .unwrap();
+    current.remove("enabled");
+    current.insert("category", &Value::string("Glitchless"));
+
+    assert_eq!(
+        previous.diff(&current),
+        [
+            Change::new(REMOVED, "routes/0/splits", Some(Value::I64(5)), None),
+            Change::new(
+                CHANGED,
+                "routes/1/splits",
+                Some(Value::I64(12)),
+                Some(Value::I64(13)),
+            ),
+            Change::new(REMOVED, "enabled", Some(Value::Bool(true)), None),
+            Change::new(ADDED, "category", None, Some(Value::string("Glitchless"))),
+        ],
+    );
+    assert_eq!(previous.diff(&routes()), []);
+}
+
+#[test]
+fn diffs_lists_by_index_and_changed_types() {
+    let mut previous = TestMap::new();
+    previous.insert("list", &Value::List(vec![Value::I64(1), Value::I64(2)]));
+    previous.insert("value", &Value::I64(1));
+    previous.insert("nan", &Value::F64(f64::NAN));
+    let mut current = TestMap::new();
+    current.insert("list", &Value::List(vec![Value::I64(2)]));
+    current.insert("value", &Value::F64(1.0));
+    current.insert("nan", &Value::F64(f64::NAN));
+
+    assert_eq!(
+        previous.diff(&current),
+        [
+            Change::new(CHANGED, "list/0", Some(Value::I64(1)), Some(Value::I64(2))),
+            Change::new(REMOVED, "list/1", Some(Value::I64(2)), None),
+            Change::new(CHANGED, "value", Some(Value::I64(1)), Some(Value::F64(1.0)),),
+        ],
+    );
+    assert_eq!(
+        current.diff(&previous)[1],
+        Change::new(ADDED, "list/1", None, Some(Value::I64(2))),
+    );
+}
+
+#[test]
+fn escapes_slashes_in_the_keys_of_changes() {
+    let keys = || {
+        let mut map = TestMap::new();
+        map.insert("a/b", &Value::I64(1));
+        map.insert("a", &Value::map([("b", Value::I64(2))]));
+        map
+    };
+    let current = keys();
+
+    let changes = TestMap::new().diff(&current);
+    assert_eq!(
+        changes,
+        [
+            Change::new(ADDED, "a~1b", None, Some(Value::I64(1))),
+            Change::new(ADDED, "a", None, Some(Value::map([("b", Value::I64(2))]))),
+        ],
+    );
+    assert_eq!(current.get_path(&changes[0].path), Ok(Value::I64(1)));
+
+    let mut changed = keys();
+    changed.set_path("a/b", &Value::I64(3)).unwrap();
+    assert_eq!(
+        current.diff(&changed),
+        [Change::new(
+            CHANGED,
+            "a/b",
+            Some(Value::I64(2)),
+            Some(Value::I64(3))
+        )],
+    );
+}
+
+#[test]
+fn diffs_the_settings_of_a_running_auto_splitter() {
+    let timer = MockTimer::new(1);
+    let runtime = TestRuntime::load("start_split", &timer).unwrap();
+
+    let previous = runtime.settings_map();
+    assert_eq!(runtime.diff_settings(&previous), []);
+
+    assert!(runtime.set_i64("count", 3));
+    assert_eq!(
+        runtime.diff_settings(&previous),
+        [Change::new(ADDED, "count", None, Some(Value::I64(3)))],
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use asr_capi::*;
+use common::*;
+
+const INVALID_UTF8: &[u8] = b"f\xffo\0";
+
+#[test]
+fn rejects_strings_that_are_not_utf8() {
+    let mut map = TestMap::new();
+    let key = INVALID_UTF8.as_ptr();
+
+    unsafe {
+        assert_eq!(
+            with_last_error(|| SettingsMap_insert(&mut map, key, SettingValue_new_i64(1))),
+            (false, Some(ErrorKind::InvalidInput)),
+        );
+        assert_eq!(SettingsMap_len(&map), 0);
+        assert_eq!(
+            with_last_error(|| SettingsMap_get_value_by_key(&map, key).is_none()),
+            (true, Some(ErrorKind::InvalidInput)),
+        );
+        assert_eq!(
+            with_last_error(|| SettingsMap_get_value_by_path(&map, key).is_none()),
+            (true, Some(ErrorKind::InvalidInput)),
+        );
+        assert_eq!(
+            with_last_error(|| SettingValue_new_string(key).is_none()),
+            (true, Some(ErrorKind::InvalidInput)),
+        );
+        assert_eq!(
+            with_last_error(|| string(path_to_wasi(key))),
+            (String::new(), Some(ErrorKind::InvalidInput)),
+        );
+        assert_eq!(
+            with_last_error(|| string(wasi_to_path(key))),
+            (String::new(), Some(ErrorKind::InvalidInput)),
+        );
+
+        // Valid strings are accepted again afterwards.
+        assert!(SettingValue_new_string(c"foo".as_ptr().cast()).is_some());
+    }
+}
+
+#[test]
+fn writes_strings_into_buffers_of_the_caller() {
+    let value = Value::string("äb").to_setting_value();
+
+    unsafe {
+        let mut buf = [0xff; 5];
+        assert_eq!(SettingValue_get_string_into(&value, buf.as_mut_ptr(), 5), 3);
+        assert_eq!(buf, *b"\xc3\xa4b\0\xff");
+
+        // The string fits exactly, including the nul-terminator.
+        let mut buf = [0xff; 4];
+        assert_eq!(SettingValue_get_string_into(&value, buf.as_mut_ptr(), 4), 3);
+        assert_eq!(buf, *b"\xc3\xa4b\0");
+
+        // Only the nul-terminator is written if the string doesn't fit.
+        let mut buf = [0xff; 3];
+        assert_eq!(SettingValue_get_string_into(&value, buf.as_mut_ptr(), 3), 3);
+        assert_eq!(buf, [0, 0xff, 0xff]);
+
+        // Nothing is written without any capacity, which allows querying the
+        // length.
+        let mut buf = [0xff];
+        assert_eq!(SettingValue_get_string_into(&value, buf.as_mut_ptr(), 0), 3);
+        assert_eq!(buf, [0xff]);
+        assert_eq!(
+            SettingValue_get_string_into(&value, std::ptr::null_mut(), 0),
+            3,
+        );
+    }
+}
+
+#[test]
+fn writes_utf16_strings_into_buffers_of_the_caller() {
+    // The emoji is encoded as a surrogate pair.
+    let value = Value::string("a😀").to_setting_value();
+
+    unsafe {
+        let mut buf = [0xffff; 4];
+        assert_eq!(
+            SettingValue_get_string_utf16(&value, buf.as_mut_ptr(), 4),
+            3
+        );
+        assert_eq!(buf, [0x61, 0xd83d, 0xde00, 0]);
+
+        // The surrogate pair is never split up.
+        let mut buf = [0xffff; 3];
+        assert_eq!(
+            SettingValue_get_string_utf16(&value, buf.as_mut_ptr(), 3),
+            3
+        );
+        assert_eq!(buf, [0, 0xffff, 0xffff]);
+
+        let mut buf = [0xffff];
+        assert_eq!(
+            SettingValue_get_string_utf16(&value, buf.as_mut_ptr(), 0),
+            3
+        );
+        assert_eq!(buf, [0xffff]);
+    }
+}